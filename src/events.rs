@@ -0,0 +1,41 @@
+use near_sdk::{log, serde_json, AccountId};
+
+// NEP-297 events logged by the challenge, so indexers and front-ends can
+// follow it without parsing free-form logs.
+// https://github.com/near/NEPs/blob/master/neps/nep-0297.md
+pub const EVENT_STANDARD: &str = "nft_challenge";
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+// Logs a NEP-297 event for this contract with `data` as its only entry.
+pub fn emit_event(event: &str, data: serde_json::Value) {
+    log!(
+        "EVENT_JSON:{}",
+        serde_json::json!({
+            "standard": EVENT_STANDARD,
+            "version": EVENT_STANDARD_VERSION,
+            "event": event,
+            "data": [data],
+        })
+    );
+}
+
+// Logged every time an account wins the challenge.
+pub fn emit_challenge_won(account_id: &AccountId, challenge_name: &str, winners_count: u64) {
+    emit_event(
+        "challenge_won",
+        serde_json::json!({
+            "account_id": account_id,
+            "challenge_name": challenge_name,
+            "winners_count": winners_count,
+        }),
+    );
+}
+
+// Logged once, when the challenge moves to completed. `reason` is one of
+// "expired", "winner_limit_reached" or "ended_by_owner".
+pub fn emit_challenge_completed(reason: &str) {
+    emit_event(
+        "challenge_completed",
+        serde_json::json!({ "reason": reason }),
+    );
+}
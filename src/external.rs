@@ -1,7 +1,7 @@
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
     ext_contract,
-    json_types::Base64VecU8,
+    json_types::{Base64VecU8, U128},
     serde::{Deserialize, Serialize},
     AccountId, PromiseOrValue,
 };
@@ -9,7 +9,9 @@ use near_sdk::{
 use std::collections::HashMap;
 
 // Mintbase's TokenMetadata structure.
-#[derive(Clone, Debug, Deserialize, Serialize, BorshDeserialize, BorshSerialize)]
+#[derive(
+    Clone, Debug, Default, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize,
+)]
 pub struct NFTTokenMetadata {
     /// The Title for this token. ex. "Arch Nemesis: Mail Carrier" or "Parcel 5055"
     pub title: Option<String>,
@@ -40,6 +42,60 @@ pub struct NFTTokenMetadata {
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TokenCompliant {
     pub token_id: String,
+    pub metadata: Option<TokenMetadataCompliant>,
+    pub approved_account_ids: Option<HashMap<AccountId, u64>>,
+}
+
+// The subset of the NEP-177 token metadata used to tell when a token was minted.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TokenMetadataCompliant {
+    /// When token was issued or minted, Unix epoch in milliseconds.
+    pub issued_at: Option<String>,
+    /// When token starts being valid, Unix epoch in milliseconds.
+    pub starts_at: Option<String>,
+}
+
+impl TokenCompliant {
+    // The time this token was minted at, in nano seconds, falling back to when
+    // it starts being valid for contracts that don't report an issue date.
+    // NEP-177 times are epoch milliseconds, so other formats, like the ISO 8601
+    // dates some contracts store instead, are skipped.
+    pub fn mint_time_ns(&self) -> Option<u64> {
+        let metadata = self.metadata.as_ref()?;
+        [&metadata.issued_at, &metadata.starts_at]
+            .into_iter()
+            .flatten()
+            .find_map(|time| time.parse::<u64>().ok())
+            .map(|time_ms| time_ms.saturating_mul(1_000_000))
+    }
+
+    // Whether `account_id` is approved to transfer this token.
+    pub fn is_approved_for(&self, account_id: &AccountId) -> bool {
+        self.approved_account_ids
+            .as_ref()
+            .map_or(false, |approvals| approvals.contains_key(account_id))
+    }
+}
+
+// The subset of the NEP-177 contract metadata used to identify a collection.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NFTContractMetadataCompliant {
+    pub name: String,
+}
+
+// The base challenge config served by a template contract's `get_config`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct TemplateConfig {
+    pub name: String,
+    pub description: String,
+    pub media_link: String,
+    pub reward_nft_id: String,
+    pub challenge_nft_ids: Vec<String>,
+    pub burn_challenge_piece_on_claim: Vec<bool>,
+    pub expiration_date_in_ns: u64,
+    pub winner_limit: u64,
+    pub creator_can_update: bool,
+    pub reward_nft_metadata: NFTTokenMetadata,
 }
 
 pub type SplitBetweenUnparsed = HashMap<AccountId, u32>;
@@ -50,10 +106,24 @@ pub struct RoyaltyArgs {
     pub percentage: u32,
 }
 
+// Mintbase's storage costs, in yoctoNEAR.
+#[derive(Deserialize, Serialize)]
+pub struct StorageCosts {
+    pub storage_price_per_byte: U128,
+    pub common: U128,
+    pub token: U128,
+}
+
 #[ext_contract(mintbase_nft)]
 pub trait MintbaseNft {
     fn check_is_minter(&self, account_id: near_sdk::AccountId) -> bool;
 
+    fn get_storage_costs(&self) -> StorageCosts;
+
+    fn get_owner_id(&self) -> AccountId;
+
+    fn nft_metadata(&self) -> NFTContractMetadataCompliant;
+
     fn nft_tokens_for_owner(
         &self,
         account_id: AccountId,
@@ -70,19 +140,31 @@ pub trait MintbaseNft {
         split_owners: Option<SplitBetweenUnparsed>,
     ) -> PromiseOrValue<()>;
 
-    fn nft_batch_burn(&mut self, token_ids: Vec<near_sdk::json_types::U64>);
+    fn nft_batch_burn(&mut self, token_ids: Vec<String>);
 
-    fn nft_approval_id(
-        &self,
-        token_id: near_sdk::json_types::U64,
-        account_id: AccountId,
-    ) -> Option<String>;
+    fn nft_approval_id(&self, token_id: String, account_id: AccountId) -> Option<String>;
 
     fn nft_transfer(
         &mut self,
         receiver_id: AccountId,
-        token_id: near_sdk::json_types::U64,
-        approval_id: u64,
+        token_id: String,
+        approval_id: Option<u64>,
         memo: Option<String>,
     );
 }
+
+// https://github.com/near/NEPs/blob/master/neps/nep-0141.md
+#[ext_contract(ft_contract)]
+pub trait FungibleToken {
+    fn ft_balance_of(&self, account_id: AccountId) -> U128;
+}
+
+#[ext_contract(challenge_template)]
+pub trait ChallengeTemplate {
+    fn get_config(&self) -> TemplateConfig;
+}
+
+#[ext_contract(achievements)]
+pub trait Achievements {
+    fn grant_achievement(&mut self, account_id: AccountId, challenge_hash: Base64VecU8);
+}
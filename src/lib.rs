@@ -1,14 +1,17 @@
 use std::process::{ExitCode, Termination};
 
 use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
     env,
-    json_types::U64,
+    json_types::{Base64VecU8, U128, U64},
     log, near,
     serde::{Deserialize, Serialize},
-    store::{LookupMap, LookupSet, Vector},
-    AccountId, Gas, NearToken, PanicOnDefault, Promise, PromiseResult,
+    store::{LookupMap, LookupSet, UnorderedMap, Vector},
+    AccountId, Gas, NearToken, PanicOnDefault, Promise, PromiseOrValue, PromiseResult,
 };
+mod events;
 pub mod external;
+use crate::events::*;
 pub use crate::external::*;
 
 impl Termination for Contract {
@@ -17,12 +20,528 @@ impl Termination for Contract {
     }
 }
 pub struct TransferTokenArgs {
-    token_id: U64,
+    token_id: String,
     approval_id: u64,
-    nft_id: String,
+    nft_id: AccountId,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize)]
+pub struct MutationRecord {
+    // The method that performed the mutation.
+    pub method: String,
+    // JSON encoding of the value before the mutation.
+    pub old_value: String,
+    // JSON encoding of the value after the mutation.
+    pub new_value: String,
+    // The block timestamp of the mutation, in nano seconds.
+    pub timestamp: u64,
+    // The account that performed the mutation.
+    pub caller: AccountId,
+}
+
+// The arguments of an `nft_approve` call granting this contract transfer
+// approval for a challenge piece.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ApprovalCall {
+    // The challenge piece contract to call `nft_approve` on.
+    pub contract: AccountId,
+    // The token to approve.
+    pub token_id: String,
+    // The account to approve, always this contract.
+    pub account_id: AccountId,
+}
+
+// A token on one of the challenge piece contracts.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize)]
+pub struct NftToken {
+    // The id of the NFT contract.
+    pub nft_id: AccountId,
+    // The id of the token on that contract.
+    pub token_id: String,
+}
+
+// What happens to a challenge piece when a winner claims the challenge.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize)]
+pub enum PieceAction {
+    // The winner keeps the piece.
+    Keep,
+    // The piece is transferred to this contract and burned.
+    Burn,
+    // The piece is transferred to the given account, e.g. a treasury.
+    TransferTo(AccountId),
+}
+
+// Messages shown in place of the defaults when a claim is rejected.
+#[derive(
+    Clone, Debug, Default, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize,
+)]
+pub struct CustomMessages {
+    // Shown when the account does not own every challenge piece.
+    pub not_owner: Option<String>,
+    // Shown when the account has already won the challenge.
+    pub already_won: Option<String>,
+    // Shown when the challenge has expired.
+    pub expired: Option<String>,
+    // Shown when every winner slot is taken.
+    pub full: Option<String>,
+}
+
+// Fields of a template config replaced by `new_from_template`. Unset fields
+// keep the template's value.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct TemplateOverrides {
+    pub name: Option<String>,
+    pub expiration_date_in_ns: Option<u64>,
+    pub winner_limit: Option<u64>,
+}
+
+// Everything a challenge is created with. Optional settings can be left
+// unset to keep their defaults.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct ChallengeConfig {
+    // The owner of this NFT Challenge.
+    pub owner_id: String,
+    // The name for this challenge.
+    pub name: String,
+    // Free-form description of this challenge.
+    pub description: String,
+    // URL to associated media, preferably to decentralized, content-addressed storage
+    pub media_link: String,
+    // The id of the reward NFT.
+    pub reward_nft_id: String,
+    // Ids of the challenge nfts that are part of this challenge.
+    pub challenge_nft_ids: Vec<String>,
+    // Whether to burn the challenge piece at the associated index when claiming.
+    pub burn_challenge_piece_on_claim: Vec<bool>,
+    // The expiration date of this challenge, expressed as a nano second timestamp.
+    pub expiration_date_in_ns: u64,
+    // Maximum number of winners for this challenge.
+    pub winner_limit: u64,
+    // Whether the creator of this challenge can update the challenge status.
+    pub creator_can_update: bool,
+    // Metadata for the reward token NFT.
+    pub reward_nft_metadata: NFTTokenMetadata,
+    // The action to take on the challenge piece at the associated index when
+    // claiming. Takes precedence over `burn_challenge_piece_on_claim`.
+    pub piece_actions: Option<Vec<PieceAction>>,
+    // Number of reward NFTs minted to each winner. Defaults to 1.
+    pub rewards_per_winner: Option<u64>,
+    // Metadata minted in place of `reward_nft_metadata` once revealed.
+    pub revealed_reward_metadata: Option<NFTTokenMetadata>,
+    // Fee charged for each claim attempt, refunded if the claim fails.
+    pub claim_fee: Option<NearToken>,
+    // Maximum number of pieces verified per `initiate_claim_stage` call.
+    pub max_pieces_per_claim_tx: Option<u32>,
+    // The earliest mint time, in nano seconds, accepted for the challenge
+    // piece at the associated index.
+    pub min_mint_time_ns: Option<Vec<Option<u64>>>,
+    // Whether the challenge ends once every winner has minted their rewards.
+    pub auto_end_on_full_mint: Option<bool>,
+    // Messages shown in place of the defaults when a claim is rejected.
+    pub custom_messages: Option<CustomMessages>,
+    // Maximum number of claims that can start in a single block.
+    pub max_claims_per_block: Option<u32>,
+    // Whether the challenge piece at the associated index is required or a bonus.
+    pub piece_roles: Option<Vec<PieceRole>>,
+    // Reward metadata for winners that own bonus pieces, by bonus piece count.
+    pub bonus_reward_metadata: Option<Vec<NFTTokenMetadata>>,
+    // Royalty on the reward NFT, in basis points, split between the owners of
+    // the challenge piece contracts.
+    pub royalty_from_piece_owners: Option<u32>,
+    // How long a reserved slot is held, in nano seconds.
+    pub reservation_ttl_ns: Option<u64>,
+    // The contract a soulbound badge is minted from for every winner.
+    pub winner_badge_nft_id: Option<AccountId>,
+    // How long winners have to mint their reward after winning, in nano seconds.
+    pub mint_deadline_ns_after_win: Option<u64>,
+    // How long a claim or reservation can stay in flight, in nano seconds.
+    pub claim_ttl_ns: Option<u64>,
+    // How long an account has to wait after a claim resolved before starting
+    // another one, in nano seconds.
+    pub claim_cooldown_ns: Option<u64>,
+    // When the challenge opens for claims, in nano seconds.
+    pub start_date_in_ns: Option<u64>,
+    // Accounts allowed to win the challenge. Everyone may take part when unset.
+    pub whitelist: Option<Vec<AccountId>>,
+    // Whether claims check approvals before taking a slot.
+    pub require_pre_approval: Option<bool>,
+    // Whether winners can only mint their reward to themselves or to accounts
+    // on the mint recipient allowlist.
+    pub restrict_mint_recipient: Option<bool>,
+    // Whether winners can claim the challenge again once they minted the
+    // reward of their last win.
+    pub allow_repeat_wins: Option<bool>,
+    // The fungible token contract and the balance of it an account must hold
+    // to claim.
+    pub ft_gate: Option<(AccountId, U128)>,
+    // The achievements contract every win is granted on.
+    pub achievements_contract: Option<AccountId>,
+    // Maximum number of claims each account can start.
+    pub max_attempts_per_account: Option<u32>,
+    // The contract and metadata of the consolation reward.
+    pub consolation_reward: Option<(AccountId, NFTTokenMetadata)>,
+    // How many of the required pieces an account must own to win. Defaults
+    // to all of them.
+    pub required_pieces: Option<u64>,
+    // How many tokens of the piece at the associated index a claim consumes.
+    pub burn_quantities: Option<Vec<u32>>,
+    // Human readable name of the piece at the associated index.
+    pub piece_labels: Option<Vec<String>>,
+    // The deposit `mint_nft` requires, in millinear, as an upper bound on the
+    // reward store's storage cost. Defaults to 54.
+    pub mint_deposit_in_millinear: Option<u64>,
+}
+
+// Why a challenge could not be created.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChallengeError {
+    InvalidOwnerId,
+    InvalidRewardNftId,
+    InvalidChallengeNftId { index: usize },
+    BurnFlagsLengthMismatch,
+    NoChallengePieces,
+    ExpirationInPast,
+    NoRewardsPerWinner,
+    NoPiecesPerClaimTx,
+    PieceActionsLengthMismatch,
+    MinMintTimesLengthMismatch,
+    PieceRolesLengthMismatch,
+    NoRequiredPieces,
+    RoyaltyPercentageTooHigh,
+    CustomMessageTooLong,
+    StartDateAfterExpiration,
+    MissingRewardMediaHash,
+    InvalidRequiredPieces,
+    BurnQuantitiesLengthMismatch,
+    InvalidBurnQuantity,
+    PieceLabelsLengthMismatch,
+}
+
+impl std::fmt::Display for ChallengeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChallengeError::InvalidOwnerId => write!(f, "Owner's account ID is invalid"),
+            ChallengeError::InvalidRewardNftId => {
+                write!(f, "Reward NFT contract's account ID is invalid")
+            }
+            ChallengeError::InvalidChallengeNftId { index } => write!(
+                f,
+                "Challenge NFT contract's account ID at index {} is invalid",
+                index
+            ),
+            ChallengeError::BurnFlagsLengthMismatch => write!(
+                f,
+                "The challenge nft ids and burn challenge piece on claim must be the same length"
+            ),
+            ChallengeError::NoChallengePieces => {
+                write!(f, "Challenge must have at least 1 challenge NFT")
+            }
+            ChallengeError::ExpirationInPast => {
+                write!(f, "Challenge expiration date must be in the future")
+            }
+            ChallengeError::NoRewardsPerWinner => {
+                write!(f, "Challenge must reward at least 1 NFT per winner")
+            }
+            ChallengeError::NoPiecesPerClaimTx => {
+                write!(
+                    f,
+                    "Staged claims must verify at least 1 piece per transaction"
+                )
+            }
+            ChallengeError::PieceActionsLengthMismatch => write!(
+                f,
+                "The challenge nft ids and piece actions must be the same length"
+            ),
+            ChallengeError::MinMintTimesLengthMismatch => write!(
+                f,
+                "The challenge nft ids and min mint times must be the same length"
+            ),
+            ChallengeError::PieceRolesLengthMismatch => write!(
+                f,
+                "The challenge nft ids and piece roles must be the same length"
+            ),
+            ChallengeError::NoRequiredPieces => {
+                write!(f, "Challenge must have at least 1 required challenge NFT")
+            }
+            ChallengeError::RoyaltyPercentageTooHigh => write!(
+                f,
+                "Reward royalties must be at most {} basis points",
+                ROYALTY_BASIS_POINTS
+            ),
+            ChallengeError::CustomMessageTooLong => write!(
+                f,
+                "Custom messages must be at most {} bytes long",
+                MAX_CUSTOM_MESSAGE_LEN
+            ),
+            ChallengeError::StartDateAfterExpiration => {
+                write!(f, "Challenge start date must be before its expiration date")
+            }
+            ChallengeError::MissingRewardMediaHash => {
+                write!(f, "Reward NFT media hash is required when media is set")
+            }
+            ChallengeError::InvalidRequiredPieces => write!(
+                f,
+                "Required pieces must be between 1 and the number of required challenge NFTs"
+            ),
+            ChallengeError::BurnQuantitiesLengthMismatch => write!(
+                f,
+                "The challenge nft ids and burn quantities must be the same length"
+            ),
+            ChallengeError::InvalidBurnQuantity => write!(
+                f,
+                "Burn quantities must be at least 1, and exactly 1 for staged claims"
+            ),
+            ChallengeError::PieceLabelsLengthMismatch => write!(
+                f,
+                "The challenge nft ids and piece labels must be the same length"
+            ),
+        }
+    }
+}
+
+impl near_sdk::FunctionError for ChallengeError {
+    fn panic(&self) -> ! {
+        env::panic_str(&self.to_string())
+    }
+}
+
+// Everything that determines how a challenge piece is treated on claim.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize)]
+pub struct PieceSpec {
+    // The action to take on the piece when claiming.
+    pub action: PieceAction,
+    // Whether the piece is required or a bonus.
+    pub role: PieceRole,
+    // The earliest mint time, in nano seconds, accepted for the piece.
+    pub min_mint_time_ns: Option<u64>,
+    // How many tokens of the piece a claim burns or transfers. Kept pieces
+    // only need a single token.
+    pub burn_quantity: u32,
+    // Human readable name shown by clients instead of the piece's contract id.
+    pub label: Option<String>,
+}
+
+// A challenge piece as reported by `get_challenge_metadata`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize)]
+pub struct ChallengePiece {
+    // The index of the piece, used to refer to it in claim results and
+    // owner methods. Indices never change once the challenge is created.
+    pub index: u32,
+    // The id of the piece's nft contract.
+    pub nft_id: String,
+    pub spec: PieceSpec,
+}
+
+// The phase a challenge is in, derived from its time, counts and flags.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ChallengePhase {
+    // The start date has not been reached yet.
+    NotStarted,
+    // Claims are open and winner slots are available.
+    Active,
+    // Every winner slot is won or held by an in-flight claim.
+    Full,
+    // The expiration date has passed.
+    Expired,
+    // The challenge was marked as completed.
+    Completed,
+}
+
+// Whether a challenge piece is needed to win the challenge.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize)]
+pub enum PieceRole {
+    // The piece must be owned to win the challenge.
+    Required,
+    // The piece is optional, and owning it upgrades the reward. Staged claims
+    // verify bonus pieces like required ones.
+    Bonus,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Capacity {
+    // Maximum number of winners for this challenge.
+    pub limit: u64,
+    // Number of accounts that have won this challenge.
+    pub confirmed_winners: u64,
+    // Number of slots still available to new claims.
+    pub potential_left: u64,
+    // Number of claims currently going through the claim process.
+    pub inflight: u64,
+    // Set when the counts above do not add up to `limit`.
+    pub warning: Option<String>,
+}
+
+// The challenge's common views, all read at the same block.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Snapshot {
+    // The block the snapshot was taken at.
+    pub block_height: u64,
+    pub block_timestamp: u64,
+    pub metadata: ChallengeMetaData,
+    pub capacity: Capacity,
+    pub phase: ChallengePhase,
+    pub supported_standards: Vec<StandardPair>,
+    pub is_paused: bool,
+    pub is_minting_frozen: bool,
+    pub effective_completion: bool,
+    pub rewards_minted_count: u64,
+    pub claim_is_destructive: bool,
+    pub burn_transfer_deposit: NearToken,
+    pub claim_stage_count: u32,
+    pub has_stuck_tokens: bool,
+    pub self_check: Vec<String>,
+}
+
+// The final stats of a challenge, returned when its owner ends it.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct FinalReport {
+    // Number of accounts that won the challenge.
+    pub total_winners: u64,
+    // Number of claim attempts that resolved, won or not.
+    pub total_attempts: u64,
+    // Number of challenge pieces burned by winning claims.
+    pub pieces_burned: u64,
+    // The block timestamp the challenge ended at, in nano seconds.
+    pub ended_at_ns: U64,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct OwnerInfo {
+    // The owner of this NFT Challenge.
+    pub owner_id: String,
+    // The creator of this NFT Challenge.
+    pub creator_id: String,
+    // Whether the caller is the challenge owner.
+    pub is_owner: bool,
+    // Whether the caller is the challenge creator.
+    pub is_creator: bool,
+    // Whether the caller can update the completion status of the challenge.
+    pub can_update_completion: bool,
+}
+
+// An attestation that an account won this challenge. A verifier recomputes
+// `commitment` as the sha256 of `challenge_hash`, the account id bytes and the
+// little endian `won_at` timestamp, and compares it against the proof.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WinProof {
+    // The account that won the challenge.
+    pub account_id: AccountId,
+    // The block timestamp the account won at, in nano seconds.
+    pub won_at: u64,
+    // The sha256 of the challenge contract id followed by the challenge nft
+    // ids, each terminated by a newline.
+    pub challenge_hash: Base64VecU8,
+    // The sha256 commitment over the fields above.
+    pub commitment: Base64VecU8,
+}
+
+// A standard implemented by this contract, as reported by `supported_standards`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct StandardPair {
+    pub standard: String,
+    pub version: String,
+}
+
+// Outcomes recorded for a claim attempt.
+pub const CLAIM_OUTCOME_WON: u8 = 0;
+pub const CLAIM_OUTCOME_MISSING_PIECE: u8 = 1;
+pub const CLAIM_OUTCOME_MISSING_APPROVAL: u8 = 2;
+pub const CLAIM_OUTCOME_BURN_FAILED: u8 = 3;
+pub const CLAIM_OUTCOME_STALE: u8 = 4;
+pub const CLAIM_OUTCOME_BELOW_FT_GATE: u8 = 5;
+
+// Maximum number of claim attempts kept in the recent attempts log.
+const MAX_RECENT_ATTEMPTS: u32 = 200;
+// Maximum number of owner mutations kept in the mutation log.
+const MAX_MUTATION_RECORDS: u32 = 200;
+// Maximum length of a custom rejection message, in bytes.
+const MAX_CUSTOM_MESSAGE_LEN: usize = 256;
+// Number of nano seconds in a second.
+const NS_IN_SECOND: u64 = 1_000_000_000;
+// Mintbase royalties and splits are expressed out of this many basis points.
+const ROYALTY_BASIS_POINTS: u32 = 10_000;
+// Bytes Mintbase stores once per mint call, on top of the metadata.
+const MINT_COMMON_STORAGE_BYTES: u128 = 80;
+// Bytes Mintbase stores for every token minted.
+const MINT_TOKEN_STORAGE_BYTES: u128 = 360;
+// The deposit `mint_nft` requires when the challenge doesn't configure one,
+// in millinear.
+const DEFAULT_MINT_DEPOSIT_IN_MILLINEAR: u64 = 54;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize)]
+pub struct ClaimAttempt {
+    // The account that attempted to claim the challenge.
+    pub account: AccountId,
+    // The block timestamp at which the attempt resolved, in nano seconds.
+    pub ts: u64,
+    // One of the `CLAIM_OUTCOME_*` values.
+    pub outcome: u8,
+}
+
+// A winner slot held for an account until it completes its claim.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize)]
+pub struct Reservation {
+    // The deposit attached to reserve the slot, refunded once the reservation
+    // is completed or released.
+    pub deposit: NearToken,
+    // The block timestamp the reservation expires at, in nano seconds.
+    pub expires_at: u64,
+}
+
+// How a claim resolved, returned by the claim methods once every callback
+// has run. The claim callbacks still log the details.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ClaimResult {
+    // The account won the challenge.
+    Won,
+    // The account does not own the required piece at `index`.
+    MissingPiece { index: u32 },
+    // A piece burned or transferred on claim is not approved to this contract.
+    NeedsApproval,
+    // A piece could not be transferred, so the transferred pieces are handed
+    // back.
+    TransferFailed,
+    // A piece could not be burned, so the unburned pieces are handed back and
+    // the claim fails.
+    BurnFailed,
+    // The last winner slot was taken while the claim was being verified.
+    ChallengeFull,
+    // The account holds less than the fungible token balance required to
+    // claim.
+    BelowFtGate,
+    // A staged claim verified `stage`, and other stages are left to verify.
+    StageVerified { stage: u32 },
+    // The claim was released, e.g. by `sweep_stale_claims`, before its
+    // callbacks resolved, so it neither won nor failed.
+    Released,
+}
+
+// The result of an account's most recent claim, kept so it can be fetched
+// after the claim resolves.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize)]
+pub struct ClaimReceipt {
+    // Whether the claim won the challenge.
+    pub won: bool,
+    // One of the `CLAIM_OUTCOME_*` values.
+    pub outcome: u8,
+    // The block timestamp at which the claim resolved, in nano seconds.
+    pub resolved_at: u64,
+}
+
+// How many times an account has won the challenge, and when.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize)]
+pub struct WinnerInfo {
+    // Number of times the account has won.
+    pub count: u64,
+    // The block timestamp of the account's first win, in nano seconds.
+    pub first_won_at_ns: u64,
+    // The block timestamp of the account's latest win, in nano seconds.
+    pub last_won_at_ns: u64,
+}
+
+// Every per-piece vector is ordered by piece index, so the entries at the
+// same position across vectors always describe the same piece.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, BorshDeserialize, BorshSerialize)]
 pub struct ChallengeMetaData {
     // The owner of this NFT Challenge
     pub owner_id: String,
@@ -40,6 +559,8 @@ pub struct ChallengeMetaData {
     pub challenge_nft_ids: Vec<String>,
     // Whether to burn the challenge piece at the associated index when claiming.
     pub burn_challenge_piece_on_claim: Vec<bool>,
+    // The action to take on the challenge piece at the associated index when claiming.
+    pub piece_actions: Vec<PieceAction>,
     // The expiration date of this challenge, expressed as a nano second timestamp.
     pub expiration_date_in_ns: u64,
     // Maximum number of winners for this challenge.
@@ -50,6 +571,69 @@ pub struct ChallengeMetaData {
     pub challenge_completed: bool,
     // Whether the creator of this challenge can update the challenge status.
     pub creator_can_update: bool,
+    // Number of reward NFTs minted to each winner.
+    pub rewards_per_winner: u64,
+    // Whether the revealed reward metadata is used for minting.
+    pub revealed: bool,
+    // Maximum number of pieces verified per `initiate_claim_stage` call.
+    pub max_pieces_per_claim_tx: Option<u32>,
+    // The earliest mint time, in nano seconds, accepted for the challenge
+    // piece at the associated index.
+    pub min_mint_time_ns: Vec<Option<u64>>,
+    // Whether the challenge ends once every winner has minted their rewards.
+    pub auto_end_on_full_mint: bool,
+    // Messages shown in place of the defaults when a claim is rejected.
+    pub custom_messages: CustomMessages,
+    // Maximum number of claims that can start in a single block.
+    pub max_claims_per_block: Option<u32>,
+    // Whether the challenge piece at the associated index is required or a bonus.
+    pub piece_roles: Vec<PieceRole>,
+    // Every challenge piece along with its index and full spec.
+    pub pieces: Vec<ChallengePiece>,
+    // Reward metadata for winners that own bonus pieces, where the entry at
+    // index `i` is minted to winners owning `i + 1` bonus pieces.
+    pub bonus_reward_metadata: Vec<NFTTokenMetadata>,
+    // Royalty on the reward NFT, in basis points, split between the owners of
+    // the challenge piece contracts.
+    pub royalty_from_piece_owners: Option<u32>,
+    // How long a reserved slot is held, in nano seconds.
+    pub reservation_ttl_ns: Option<u64>,
+    // The contract a soulbound badge is minted from for every winner.
+    pub winner_badge_nft_id: Option<AccountId>,
+    // How long winners have to mint their reward after winning, in nano seconds.
+    pub mint_deadline_ns_after_win: Option<u64>,
+    // How long a claim or reservation can stay in flight before its slot is
+    // reclaimed, in nano seconds.
+    pub claim_ttl_ns: Option<u64>,
+    // How long an account has to wait after a claim resolved before starting
+    // another one, in nano seconds.
+    pub claim_cooldown_ns: Option<u64>,
+    // When the challenge opens for claims, in nano seconds.
+    pub start_date_in_ns: u64,
+    // Whether claims are rejected before taking a slot when a consumed piece
+    // is not approved to this contract.
+    pub require_pre_approval: bool,
+    // Whether winners can only mint their reward to themselves or to accounts
+    // on the mint recipient allowlist.
+    pub restrict_mint_recipient: bool,
+    // Whether winners can claim the challenge again once they minted the
+    // reward of their last win.
+    pub allow_repeat_wins: bool,
+    // The fungible token contract and the balance of it an account must hold
+    // to claim.
+    pub ft_gate: Option<(AccountId, U128)>,
+    // The achievements contract every win is granted on.
+    pub achievements_contract: Option<AccountId>,
+    // Maximum number of claims each account can start.
+    pub max_attempts_per_account: Option<u32>,
+    // The contract and metadata of the consolation reward accounts that
+    // attempted the challenge without winning can mint.
+    pub consolation_reward: Option<(AccountId, NFTTokenMetadata)>,
+    // How many of the required pieces an account must own to win.
+    pub required_pieces: u64,
+    // The deposit `mint_nft` requires, in millinear, as an upper bound on the
+    // reward store's storage cost.
+    pub mint_deposit_in_millinear: u64,
 }
 
 // Define the contract structure
@@ -57,7 +641,7 @@ pub struct ChallengeMetaData {
 #[derive(PanicOnDefault)]
 pub struct Contract {
     // The owner of this NFT Challenge
-    owner_id: String,
+    owner_id: AccountId,
     // The creator of this NFT Challenge
     creator_id: String,
     // The name for this challenge.
@@ -71,9 +655,9 @@ pub struct Contract {
     // Metadata for the reward token NFT. Only necessary if we mint the nft.
     reward_nft_metadata: NFTTokenMetadata,
     // Ids of the challenge nfts that are part of this challenge.
-    challenge_nft_ids: Vector<String>,
-    // Whether to burn the challenge piece at the associated index when claiming.
-    burn_challenge_piece_on_claim: Vector<bool>,
+    challenge_nft_ids: Vector<AccountId>,
+    // How the challenge piece at the associated index is treated on claim.
+    pieces: Vector<PieceSpec>,
     // The expiration date of this challenge, expressed as a nano second timestamp.
     expiration_date_in_ns: u64,
     // Maximum number of winners for this challenge.
@@ -82,7 +666,7 @@ pub struct Contract {
     winner_count: u64,
     // The list of winners for this challenge. This is a map and not a set
     // in case we want to let winners win multiple times.
-    winners: LookupMap<AccountId, u64>,
+    winners: LookupMap<AccountId, WinnerInfo>,
     // The number of potential winners left for this challenge, it includes
     // accounts currently going through the claim process, to ensure we don't
     // have more winners than the winner limit.
@@ -91,51 +675,439 @@ pub struct Contract {
     challenge_completed: bool,
     // Whether the creator of this challenge can update the challenge status.
     creator_can_update: bool,
+    // The most recent claim attempts, used as a ring buffer once it reaches
+    // `MAX_RECENT_ATTEMPTS` entries.
+    recent_attempts: Vector<ClaimAttempt>,
+    // Index of the oldest entry in `recent_attempts` once the buffer is full.
+    recent_attempts_head: u32,
+    // Number of reward NFTs minted to each winner.
+    rewards_per_winner: u64,
+    // Metadata minted in place of `reward_nft_metadata` once the challenge
+    // owner reveals the reward.
+    revealed_reward_metadata: Option<NFTTokenMetadata>,
+    // Whether the reward has been revealed.
+    revealed: bool,
+    // An account that can perform limited admin actions alongside the owner.
+    co_owner_id: Option<AccountId>,
+    // Whether claims are currently paused.
+    paused: bool,
+    // Number of claims currently going through the claim process.
+    inflight_claims: u64,
+    // Fee charged for each claim attempt, refunded if the claim fails.
+    claim_fee: NearToken,
+    // Claim fees held for claims currently going through the claim process,
+    // keyed by the account the claim is for and holding the account that paid.
+    pending_claim_fees: LookupMap<AccountId, (AccountId, NearToken)>,
+    // Audit trail of owner mutations, used as a ring buffer once it reaches
+    // `MAX_MUTATION_RECORDS` entries.
+    mutation_log: Vector<MutationRecord>,
+    // Index of the oldest entry in `mutation_log` once the buffer is full.
+    mutation_log_head: u32,
+    // Accounts allowed to claim on behalf of other accounts.
+    delegates: LookupSet<AccountId>,
+    // Claims currently going through the claim process, keyed by the account
+    // the claim is for, with the block timestamp the claim started at.
+    pending_claims: UnorderedMap<AccountId, u64>,
+    // Maximum number of pieces verified per `initiate_claim_stage` call, for
+    // challenges with too many pieces to verify in a single transaction.
+    max_pieces_per_claim_tx: Option<u32>,
+    // Token ids of the pieces verified so far by staged claims, indexed by
+    // challenge piece.
+    staged_claims: LookupMap<AccountId, Vec<Option<String>>>,
+    // Challenge pieces left owned by this contract after a failed burn.
+    stuck_tokens: Vector<NftToken>,
+    // Number of reward NFTs minted through this contract.
+    rewards_minted_count: u64,
+    // Whether the challenge ends once every winner slot has been won and
+    // every winner has minted their rewards.
+    auto_end_on_full_mint: bool,
+    // Messages shown in place of the defaults when a claim is rejected.
+    custom_messages: CustomMessages,
+    // Number of claim attempts that observed ownership of the challenge piece
+    // at the associated index.
+    piece_ownership_observations: Vector<u64>,
+    // Maximum number of claims that can start in a single block.
+    max_claims_per_block: Option<u32>,
+    // The height of the last block a claim started in, with the number of
+    // claims started in that block.
+    claims_in_block: (u64, u32),
+    // Reward metadata for winners that own bonus pieces, where the entry at
+    // index `i` is minted to winners owning `i + 1` bonus pieces. Winners
+    // owning more bonus pieces than there are tiers get the last tier.
+    bonus_reward_metadata: Vector<NFTTokenMetadata>,
+    // Number of bonus pieces each winner owned when claiming, if any.
+    bonus_pieces_owned: LookupMap<AccountId, u32>,
+    // Royalty on the reward NFT, in basis points, split evenly between the
+    // owners of the challenge piece contracts, which are resolved at mint time.
+    royalty_from_piece_owners: Option<u32>,
+    // The royalty split used for the most recent reward mint.
+    reward_royalty_split: Option<SplitBetweenUnparsed>,
+    // The receipt of each account's most recent claim.
+    last_receipts: LookupMap<AccountId, ClaimReceipt>,
+    // Accounts blocked from claiming the challenge.
+    blacklist: LookupSet<AccountId>,
+    // Winners in the order they won the challenge.
+    winners_in_order: Vector<AccountId>,
+    // How long a reserved slot is held, in nano seconds. Reservations are
+    // disabled when unset.
+    reservation_ttl_ns: Option<u64>,
+    // Slots reserved by accounts that have not completed their claim yet.
+    // Reserved slots are counted as in-flight claims.
+    reservations: UnorderedMap<AccountId, Reservation>,
+    // The contract a non-transferable badge is minted from for every winner,
+    // separately from the reward. No badges are minted when unset.
+    winner_badge_nft_id: Option<AccountId>,
+    // What the owner has deposited to pay for minting winner badges, less
+    // what the badges minted so far cost. Badges are skipped once it runs
+    // out, so they never draw on the contract's own balance.
+    badge_budget: NearToken,
+    // How long winners have to mint their reward after winning, in nano
+    // seconds. Once it passes, the owner can forfeit the unminted win.
+    mint_deadline_ns_after_win: Option<u64>,
+    // How many of each winner's wins have had their reward minted. Tracked
+    // per win against the win count in `winners`, so an account that wins
+    // again can mint again.
+    wins_minted: LookupMap<AccountId, u64>,
+    // Whether rewards can no longer be minted, set once the contract has
+    // been evacuated.
+    minting_frozen: bool,
+    // Number of wins attributed to each referrer.
+    referrals: LookupMap<AccountId, u64>,
+    // The referrer of each claim currently going through the claim process,
+    // credited once the claim wins.
+    pending_referrals: LookupMap<AccountId, AccountId>,
+    // How long a claim or reservation can stay in flight, in nano seconds.
+    // Older ones are considered abandoned and their slots are reclaimed by
+    // the next claim. This should comfortably exceed the time a claim's
+    // callbacks take to resolve.
+    claim_ttl_ns: Option<u64>,
+    // How long an account has to wait after its last claim resolved before
+    // starting another one, in nano seconds. Accounts can retry right away
+    // when unset.
+    claim_cooldown_ns: Option<u64>,
+    // When the challenge opens for claims, in nano seconds. Challenges
+    // without a start date are open from deployment.
+    start_date_in_ns: u64,
+    // Accounts allowed to win the challenge. Everyone may take part while
+    // it is empty.
+    whitelist: LookupSet<AccountId>,
+    // Number of accounts in `whitelist`, since lookup sets can't be counted.
+    whitelist_count: u64,
+    // Whether claims check that every piece they burn or transfer is
+    // approved to this contract before taking a slot.
+    require_pre_approval: bool,
+    // Whether winners can only mint their reward to themselves or to accounts
+    // on `mint_recipient_allowlist`, so rewards can't be routed to sale bots.
+    restrict_mint_recipient: bool,
+    // Accounts winners may mint their reward to when `restrict_mint_recipient`
+    // is set.
+    mint_recipient_allowlist: LookupSet<AccountId>,
+    // Whether winners can claim the challenge again once they minted the
+    // reward of their last win. Every win takes a winner slot.
+    allow_repeat_wins: bool,
+    // The account proposed as the next owner, until it accepts ownership.
+    pending_owner_id: Option<AccountId>,
+    // The fungible token contract and the balance of it an account must hold
+    // to claim, if claims are gated on one.
+    ft_gate: Option<(AccountId, U128)>,
+    // The achievements contract every win is granted on, best-effort.
+    achievements_contract: Option<AccountId>,
+    // Whether the last achievement grant for each winner succeeded.
+    achievement_grants: LookupMap<AccountId, bool>,
+    // Whether the challenge details are permanently frozen.
+    metadata_locked: bool,
+    // Maximum number of claims each account can start, however long it
+    // waits between them.
+    max_attempts_per_account: Option<u32>,
+    // Number of claims each account has started.
+    attempts_per_account: LookupMap<AccountId, u32>,
+    // The contract and metadata of the consolation reward accounts that
+    // attempted the challenge without winning can mint, once each.
+    consolation_reward: Option<(AccountId, NFTTokenMetadata)>,
+    // Accounts that have claimed the consolation reward.
+    consolations_claimed: LookupSet<AccountId>,
+    // Whether `cleanup_and_refund` has cleared the challenge storage.
+    cleaned_up: bool,
+    // Accounts whose wins `cleanup_and_refund` cleared, so they still can't
+    // claim the consolation reward.
+    former_winners: LookupSet<AccountId>,
+    // How many of the required pieces an account must own to win. Defaults
+    // to all of them.
+    required_pieces: u64,
+    // Number of claim attempts that resolved, won or not.
+    total_attempts: u64,
+    // Number of challenge pieces burned by winning claims.
+    pieces_burned: u64,
+    // The deposit `mint_nft` requires, in millinear. It is an upper bound:
+    // only the reward store's exact storage cost is attached to the mint and
+    // the rest is refunded, while mints that cost more fail with a refund.
+    mint_deposit_in_millinear: u64,
+}
+
+// The state layout before per-piece settings moved into `PieceSpec`, read by
+// `migrate`.
+#[derive(BorshDeserialize, BorshSerialize)]
+struct OldContract {
+    owner_id: String,
+    creator_id: String,
+    name: String,
+    description: String,
+    media_link: String,
+    reward_nft_id: String,
+    reward_nft_metadata: NFTTokenMetadata,
+    // Stored as strings, which share their borsh layout with account ids.
+    challenge_nft_ids: Vector<AccountId>,
+    piece_actions: Vector<PieceAction>,
+    expiration_date_in_ns: u64,
+    winner_limit: u64,
+    winner_count: u64,
+    winners: LookupMap<AccountId, u64>,
+    potential_winners_left: u64,
+    challenge_completed: bool,
+    creator_can_update: bool,
+    recent_attempts: Vector<ClaimAttempt>,
+    recent_attempts_head: u32,
+    rewards_per_winner: u64,
+    revealed_reward_metadata: Option<NFTTokenMetadata>,
+    revealed: bool,
+    co_owner_id: Option<AccountId>,
+    paused: bool,
+    inflight_claims: u64,
+    claim_fee: NearToken,
+    pending_claim_fees: LookupMap<AccountId, (AccountId, NearToken)>,
+    mutation_log: Vector<MutationRecord>,
+    mutation_log_head: u32,
+    delegates: LookupSet<AccountId>,
+    pending_claims: UnorderedMap<AccountId, u64>,
+    max_pieces_per_claim_tx: Option<u32>,
+    staged_claims: LookupMap<AccountId, Vec<Option<String>>>,
+    stuck_tokens: Vector<NftToken>,
+    min_mint_time_ns: Vector<Option<u64>>,
+    won_at: LookupMap<AccountId, u64>,
+    rewards_minted_count: u64,
+    auto_end_on_full_mint: bool,
+    custom_messages: CustomMessages,
+    piece_ownership_observations: Vector<u64>,
+    max_claims_per_block: Option<u32>,
+    claims_in_block: (u64, u32),
+    piece_roles: Vector<PieceRole>,
+    bonus_reward_metadata: Vector<NFTTokenMetadata>,
+    bonus_pieces_owned: LookupMap<AccountId, u32>,
+    royalty_from_piece_owners: Option<u32>,
+    reward_royalty_split: Option<SplitBetweenUnparsed>,
+    last_receipts: LookupMap<AccountId, ClaimReceipt>,
+    blacklist: LookupSet<AccountId>,
+    winners_in_order: Vector<AccountId>,
+    reservation_ttl_ns: Option<u64>,
+    reservations: UnorderedMap<AccountId, Reservation>,
 }
 
 // Implement the contract structure
 #[near]
 impl Contract {
     #[init]
-    pub fn new(
-        owner_id: String,
-        name: String,
-        description: String,
-        media_link: String,
-        reward_nft_id: String,
-        _challenge_nft_ids: std::vec::Vec<String>,
-        _burn_challenge_piece_on_claim: std::vec::Vec<bool>,
-        expiration_date_in_ns: u64,
-        winner_limit: u64,
-        creator_can_update: bool,
-        reward_nft_metadata: NFTTokenMetadata,
-    ) -> Self {
-        assert!(
-            env::is_valid_account_id(owner_id.as_bytes()),
-            "Owner's account ID is invalid",
-        );
-        assert_eq!(
-            _challenge_nft_ids.len(),
-            _burn_challenge_piece_on_claim.len(),
-            "The challenge nft ids and burn challenge piece on claim must be the same length"
-        );
-        assert!(
-            _challenge_nft_ids.len() > 0,
-            "Challenge must have at least 1 challenge NFT"
-        );
-        let mut challenge_nft_ids_set = LookupSet::new(b"t");
+    pub fn new(config: ChallengeConfig) -> Self {
+        match Self::try_new(config) {
+            Ok(contract) => contract,
+            Err(error) => env::panic_str(&error.to_string()),
+        }
+    }
+
+    // Same as `new`, but returns a `ChallengeError` describing the first
+    // invalid argument instead of panicking with a generic message.
+    #[init]
+    #[handle_result]
+    pub fn try_new(config: ChallengeConfig) -> Result<Self, ChallengeError> {
+        let ChallengeConfig {
+            owner_id,
+            name,
+            description,
+            media_link,
+            reward_nft_id,
+            challenge_nft_ids: _challenge_nft_ids,
+            burn_challenge_piece_on_claim: _burn_challenge_piece_on_claim,
+            expiration_date_in_ns,
+            winner_limit,
+            creator_can_update,
+            reward_nft_metadata,
+            piece_actions: _piece_actions,
+            rewards_per_winner,
+            revealed_reward_metadata,
+            claim_fee,
+            max_pieces_per_claim_tx,
+            min_mint_time_ns: _min_mint_time_ns,
+            auto_end_on_full_mint,
+            custom_messages,
+            max_claims_per_block,
+            piece_roles: _piece_roles,
+            bonus_reward_metadata,
+            royalty_from_piece_owners,
+            reservation_ttl_ns,
+            winner_badge_nft_id,
+            mint_deadline_ns_after_win,
+            claim_ttl_ns,
+            claim_cooldown_ns,
+            start_date_in_ns,
+            whitelist,
+            require_pre_approval,
+            restrict_mint_recipient,
+            allow_repeat_wins,
+            ft_gate,
+            achievements_contract,
+            max_attempts_per_account,
+            consolation_reward,
+            required_pieces,
+            burn_quantities,
+            piece_labels,
+            mint_deposit_in_millinear,
+        } = config;
+        let owner_id: AccountId = owner_id
+            .parse()
+            .map_err(|_| ChallengeError::InvalidOwnerId)?;
+        // The reward contract id is parsed whenever it is called, so reject
+        // malformed ones now rather than in the middle of a claim.
+        if !env::is_valid_account_id(reward_nft_id.as_bytes()) {
+            return Err(ChallengeError::InvalidRewardNftId);
+        }
+        // The challenge piece ids are parsed once here and stored as account
+        // ids, keeping the parsing out of the claim path.
+        let parsed_challenge_nft_ids = _challenge_nft_ids
+            .iter()
+            .enumerate()
+            .map(|(index, nft_id)| {
+                nft_id
+                    .parse::<AccountId>()
+                    .map_err(|_| ChallengeError::InvalidChallengeNftId { index })
+            })
+            .collect::<Result<std::vec::Vec<AccountId>, ChallengeError>>()?;
+        if _challenge_nft_ids.len() != _burn_challenge_piece_on_claim.len() {
+            return Err(ChallengeError::BurnFlagsLengthMismatch);
+        }
+        if _challenge_nft_ids.len() == 0 {
+            return Err(ChallengeError::NoChallengePieces);
+        }
+        if expiration_date_in_ns <= env::block_timestamp() {
+            return Err(ChallengeError::ExpirationInPast);
+        }
+        let start_date_in_ns = start_date_in_ns.unwrap_or(0);
+        if start_date_in_ns >= expiration_date_in_ns {
+            return Err(ChallengeError::StartDateAfterExpiration);
+        }
+        // The hash pins the reward media, so it can't be swapped later.
+        if reward_nft_metadata.media.is_some() && reward_nft_metadata.media_hash.is_none() {
+            return Err(ChallengeError::MissingRewardMediaHash);
+        }
+        let rewards_per_winner = rewards_per_winner.unwrap_or(1);
+        if rewards_per_winner == 0 {
+            return Err(ChallengeError::NoRewardsPerWinner);
+        }
+        if max_pieces_per_claim_tx == Some(0) {
+            return Err(ChallengeError::NoPiecesPerClaimTx);
+        }
+        if let Some(actions) = &_piece_actions {
+            if _challenge_nft_ids.len() != actions.len() {
+                return Err(ChallengeError::PieceActionsLengthMismatch);
+            }
+        }
+        if let Some(min_mint_times) = &_min_mint_time_ns {
+            if _challenge_nft_ids.len() != min_mint_times.len() {
+                return Err(ChallengeError::MinMintTimesLengthMismatch);
+            }
+        }
+        if let Some(roles) = &_piece_roles {
+            if _challenge_nft_ids.len() != roles.len() {
+                return Err(ChallengeError::PieceRolesLengthMismatch);
+            }
+            if !roles.contains(&PieceRole::Required) {
+                return Err(ChallengeError::NoRequiredPieces);
+            }
+        }
+        if let Some(quantities) = &burn_quantities {
+            if _challenge_nft_ids.len() != quantities.len() {
+                return Err(ChallengeError::BurnQuantitiesLengthMismatch);
+            }
+            // Staged claims verify a single token per piece.
+            let max_quantity = if max_pieces_per_claim_tx.is_some() {
+                1
+            } else {
+                u32::MAX
+            };
+            if quantities
+                .iter()
+                .any(|quantity| *quantity == 0 || *quantity > max_quantity)
+            {
+                return Err(ChallengeError::InvalidBurnQuantity);
+            }
+        }
+        if piece_labels
+            .as_ref()
+            .map_or(false, |labels| labels.len() != _challenge_nft_ids.len())
+        {
+            return Err(ChallengeError::PieceLabelsLengthMismatch);
+        }
+        let required_piece_count = match &_piece_roles {
+            Some(roles) => roles
+                .iter()
+                .filter(|role| **role == PieceRole::Required)
+                .count(),
+            None => _challenge_nft_ids.len(),
+        } as u64;
+        let required_pieces = required_pieces.unwrap_or(required_piece_count);
+        if required_pieces == 0 || required_pieces > required_piece_count {
+            return Err(ChallengeError::InvalidRequiredPieces);
+        }
+        if royalty_from_piece_owners.map_or(false, |royalty| royalty > ROYALTY_BASIS_POINTS) {
+            return Err(ChallengeError::RoyaltyPercentageTooHigh);
+        }
+        let custom_messages = custom_messages.unwrap_or_default();
+        for message in [
+            &custom_messages.not_owner,
+            &custom_messages.already_won,
+            &custom_messages.expired,
+            &custom_messages.full,
+        ] {
+            if message.as_ref().map_or(0, |message| message.len()) > MAX_CUSTOM_MESSAGE_LEN {
+                return Err(ChallengeError::CustomMessageTooLong);
+            }
+        }
         let mut challenge_nft_ids = Vector::new(b"a");
-        let mut burn_challenge_piece_on_claim = Vector::new(b"c");
+        let mut pieces = Vector::new(b"c");
+        let mut piece_ownership_observations = Vector::new(b"o");
         for i in 0.._challenge_nft_ids.len() {
-            if challenge_nft_ids_set.contains(&_challenge_nft_ids[i]) {
-                panic!("Challenge NFT ids must be unique");
+            challenge_nft_ids.push(parsed_challenge_nft_ids[i].clone());
+            // Explicit piece actions take precedence over the burn flags.
+            let action = match &_piece_actions {
+                Some(actions) => actions[i].clone(),
+                None if _burn_challenge_piece_on_claim[i] => PieceAction::Burn,
+                None => PieceAction::Keep,
+            };
+            pieces.push(PieceSpec {
+                action,
+                role: match &_piece_roles {
+                    Some(roles) => roles[i].clone(),
+                    None => PieceRole::Required,
+                },
+                min_mint_time_ns: _min_mint_time_ns.as_ref().and_then(|times| times[i]),
+                burn_quantity: burn_quantities
+                    .as_ref()
+                    .map_or(1, |quantities| quantities[i]),
+                label: piece_labels.as_ref().map(|labels| labels[i].clone()),
+            });
+            piece_ownership_observations.push(0);
+        }
+        let mut whitelist_set = LookupSet::new(b"j");
+        let mut whitelist_count = 0;
+        for account_id in whitelist.unwrap_or_default() {
+            if whitelist_set.insert(account_id) {
+                whitelist_count += 1;
             }
-            challenge_nft_ids.push(_challenge_nft_ids[i].clone());
-            challenge_nft_ids_set.insert(&_challenge_nft_ids[i]);
-            burn_challenge_piece_on_claim.push(_burn_challenge_piece_on_claim[i]);
+        }
+        let mut bonus_reward_tiers = Vector::new(b"x");
+        for metadata in bonus_reward_metadata.unwrap_or_default() {
+            bonus_reward_tiers.push(metadata);
         }
 
-        Self {
+        Ok(Self {
             owner_id,
             creator_id: env::predecessor_account_id().to_string(),
             name,
@@ -143,7 +1115,7 @@ impl Contract {
             media_link,
             reward_nft_id,
             challenge_nft_ids,
-            burn_challenge_piece_on_claim,
+            pieces,
             expiration_date_in_ns,
             winner_limit,
             challenge_completed: false,
@@ -152,6 +1124,265 @@ impl Contract {
             winners: LookupMap::new(b"z"),
             reward_nft_metadata,
             creator_can_update,
+            recent_attempts: Vector::new(b"r"),
+            recent_attempts_head: 0,
+            rewards_per_winner,
+            revealed_reward_metadata,
+            revealed: false,
+            co_owner_id: None,
+            paused: false,
+            inflight_claims: 0,
+            claim_fee: claim_fee.unwrap_or(NearToken::from_yoctonear(0)),
+            pending_claim_fees: LookupMap::new(b"f"),
+            mutation_log: Vector::new(b"m"),
+            mutation_log_head: 0,
+            delegates: LookupSet::new(b"d"),
+            pending_claims: UnorderedMap::new(b"p"),
+            max_pieces_per_claim_tx,
+            staged_claims: LookupMap::new(b"g"),
+            stuck_tokens: Vector::new(b"k"),
+            rewards_minted_count: 0,
+            auto_end_on_full_mint: auto_end_on_full_mint.unwrap_or(false),
+            custom_messages,
+            piece_ownership_observations,
+            max_claims_per_block,
+            claims_in_block: (0, 0),
+            bonus_reward_metadata: bonus_reward_tiers,
+            bonus_pieces_owned: LookupMap::new(b"q"),
+            royalty_from_piece_owners,
+            reward_royalty_split: None,
+            last_receipts: LookupMap::new(b"l"),
+            blacklist: LookupSet::new(b"b"),
+            winners_in_order: Vector::new(b"v"),
+            reservation_ttl_ns,
+            reservations: UnorderedMap::new(b"s"),
+            winner_badge_nft_id,
+            badge_budget: NearToken::from_yoctonear(0),
+            mint_deadline_ns_after_win,
+            wins_minted: LookupMap::new(b"u"),
+            minting_frozen: false,
+            referrals: LookupMap::new(b"h"),
+            pending_referrals: LookupMap::new(b"i"),
+            claim_ttl_ns,
+            claim_cooldown_ns,
+            start_date_in_ns,
+            whitelist: whitelist_set,
+            whitelist_count,
+            require_pre_approval: require_pre_approval.unwrap_or(false),
+            restrict_mint_recipient: restrict_mint_recipient.unwrap_or(false),
+            mint_recipient_allowlist: LookupSet::new(b"D"),
+            allow_repeat_wins: allow_repeat_wins.unwrap_or(false),
+            pending_owner_id: None,
+            ft_gate,
+            achievements_contract,
+            achievement_grants: LookupMap::new(b"y"),
+            metadata_locked: false,
+            max_attempts_per_account,
+            attempts_per_account: LookupMap::new(b"A"),
+            consolation_reward,
+            consolations_claimed: LookupSet::new(b"B"),
+            cleaned_up: false,
+            former_winners: LookupSet::new(b"C"),
+            required_pieces,
+            total_attempts: 0,
+            pieces_burned: 0,
+            mint_deposit_in_millinear: mint_deposit_in_millinear
+                .unwrap_or(DEFAULT_MINT_DEPOSIT_IN_MILLINEAR),
+        })
+    }
+
+    // Creates the challenge from the config served by the `template_id`
+    // contract, with `overrides` applied on top. Init methods can't wait on a
+    // cross-contract call, so the challenge is only initialized once
+    // `finalize_from_template` resolves, owned by the caller.
+    pub fn new_from_template(template_id: AccountId, overrides: TemplateOverrides) -> Promise {
+        assert!(
+            !env::state_exists(),
+            "The contract has already been initialized"
+        );
+        challenge_template::ext(template_id)
+            .with_static_gas(Gas::from_tgas(5))
+            .get_config()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(50))
+                    .finalize_from_template(env::predecessor_account_id(), overrides),
+            )
+    }
+
+    #[private]
+    #[init]
+    pub fn finalize_from_template(owner_id: AccountId, overrides: TemplateOverrides) -> Self {
+        let config = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<TemplateConfig>(&value)
+                    .unwrap_or_else(|_| env::panic_str("The template config is invalid"))
+            }
+            PromiseResult::Failed => env::panic_str("Could not fetch the template config"),
+        };
+        Self::new(ChallengeConfig {
+            owner_id: owner_id.to_string(),
+            name: overrides.name.unwrap_or(config.name),
+            description: config.description,
+            media_link: config.media_link,
+            reward_nft_id: config.reward_nft_id,
+            challenge_nft_ids: config.challenge_nft_ids,
+            burn_challenge_piece_on_claim: config.burn_challenge_piece_on_claim,
+            expiration_date_in_ns: overrides
+                .expiration_date_in_ns
+                .unwrap_or(config.expiration_date_in_ns),
+            winner_limit: overrides.winner_limit.unwrap_or(config.winner_limit),
+            creator_can_update: config.creator_can_update,
+            reward_nft_metadata: config.reward_nft_metadata,
+            ..Default::default()
+        })
+    }
+
+    // Upgrades the state of a challenge deployed before per-piece settings
+    // moved into `PieceSpec`. Settings added since then start out as
+    // `try_new` leaves them when they aren't configured.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old: OldContract = env::state_read().expect("There is no challenge state to migrate");
+        let OldContract {
+            mut piece_actions,
+            mut min_mint_time_ns,
+            mut piece_roles,
+            winners: old_winners,
+            mut won_at,
+            ..
+        } = old;
+
+        // Pieces keep the prefix of the piece actions they replace.
+        let specs: Vec<PieceSpec> = (0..piece_actions.len())
+            .map(|i| PieceSpec {
+                action: piece_actions[i].clone(),
+                role: piece_roles[i].clone(),
+                min_mint_time_ns: min_mint_time_ns[i],
+                burn_quantity: 1,
+                label: None,
+            })
+            .collect();
+        piece_actions.clear();
+        piece_actions.flush();
+        min_mint_time_ns.clear();
+        min_mint_time_ns.flush();
+        piece_roles.clear();
+        piece_roles.flush();
+        let mut pieces = Vector::new(b"c");
+        for spec in specs {
+            pieces.push(spec);
+        }
+        let required_pieces = pieces
+            .iter()
+            .filter(|spec| spec.role == PieceRole::Required)
+            .count() as u64;
+
+        // Winners were stored as win counts, with the time of the win kept
+        // separately.
+        let mut winners = LookupMap::new(b"z");
+        for winner_id in old.winners_in_order.iter() {
+            let count = *old_winners.get(winner_id).unwrap_or(&1);
+            let won_at_ns = won_at.remove(winner_id).unwrap_or(0);
+            winners.insert(
+                winner_id.clone(),
+                WinnerInfo {
+                    count,
+                    first_won_at_ns: won_at_ns,
+                    last_won_at_ns: won_at_ns,
+                },
+            );
+        }
+        won_at.flush();
+        // Mints weren't tracked per win, so wins only count as minted once
+        // every reward won so far has been minted.
+        let mut wins_minted = LookupMap::new(b"u");
+        if old.rewards_minted_count >= old.winner_count * old.rewards_per_winner {
+            for winner_id in old.winners_in_order.iter() {
+                wins_minted.insert(winner_id.clone(), winners.get(winner_id).unwrap().count);
+            }
+        }
+
+        Self {
+            owner_id: old.owner_id.parse().unwrap(),
+            creator_id: old.creator_id,
+            name: old.name,
+            description: old.description,
+            media_link: old.media_link,
+            reward_nft_id: old.reward_nft_id,
+            challenge_nft_ids: old.challenge_nft_ids,
+            pieces,
+            expiration_date_in_ns: old.expiration_date_in_ns,
+            winner_limit: old.winner_limit,
+            winner_count: old.winner_count,
+            winners,
+            potential_winners_left: old.potential_winners_left,
+            challenge_completed: old.challenge_completed,
+            creator_can_update: old.creator_can_update,
+            recent_attempts: old.recent_attempts,
+            recent_attempts_head: old.recent_attempts_head,
+            rewards_per_winner: old.rewards_per_winner,
+            revealed_reward_metadata: old.revealed_reward_metadata,
+            revealed: old.revealed,
+            co_owner_id: old.co_owner_id,
+            paused: old.paused,
+            inflight_claims: old.inflight_claims,
+            claim_fee: old.claim_fee,
+            pending_claim_fees: old.pending_claim_fees,
+            mutation_log: old.mutation_log,
+            mutation_log_head: old.mutation_log_head,
+            delegates: old.delegates,
+            pending_claims: old.pending_claims,
+            max_pieces_per_claim_tx: old.max_pieces_per_claim_tx,
+            staged_claims: old.staged_claims,
+            stuck_tokens: old.stuck_tokens,
+            rewards_minted_count: old.rewards_minted_count,
+            auto_end_on_full_mint: old.auto_end_on_full_mint,
+            custom_messages: old.custom_messages,
+            piece_ownership_observations: old.piece_ownership_observations,
+            max_claims_per_block: old.max_claims_per_block,
+            claims_in_block: old.claims_in_block,
+            bonus_reward_metadata: old.bonus_reward_metadata,
+            bonus_pieces_owned: old.bonus_pieces_owned,
+            royalty_from_piece_owners: old.royalty_from_piece_owners,
+            reward_royalty_split: old.reward_royalty_split,
+            last_receipts: old.last_receipts,
+            blacklist: old.blacklist,
+            winners_in_order: old.winners_in_order,
+            reservation_ttl_ns: old.reservation_ttl_ns,
+            reservations: old.reservations,
+            winner_badge_nft_id: None,
+            badge_budget: NearToken::from_yoctonear(0),
+            mint_deadline_ns_after_win: None,
+            wins_minted,
+            minting_frozen: false,
+            referrals: LookupMap::new(b"h"),
+            pending_referrals: LookupMap::new(b"i"),
+            claim_ttl_ns: None,
+            claim_cooldown_ns: None,
+            start_date_in_ns: 0,
+            whitelist: LookupSet::new(b"j"),
+            whitelist_count: 0,
+            require_pre_approval: false,
+            restrict_mint_recipient: false,
+            mint_recipient_allowlist: LookupSet::new(b"D"),
+            allow_repeat_wins: false,
+            pending_owner_id: None,
+            ft_gate: None,
+            achievements_contract: None,
+            achievement_grants: LookupMap::new(b"y"),
+            metadata_locked: false,
+            max_attempts_per_account: None,
+            attempts_per_account: LookupMap::new(b"A"),
+            consolation_reward: None,
+            consolations_claimed: LookupSet::new(b"B"),
+            cleaned_up: false,
+            former_winners: LookupSet::new(b"C"),
+            required_pieces,
+            total_attempts: 0,
+            pieces_burned: 0,
+            mint_deposit_in_millinear: DEFAULT_MINT_DEPOSIT_IN_MILLINEAR,
         }
     }
 
@@ -159,30 +1390,140 @@ impl Contract {
     pub fn get_challenge_metadata(&self) -> ChallengeMetaData {
         let mut challenge_list = Vec::new();
         let mut challenge_burn_list = Vec::new();
+        let mut challenge_action_list = Vec::new();
+        let mut challenge_min_mint_time_list = Vec::new();
+        let mut challenge_role_list = Vec::new();
         for i in 0..self.challenge_nft_ids.len() {
-            challenge_list.push(self.challenge_nft_ids[i].clone());
-            challenge_burn_list.push(self.burn_challenge_piece_on_claim[i]);
+            let piece = &self.pieces[i];
+            challenge_list.push(self.challenge_nft_ids[i].to_string());
+            challenge_burn_list.push(piece.action == PieceAction::Burn);
+            challenge_action_list.push(piece.action.clone());
+            challenge_min_mint_time_list.push(piece.min_mint_time_ns);
+            challenge_role_list.push(piece.role.clone());
         }
         ChallengeMetaData {
-            owner_id: self.owner_id.clone(),
+            owner_id: self.owner_id.to_string(),
             name: self.name.clone(),
             description: self.description.clone(),
             media_link: Some(self.media_link.clone()),
             reward_nft_id: self.reward_nft_id.clone(),
             challenge_nft_ids: challenge_list,
             burn_challenge_piece_on_claim: challenge_burn_list,
+            piece_actions: challenge_action_list,
             expiration_date_in_ns: self.expiration_date_in_ns,
             winner_limit: self.winner_limit,
             challenge_completed: self.challenge_completed,
             winners_count: self.winner_count,
             reward_nft_metadata: self.reward_nft_metadata.clone(),
             creator_can_update: self.creator_can_update,
+            rewards_per_winner: self.rewards_per_winner,
+            revealed: self.revealed,
+            max_pieces_per_claim_tx: self.max_pieces_per_claim_tx,
+            min_mint_time_ns: challenge_min_mint_time_list,
+            auto_end_on_full_mint: self.auto_end_on_full_mint,
+            custom_messages: self.custom_messages.clone(),
+            max_claims_per_block: self.max_claims_per_block,
+            piece_roles: challenge_role_list,
+            pieces: (0..self.pieces.len())
+                .map(|index| ChallengePiece {
+                    index,
+                    nft_id: self.challenge_nft_ids[index].to_string(),
+                    spec: self.pieces[index].clone(),
+                })
+                .collect(),
+            bonus_reward_metadata: self.bonus_reward_metadata.iter().cloned().collect(),
+            royalty_from_piece_owners: self.royalty_from_piece_owners,
+            reservation_ttl_ns: self.reservation_ttl_ns,
+            winner_badge_nft_id: self.winner_badge_nft_id.clone(),
+            mint_deadline_ns_after_win: self.mint_deadline_ns_after_win,
+            claim_ttl_ns: self.claim_ttl_ns,
+            claim_cooldown_ns: self.claim_cooldown_ns,
+            start_date_in_ns: self.start_date_in_ns,
+            require_pre_approval: self.require_pre_approval,
+            restrict_mint_recipient: self.restrict_mint_recipient,
+            allow_repeat_wins: self.allow_repeat_wins,
+            ft_gate: self.ft_gate.clone(),
+            achievements_contract: self.achievements_contract.clone(),
+            max_attempts_per_account: self.max_attempts_per_account,
+            consolation_reward: self.consolation_reward.clone(),
+            required_pieces: self.required_pieces,
+            mint_deposit_in_millinear: self.mint_deposit_in_millinear,
         }
     }
 
+    // Same as `get_challenge_metadata`, but borsh encoded to keep the payload
+    // small for bandwidth constrained clients.
+    pub fn get_challenge_metadata_borsh(&self) -> Base64VecU8 {
+        borsh::to_vec(&self.get_challenge_metadata())
+            .unwrap()
+            .into()
+    }
+
+    // The challenge piece contract ids and whether each is burned on claim,
+    // in piece order, without the rest of the challenge metadata.
+    pub fn get_challenge_pieces(
+        &self,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<(String, bool)> {
+        self.challenge_nft_ids
+            .iter()
+            .zip(self.pieces.iter())
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.map_or(usize::MAX, |limit| limit as usize))
+            .map(|(nft_id, piece)| (nft_id.to_string(), piece.action == PieceAction::Burn))
+            .collect()
+    }
+
     // Show the current owner of this NFT Challenge
     pub fn get_owner_id(&self) -> String {
-        self.owner_id.clone()
+        self.owner_id.to_string()
+    }
+
+    // Show how `caller` relates to this NFT Challenge, so clients can decide
+    // which admin actions to offer.
+    pub fn get_owner_info(&self, caller: AccountId) -> OwnerInfo {
+        let is_owner = self.owner_id == caller;
+        OwnerInfo {
+            owner_id: self.owner_id.to_string(),
+            creator_id: self.creator_id.clone(),
+            is_owner,
+            is_creator: self.creator_id == caller,
+            can_update_completion: is_owner && self.creator_can_update,
+        }
+    }
+
+    // The standards this contract implements, so clients can detect which
+    // features to enable.
+    pub fn supported_standards(&self) -> Vec<StandardPair> {
+        vec![
+            // Events logged through `emit_event`.
+            StandardPair {
+                standard: "nep297".to_string(),
+                version: "1.0.0".to_string(),
+            },
+            // `contract_source_metadata`, generated by cargo-near.
+            StandardPair {
+                standard: "nep330".to_string(),
+                version: "1.1.0".to_string(),
+            },
+        ]
+    }
+
+    // The current phase of the challenge. A completed challenge reports
+    // `Completed` even once it has also expired.
+    pub fn get_phase(&self) -> ChallengePhase {
+        if self.challenge_completed {
+            ChallengePhase::Completed
+        } else if env::block_timestamp() < self.start_date_in_ns {
+            ChallengePhase::NotStarted
+        } else if self.is_challenge_expired() {
+            ChallengePhase::Expired
+        } else if self.potential_winners_left == 0 || self.winner_count >= self.winner_limit {
+            ChallengePhase::Full
+        } else {
+            ChallengePhase::Active
+        }
     }
 
     pub fn is_challenge_expired(&self) -> bool {
@@ -193,6 +1534,30 @@ impl Contract {
         env::block_timestamp() >= self.expiration_date_in_ns
     }
 
+    // Whether the challenge is between its start and expiration dates.
+    pub fn is_challenge_active(&self) -> bool {
+        env::block_timestamp() >= self.start_date_in_ns && !self.is_challenge_expired()
+    }
+
+    // Whether anyone could start a claim right now: the challenge is active,
+    // has slots left and isn't paused. Doesn't look at any account specific
+    // rule, like the whitelist or previous wins.
+    pub fn is_live(&self) -> bool {
+        !self.paused && self.get_phase() == ChallengePhase::Active
+    }
+
+    // Time left until the challenge expires, in nano seconds, or 0 once it
+    // has expired.
+    pub fn time_remaining_in_ns(&self) -> u64 {
+        self.expiration_date_in_ns
+            .saturating_sub(env::block_timestamp())
+    }
+
+    // Same as `time_remaining_in_ns`, in whole seconds.
+    pub fn seconds_remaining(&self) -> u64 {
+        self.time_remaining_in_ns() / NS_IN_SECOND
+    }
+
     pub fn potential_winners_left(&self) -> u64 {
         self.potential_winners_left
     }
@@ -201,509 +1566,5894 @@ impl Contract {
         self.winners.contains_key(&account_id)
     }
 
-    pub fn is_challenge_complete(&self) -> bool {
-        self.challenge_completed
+    pub fn get_winner_info(&self, account_id: AccountId) -> Option<WinnerInfo> {
+        self.winners.get(&account_id).cloned()
     }
 
-    // -------------------------- change methods ---------------------------
-    #[payable]
-    pub fn mint_nft(&mut self) -> Promise {
-        assert!(
-            self.is_account_winner(env::predecessor_account_id()),
-            "You must win the challenge to mint the NFT"
-        );
-        assert!(
-            // Approximate minting fee for a single NFT.
-            env::attached_deposit().as_millinear() >= 54,
-            "To cover minting fees, you need to attach at least {} millinear to this transaction.",
-            54
-        );
-        let promise = mintbase_nft::ext(self.reward_nft_id.parse().unwrap())
-            .with_static_gas(Gas::from_tgas(5))
-            .with_attached_deposit(NearToken::from_millinear(54))
-            .nft_batch_mint(
-                env::predecessor_account_id(),
-                self.reward_nft_metadata.clone(),
-                1,
-                None,
-                None,
-            );
+    pub fn get_win_proof(&self, account_id: AccountId) -> Option<WinProof> {
+        // Proofs attest the first win, so a repeat win doesn't invalidate
+        // proofs already handed out.
+        let won_at = self.winners.get(&account_id)?.first_won_at_ns;
+        let challenge_hash = self.challenge_hash();
+        let commitment = win_proof_commitment(&challenge_hash, &account_id, won_at);
+        Some(WinProof {
+            account_id,
+            won_at,
+            challenge_hash: challenge_hash.into(),
+            commitment: commitment.into(),
+        })
+    }
 
-        return promise.then(
-            Self::ext(env::current_account_id())
-                .with_static_gas(Gas::from_tgas(5))
-                .mint_nft_callback(),
-        );
+    // How many claim attempts observed ownership of each challenge piece, as a
+    // rough measure of how hard each piece is to collect.
+    pub fn get_piece_ownership_stats(&self) -> Vec<u64> {
+        self.piece_ownership_observations.iter().cloned().collect()
     }
 
-    #[payable]
-    pub fn initiate_claim(&mut self) -> Promise {
-        // Need 2 YOCOTNEAR per challenge NFT to claim the challenge.
-        if env::attached_deposit().as_yoctonear() < (self.challenge_nft_ids.len() * 2).into() {
-            panic!(
-                "You must attach at least {} YOCTONEAR to claim the challenge",
-                self.challenge_nft_ids.len()
-            );
-        }
+    // The royalty split used for the most recent reward mint, keyed by the
+    // owners of the challenge piece contracts.
+    pub fn get_reward_royalty_split(&self) -> Option<SplitBetweenUnparsed> {
+        self.reward_royalty_split.clone()
+    }
 
-        if self.potential_winners_left == 0 {
-            panic!("Challenge currently at max potential winners");
-        }
+    pub fn get_last_receipt(&self, account_id: AccountId) -> Option<ClaimReceipt> {
+        self.last_receipts.get(&account_id).cloned()
+    }
 
-        if self.winner_count >= self.winner_limit {
-            panic!("Challenge is not accepting any more winners");
+    // The earliest block timestamp at which `account_id` can start a claim,
+    // given the start date and its claim cooldown, or 0 if it can claim now.
+    pub fn next_claimable_at(&self, account_id: AccountId) -> u64 {
+        let claimable_at = self
+            .start_date_in_ns
+            .max(self.cooldown_ends_at(&account_id));
+        if claimable_at <= env::block_timestamp() {
+            0
+        } else {
+            claimable_at
         }
+    }
 
-        if self.challenge_completed {
-            panic!("Challenge is over");
-        }
+    pub fn get_rewards_minted_count(&self) -> u64 {
+        self.rewards_minted_count
+    }
 
-        if self.ensure_challenge_not_expired() {
-            panic!("Challenge is expired");
-        }
+    // The account that first won the challenge at `position`, starting at 0
+    // for the first winner.
+    pub fn get_winner_at(&self, position: u64) -> Option<AccountId> {
+        let position: u32 = position.try_into().ok()?;
+        self.winners_in_order.get(position).cloned()
+    }
 
-        if self.is_account_winner(env::predecessor_account_id()) {
-            panic!("You have already won this challenge");
-        }
+    // Winners in the order they won, each with its number of wins, starting
+    // at `from_index`. Returns every remaining winner when `limit` is unset.
+    pub fn get_winners(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<(AccountId, u64)> {
+        self.winners_in_order
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.map_or(usize::MAX, |limit| limit as usize))
+            .map(|winner_id| {
+                let wins = self.winners.get(winner_id).map_or(0, |info| info.count);
+                (winner_id.clone(), wins)
+            })
+            .collect()
+    }
 
-        // To ensure we don't have more winners than the winner limit.
-        self.decrement_winners();
+    pub fn is_challenge_complete(&self) -> bool {
+        self.challenge_completed
+    }
 
-        let challenge_nft_ownership_promises: Vec<Promise> = self
-            .challenge_nft_ids
+    pub fn get_capacity(&self) -> Capacity {
+        let accounted = self
+            .winner_count
+            .checked_add(self.potential_winners_left)
+            .and_then(|total| total.checked_add(self.inflight_claims));
+        let warning = if accounted == Some(self.winner_limit) {
+            None
+        } else {
+            Some(format!(
+                "Confirmed winners ({}), potential winners left ({}) and in-flight claims ({}) do not add up to the winner limit ({})",
+                self.winner_count, self.potential_winners_left, self.inflight_claims, self.winner_limit
+            ))
+        };
+        Capacity {
+            limit: self.winner_limit,
+            confirmed_winners: self.winner_count,
+            potential_left: self.potential_winners_left,
+            inflight: self.inflight_claims,
+            warning,
+        }
+    }
+
+    // Packs the common views into one call, so dashboards can read them
+    // consistently from a single block.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            block_height: env::block_height(),
+            block_timestamp: env::block_timestamp(),
+            metadata: self.get_challenge_metadata(),
+            capacity: self.get_capacity(),
+            phase: self.get_phase(),
+            supported_standards: self.supported_standards(),
+            is_paused: self.is_paused(),
+            is_minting_frozen: self.is_minting_frozen(),
+            effective_completion: self.get_effective_completion(),
+            rewards_minted_count: self.get_rewards_minted_count(),
+            claim_is_destructive: self.claim_is_destructive(),
+            burn_transfer_deposit: self.get_burn_transfer_deposit(),
+            claim_stage_count: self.get_claim_stage_count(),
+            has_stuck_tokens: self.has_stuck_tokens(),
+            self_check: self.self_check(),
+        }
+    }
+
+    // Reports every broken state invariant found, as human readable issues.
+    // The list is empty when the challenge state is healthy.
+    pub fn self_check(&self) -> Vec<String> {
+        let mut issues = vec![];
+        let piece_count = self.challenge_nft_ids.len();
+        for (name, len) in [
+            ("pieces", self.pieces.len()),
+            (
+                "piece_ownership_observations",
+                self.piece_ownership_observations.len(),
+            ),
+        ] {
+            if len != piece_count {
+                issues.push(format!(
+                    "{} has {} entries but there are {} challenge nfts",
+                    name, len, piece_count
+                ));
+            }
+        }
+        if self.winner_count > self.winner_limit {
+            issues.push(format!(
+                "winner_count ({}) is greater than winner_limit ({})",
+                self.winner_count, self.winner_limit
+            ));
+        }
+        if self.potential_winners_left > self.winner_limit {
+            issues.push(format!(
+                "potential_winners_left ({}) is greater than winner_limit ({})",
+                self.potential_winners_left, self.winner_limit
+            ));
+        }
+        if let Some(warning) = self.get_capacity().warning {
+            issues.push(warning);
+        }
+        let pending_count = self.pending_claims.len() as u64 + self.reservations.len() as u64;
+        if self.inflight_claims != pending_count {
+            issues.push(format!(
+                "inflight_claims ({}) does not match the number of pending claims and reservations ({})",
+                self.inflight_claims, pending_count
+            ));
+        }
+        issues
+    }
+
+    pub fn get_reservation(&self, account_id: AccountId) -> Option<Reservation> {
+        self.reservations.get(&account_id).cloned()
+    }
+
+    // Whether `media_bytes_hash` matches the hash of the reward media set at
+    // construction. Compared in constant time.
+    pub fn verify_reward_media(&self, media_bytes_hash: Vec<u8>) -> bool {
+        match &self.reward_nft_metadata.media_hash {
+            Some(media_hash) => constant_time_eq(&media_hash.0, &media_bytes_hash),
+            None => false,
+        }
+    }
+
+    pub fn get_attempt_count(&self, account_id: AccountId) -> u32 {
+        self.attempts_per_account
+            .get(&account_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn is_blacklisted(&self, account_id: AccountId) -> bool {
+        self.blacklist.contains(&account_id)
+    }
+
+    pub fn is_allowed_mint_recipient(&self, account_id: AccountId) -> bool {
+        self.mint_recipient_allowlist.contains(&account_id)
+    }
+
+    pub fn is_whitelisted(&self, account_id: AccountId) -> bool {
+        self.whitelist.contains(&account_id)
+    }
+
+    // Whether only whitelisted accounts can win the challenge.
+    pub fn has_whitelist(&self) -> bool {
+        self.whitelist_count > 0
+    }
+
+    // The deposit `mint_nft` needs, the most the storage of the reward
+    // tokens on the reward contract may cost.
+    pub fn get_mint_deposit(&self) -> NearToken {
+        NearToken::from_millinear(self.mint_deposit_in_millinear)
+    }
+
+    // What is left of the budget that pays for winner badges.
+    pub fn get_badge_budget(&self) -> NearToken {
+        self.badge_budget
+    }
+
+    // Whether `account_id` has minted the reward for every one of its wins.
+    pub fn has_minted(&self, account_id: AccountId) -> bool {
+        self.is_account_winner(account_id.clone()) && !self.has_unminted_win(&account_id)
+    }
+
+    // Number of wins attributed to `account_id` as a referrer.
+    pub fn get_referral_count(&self, account_id: AccountId) -> u64 {
+        self.referrals.get(&account_id).copied().unwrap_or(0)
+    }
+
+    pub fn is_delegate(&self, account_id: AccountId) -> bool {
+        self.delegates.contains(&account_id)
+    }
+
+    // Number of `initiate_claim_stage` calls needed to verify every piece.
+    pub fn get_claim_stage_count(&self) -> u32 {
+        match self.max_pieces_per_claim_tx {
+            Some(max_pieces) => self.challenge_nft_ids.len().div_ceil(max_pieces),
+            None => 1,
+        }
+    }
+
+    // Which challenge pieces have been verified for the account by staged claims.
+    pub fn get_claim_stage_progress(&self, account_id: AccountId) -> Vec<bool> {
+        match self.staged_claims.get(&account_id) {
+            Some(verified_pieces) => verified_pieces.iter().map(|p| p.is_some()).collect(),
+            None => vec![false; self.challenge_nft_ids.len() as usize],
+        }
+    }
+
+    pub fn has_stuck_tokens(&self) -> bool {
+        !self.stuck_tokens.is_empty()
+    }
+
+    pub fn get_stuck_tokens(&self, from_index: u32, limit: u32) -> Vec<NftToken> {
+        self.stuck_tokens
             .iter()
-            .map(|x| {
-                mintbase_nft::ext(x.parse().unwrap())
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_co_owner_id(&self) -> Option<AccountId> {
+        self.co_owner_id.clone()
+    }
+
+    // Whether the last achievement grant for `account_id` succeeded, if one
+    // has resolved.
+    pub fn get_last_achievement_grant(&self, account_id: AccountId) -> Option<bool> {
+        self.achievement_grants.get(&account_id).copied()
+    }
+
+    pub fn get_pending_owner_id(&self) -> Option<AccountId> {
+        self.pending_owner_id.clone()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn is_minting_frozen(&self) -> bool {
+        self.minting_frozen
+    }
+
+    pub fn is_metadata_locked(&self) -> bool {
+        self.metadata_locked
+    }
+
+    // Whether the challenge is over, including expirations that have not yet
+    // been materialized by `ensure_challenge_not_expired`.
+    pub fn get_effective_completion(&self) -> bool {
+        self.challenge_completed || self.is_challenge_expired()
+    }
+
+    // Whether claiming burns or transfers away any of the claimant's pieces.
+    pub fn claim_is_destructive(&self) -> bool {
+        self.pieces
+            .iter()
+            .any(|piece| piece.action != PieceAction::Keep)
+    }
+
+    // The deposit needed to cover the transfers of every piece that is burned
+    // or transferred on claim, at 1 yoctoNEAR per transfer.
+    pub fn get_burn_transfer_deposit(&self) -> NearToken {
+        let burnable_count: u128 = self
+            .pieces
+            .iter()
+            .filter(|piece| piece.action != PieceAction::Keep)
+            .map(|piece| piece.burn_quantity as u128)
+            .sum();
+        NearToken::from_yoctonear(burnable_count)
+    }
+
+    // Returns claim attempts from oldest to newest, starting at `from`.
+    pub fn get_recent_attempts(&self, from: u32, limit: u32) -> Vec<ClaimAttempt> {
+        read_ring_buffer(
+            &self.recent_attempts,
+            self.recent_attempts_head,
+            from,
+            limit,
+        )
+    }
+
+    // Returns owner mutations from oldest to newest, starting at `from`.
+    pub fn get_mutation_log(&self, from: u32, limit: u32) -> Vec<MutationRecord> {
+        read_ring_buffer(&self.mutation_log, self.mutation_log_head, from, limit)
+    }
+
+    // -------------------------- change methods ---------------------------
+    // Mints the reward for the caller's win to `recipient`, or to the caller
+    // when unset.
+    #[payable]
+    pub fn mint_nft(&mut self, recipient: Option<AccountId>) -> Promise {
+        assert!(!self.minting_frozen, "Minting is frozen");
+        assert!(
+            self.is_account_winner(env::predecessor_account_id()),
+            "You must win the challenge to mint the NFT"
+        );
+        assert!(
+            self.has_unminted_win(&env::predecessor_account_id()),
+            "Reward already minted"
+        );
+        assert!(
+            self.rewards_per_winner >= 1,
+            "Challenge must reward at least 1 NFT per winner"
+        );
+        let recipient_id = recipient.unwrap_or_else(env::predecessor_account_id);
+        if self.restrict_mint_recipient {
+            assert!(
+                recipient_id == env::predecessor_account_id()
+                    || self.mint_recipient_allowlist.contains(&recipient_id),
+                "The reward can't be minted to {}",
+                recipient_id
+            );
+        }
+        let mint_deposit = self.get_mint_deposit();
+        assert!(
+            env::attached_deposit() >= mint_deposit,
+            "To cover minting fees, you need to attach at least {} yoctoNEAR to this transaction.",
+            mint_deposit.as_yoctonear()
+        );
+        // Only the storage cost is forwarded to the reward contract.
+        let surplus = env::attached_deposit().saturating_sub(mint_deposit);
+        if !surplus.is_zero() {
+            Promise::new(env::predecessor_account_id()).transfer(surplus);
+        }
+        // Mark the win as minted before the mint is dispatched, so it can't
+        // be minted again while the mint is in flight. A failed mint rolls
+        // the mark back.
+        let wins_minted = self
+            .wins_minted
+            .get(&env::predecessor_account_id())
+            .copied()
+            .unwrap_or(0);
+        self.wins_minted
+            .insert(env::predecessor_account_id(), wins_minted + 1);
+        // Check this contract can mint on the reward store first, since a
+        // failed mint deep in the reward contract gives a confusing error.
+        mintbase_nft::ext(self.reward_nft_id.parse().unwrap())
+            .with_static_gas(Gas::from_tgas(2))
+            .check_is_minter(env::current_account_id())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(30))
+                    .on_minter_checked(env::predecessor_account_id(), recipient_id, mint_deposit),
+            )
+    }
+
+    // Mints the consolation reward to the caller, who must have attempted
+    // the challenge without winning. Each account can claim it once, and
+    // pays for the storage of the minted token.
+    #[payable]
+    pub fn claim_consolation(&mut self) -> Promise {
+        let (consolation_nft_id, metadata) = self
+            .consolation_reward
+            .clone()
+            .expect("This challenge has no consolation reward");
+        let account_id = env::predecessor_account_id();
+        assert!(
+            !self.is_account_winner(account_id.clone())
+                && !self.former_winners.contains(&account_id),
+            "Winners can't claim the consolation reward"
+        );
+        assert!(
+            self.last_receipts.contains_key(&account_id),
+            "You must attempt the challenge to claim the consolation reward"
+        );
+        assert!(
+            !self.pending_claims.contains_key(&account_id),
+            "You already have a claim in progress"
+        );
+        assert!(
+            !self.consolations_claimed.contains(&account_id),
+            "Consolation reward already claimed"
+        );
+        let mint_deposit = mint_storage_cost(&metadata, 1);
+        assert!(
+            env::attached_deposit() >= mint_deposit,
+            "To cover minting fees, you need to attach at least {} yoctoNEAR to this transaction.",
+            mint_deposit.as_yoctonear()
+        );
+        let surplus = env::attached_deposit().saturating_sub(mint_deposit);
+        if !surplus.is_zero() {
+            Promise::new(account_id.clone()).transfer(surplus);
+        }
+        self.consolations_claimed.insert(account_id.clone());
+        mintbase_nft::ext(consolation_nft_id)
+            .with_static_gas(Gas::from_tgas(5))
+            .with_attached_deposit(mint_deposit)
+            .nft_batch_mint(account_id.clone(), metadata, 1, None, None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .on_consolation_minted(account_id, mint_deposit),
+            )
+    }
+
+    // Resolves the reward `account_id` would mint if it won now, based on the
+    // bonus pieces it currently owns and whether the reward is revealed. This
+    // queries the bonus piece contracts, so it must be called as a
+    // transaction rather than a view.
+    pub fn preview_reward_metadata(
+        &self,
+        account_id: AccountId,
+    ) -> PromiseOrValue<NFTTokenMetadata> {
+        let bonus_indices: Vec<u32> = (0..self.pieces.len())
+            .filter(|i| self.pieces[*i].role == PieceRole::Bonus)
+            .collect();
+        let ownership_promises = bonus_indices
+            .iter()
+            .map(|i| {
+                mintbase_nft::ext(self.challenge_nft_ids[*i].clone())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .nft_tokens_for_owner(account_id.clone(), None, None)
+            })
+            .reduce(|a, b| a.and(b));
+        match ownership_promises {
+            Some(promise) => PromiseOrValue::Promise(
+                promise.then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas::from_tgas(5))
+                        .on_preview_reward_metadata(bonus_indices),
+                ),
+            ),
+            None => PromiseOrValue::Value(self.current_reward_metadata()),
+        }
+    }
+
+    // Resolves the `nft_approve` calls `account_id` has to make before
+    // claiming, one for every piece it owns that is burned or transferred on
+    // claim and not approved to this contract yet. This queries the piece
+    // contracts, so it must be called as a transaction rather than a view.
+    pub fn build_approval_calls(&self, account_id: AccountId) -> PromiseOrValue<Vec<ApprovalCall>> {
+        let piece_indices: Vec<u32> = (0..self.pieces.len())
+            .filter(|i| self.pieces[*i].action != PieceAction::Keep)
+            .collect();
+        let ownership_promises = piece_indices
+            .iter()
+            .map(|i| {
+                mintbase_nft::ext(self.challenge_nft_ids[*i].clone())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .nft_tokens_for_owner(account_id.clone(), None, None)
+            })
+            .reduce(|a, b| a.and(b));
+        match ownership_promises {
+            Some(promise) => PromiseOrValue::Promise(
+                promise.then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas::from_tgas(5))
+                        .on_build_approval_calls(piece_indices),
+                ),
+            ),
+            None => PromiseOrValue::Value(vec![]),
+        }
+    }
+
+    // Looks up the collection name of every challenge piece contract, so a
+    // creator can spot mistyped or wrong addresses before launch. Pieces whose
+    // contract can't be reached resolve to `None`.
+    pub fn resolve_piece_names(&self) -> Promise {
+        self.challenge_nft_ids
+            .iter()
+            .map(|nft_id| {
+                mintbase_nft::ext(nft_id.clone())
                     .with_static_gas(Gas::from_tgas(5))
-                    .nft_tokens_for_owner(env::predecessor_account_id(), None, None)
+                    .nft_metadata()
             })
+            .reduce(|a, b| a.and(b))
+            .unwrap()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .on_resolve_piece_names(),
+            )
+    }
+
+    #[payable]
+    pub fn initiate_claim(&mut self, referral: Option<AccountId>) -> Promise {
+        // Validate the referral before the claim touches any state.
+        assert!(
+            referral.as_ref() != Some(&env::predecessor_account_id()),
+            "You cannot refer yourself"
+        );
+        let promise =
+            self.start_claim(env::predecessor_account_id(), env::predecessor_account_id());
+        if let Some(referral) = referral {
+            self.pending_referrals
+                .insert(env::predecessor_account_id(), referral);
+        }
+        promise
+    }
+
+    // Claims the challenge for `winner_id` using the challenge pieces held by
+    // `holder_id`, e.g. for custodial wallets. Only registered delegates can
+    // call this.
+    #[payable]
+    pub fn initiate_claim_for(&mut self, holder_id: AccountId, winner_id: AccountId) -> Promise {
+        assert!(
+            self.delegates.contains(&env::predecessor_account_id()),
+            "Only registered delegates can claim on behalf of other accounts"
+        );
+        self.start_claim(holder_id, winner_id)
+    }
+
+    // Holds a winner slot for the caller until it calls `complete_reservation`,
+    // or until the reservation expires. The attached deposit is refunded once
+    // the reservation is completed or released.
+    #[payable]
+    pub fn reserve_slot(&mut self) {
+        let ttl_ns = self
+            .reservation_ttl_ns
+            .expect("This challenge does not support reservations");
+        assert!(
+            !env::attached_deposit().is_zero(),
+            "You must attach a deposit to reserve a slot"
+        );
+        self.reclaim_abandoned_claims();
+        if self.potential_winners_left == 0 || self.winner_count >= self.winner_limit {
+            panic!(
+                "{}",
+                message_or(
+                    &self.custom_messages.full,
+                    "Challenge currently at max potential winners"
+                )
+            );
+        }
+        let account_id = env::predecessor_account_id();
+        self.assert_claim_open(&account_id);
+        // Reserving starts the claim attempt, so it is throttled and counted
+        // like any other claim.
+        self.throttle_claims();
+        self.count_attempt(&account_id);
+        self.decrement_winners();
+        self.reservations.insert(
+            account_id,
+            Reservation {
+                deposit: env::attached_deposit(),
+                // Reservations are abandoned after `claim_ttl_ns` at the latest.
+                expires_at: env::block_timestamp()
+                    .saturating_add(ttl_ns.min(self.claim_ttl_ns.unwrap_or(u64::MAX))),
+            },
+        );
+    }
+
+    // Runs the claim process for the slot reserved by the caller. The claim
+    // fee is charged here, like for a direct claim.
+    #[payable]
+    pub fn complete_reservation(&mut self) -> Promise {
+        let winner_id = env::predecessor_account_id();
+        let reservation = self
+            .reservations
+            .remove(&winner_id)
+            .expect("You do not have a reservation");
+        assert!(
+            env::block_timestamp() < reservation.expires_at,
+            "Your reservation has expired"
+        );
+        self.assert_claim_deposit();
+        self.assert_claim_open(&winner_id);
+
+        // The reserved slot is already counted as in flight, so it carries
+        // over to the claim as is.
+        self.pending_claims
+            .insert(winner_id.clone(), env::block_timestamp());
+        if !self.claim_fee.is_zero() {
+            self.pending_claim_fees
+                .insert(winner_id.clone(), (winner_id.clone(), self.claim_fee));
+        }
+        Promise::new(winner_id.clone()).transfer(reservation.deposit);
+        self.gate_claim(
+            winner_id.clone(),
+            winner_id.clone(),
+            Some(winner_id),
+            Some(env::block_timestamp()),
+        )
+    }
+
+    // Releases the slots held by expired reservations, refunding their
+    // deposits. Anyone may call this, since it only materializes expirations
+    // that have already happened.
+    pub fn release_expired_reservations(&mut self) -> u64 {
+        let now = env::block_timestamp();
+        let expired: Vec<(AccountId, NearToken)> = self
+            .reservations
+            .iter()
+            .filter(|(_, reservation)| reservation.expires_at <= now)
+            .map(|(account_id, reservation)| (account_id.clone(), reservation.deposit))
             .collect();
-        let compiled_promise = challenge_nft_ownership_promises
-            .into_iter()
+        for (account_id, deposit) in expired.iter() {
+            log!("Releasing expired reservation for {}", account_id);
+            self.reservations.remove(account_id);
+            self.increment_winners();
+            Promise::new(account_id.clone()).transfer(*deposit);
+        }
+        expired.len() as u64
+    }
+
+    // Verifies the challenge pieces covered by `stage` for the caller. Once
+    // every stage has been verified, the last one runs the regular claim,
+    // which queries the ownership of every piece again so a piece handed
+    // off after its stage can't be counted. Staged claims are not charged
+    // the claim fee, but every stage counts against
+    // `max_attempts_per_account`.
+    #[payable]
+    pub fn initiate_claim_stage(&mut self, stage: u32) -> Promise {
+        assert!(
+            self.max_pieces_per_claim_tx.is_some(),
+            "This challenge does not support staged claims"
+        );
+        assert!(
+            stage < self.get_claim_stage_count(),
+            "Claim stage must be less than {}",
+            self.get_claim_stage_count()
+        );
+        let (start, end) = self.claim_stage_bounds(stage);
+        // Need 2 YOCOTNEAR per challenge NFT verified in this stage.
+        if env::attached_deposit().as_yoctonear() < ((end - start) * 2).into() {
+            panic!(
+                "You must attach at least {} YOCTONEAR to claim this stage",
+                (end - start) * 2
+            );
+        }
+        let winner_id = env::predecessor_account_id();
+        self.assert_claim_open(&winner_id);
+        self.throttle_claims();
+        self.count_attempt(&winner_id);
+
+        let compiled_promise = (start..end)
+            .map(|i| {
+                mintbase_nft::ext(self.challenge_nft_ids[i].clone())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .nft_tokens_for_owner(winner_id.clone(), None, None)
+            })
             .reduce(|a, b| a.and(b));
 
         match compiled_promise {
             Some(x) => x.then(
                 Self::ext(env::current_account_id())
                     .with_static_gas(Gas::from_tgas(5))
-                    .on_claim(
-                        env::predecessor_account_id(),
-                        self.challenge_nft_ids.len().into(),
-                    ),
+                    .on_claim_stage(winner_id, stage),
             ),
-            // Should never hit because we always have at least 1 challenge
+            // Should never hit because every stage covers at least 1 challenge
             None => panic!("Error in the challenge nft ownership promises"),
         }
     }
 
     #[private]
-    pub fn on_claim(&mut self, winner_id: AccountId, number_promises: u64) -> Promise {
-        let mut token_ids_to_burn: Vec<U64> = vec![];
-        let res: Vec<bool> = (0..number_promises)
-            .map(|index| {
-                let result: PromiseResult = env::promise_result(index);
-
-                match result {
-                    PromiseResult::Failed => false,
-                    PromiseResult::Successful(value) => {
-                        if let Ok(message) =
-                            near_sdk::serde_json::from_slice::<Vec<TokenCompliant>>(&value)
-                        {
-                            if message.len() != 0 {
-                                if self.burn_challenge_piece_on_claim[index as u32] {
-                                    token_ids_to_burn
-                                        .push(U64(message[0].token_id.parse().unwrap()));
-                                }
-                                true
-                            } else {
-                                false
-                            }
-                        } else {
-                            false
-                        }
-                    }
+    pub fn on_claim_stage(
+        &mut self,
+        winner_id: AccountId,
+        stage: u32,
+    ) -> PromiseOrValue<ClaimResult> {
+        let (start, end) = self.claim_stage_bounds(stage);
+        let mut verified_pieces = self
+            .staged_claims
+            .get(&winner_id)
+            .cloned()
+            .unwrap_or_else(|| vec![None; self.challenge_nft_ids.len() as usize]);
+        for i in start..end {
+            let token_id = match env::promise_result((i - start).into()) {
+                PromiseResult::Successful(value) => {
+                    near_sdk::serde_json::from_slice::<Vec<TokenCompliant>>(&value)
+                        .ok()
+                        .and_then(|tokens| {
+                            tokens
+                                .into_iter()
+                                .find(|token| self.is_minted_in_time(i, token))
+                                .map(|token| token.token_id)
+                        })
                 }
-            })
-            .collect();
-        for i in 0..res.len() {
-            if res[i] == false {
-                self.increment_winners();
+                PromiseResult::Failed => None,
+            };
+            if token_id.is_none() {
+                self.record_attempt(winner_id, CLAIM_OUTCOME_MISSING_PIECE);
                 log!(
-                    "Account does not own any of the challenge nfts at index {}",
-                    i
+                    "{}",
+                    message_or(
+                        &self.custom_messages.not_owner,
+                        &format!(
+                            "Account does not own any of the challenge nfts at index {}",
+                            i
+                        ),
+                    )
                 );
-                return Promise::new(env::current_account_id()).as_return();
+                return PromiseOrValue::Value(claim_resolved(ClaimResult::MissingPiece {
+                    index: i,
+                }));
             }
+            self.observe_piece_ownership(i);
+            verified_pieces[i as usize] = token_id;
         }
-        if token_ids_to_burn.len() == 0 {
-            // Complete the claim process here since we have verified they
-            // own all challenge nfts and we do not need to burn any.
-            self.winner_count += 1;
-            self.winners.insert(winner_id, 1);
-            return Promise::new(env::current_account_id()).as_return();
+        if verified_pieces.iter().any(|piece| piece.is_none()) {
+            log!("Verified claim stage {}", stage);
+            self.staged_claims.insert(winner_id, verified_pieces);
+            return PromiseOrValue::Value(claim_resolved(ClaimResult::StageVerified { stage }));
+        }
+
+        // Every piece has been verified, so the claim now goes through the
+        // same gate as any other claim before it takes a slot.
+        self.staged_claims.remove(&winner_id);
+        if self.potential_winners_left == 0
+            || self.winner_count >= self.winner_limit
+            || self.challenge_completed
+        {
+            log!("Challenge is not accepting any more winners");
+            return PromiseOrValue::Value(claim_resolved(ClaimResult::ChallengeFull));
+        }
+        PromiseOrValue::Promise(self.gate_claim(winner_id.clone(), winner_id, None, None))
+    }
+
+    // Rejects a claim when `winner_id` holds less than the fungible token
+    // balance the challenge is gated on. A failed balance query counts as
+    // holding none. Otherwise the claim moves on to the ownership checks.
+    #[private]
+    pub fn on_ft_gate_checked(
+        &mut self,
+        holder_id: AccountId,
+        winner_id: AccountId,
+        payer_id: Option<AccountId>,
+        claim_started_at: Option<u64>,
+    ) -> PromiseOrValue<ClaimResult> {
+        if let Some(claim_started_at) = claim_started_at {
+            if !self.is_claim_pending(&winner_id, claim_started_at) {
+                return PromiseOrValue::Value(claim_released(&winner_id));
+            }
+        }
+        let (ft_contract_id, min_balance) = self
+            .ft_gate
+            .clone()
+            .expect("Challenge is not gated on a fungible token");
+        let balance = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<U128>(&value).map_or(0, |balance| balance.0)
+            }
+            PromiseResult::Failed => 0,
+        };
+        if balance < min_balance.0 {
+            log!(
+                "Claiming requires holding at least {} of {}",
+                min_balance.0,
+                ft_contract_id
+            );
+            self.reject_gated_claim(
+                winner_id,
+                payer_id,
+                claim_started_at.is_some(),
+                CLAIM_OUTCOME_BELOW_FT_GATE,
+            );
+            return PromiseOrValue::Value(claim_resolved(ClaimResult::BelowFtGate));
+        }
+        // Other claims may have taken the last slot in the meantime, unless
+        // this claim already holds one.
+        if claim_started_at.is_none()
+            && (self.potential_winners_left == 0
+                || self.winner_count >= self.winner_limit
+                || self.challenge_completed)
+        {
+            log!("Challenge is not accepting any more winners");
+            self.refund_claim_fee(payer_id);
+            return PromiseOrValue::Value(claim_resolved(ClaimResult::ChallengeFull));
+        }
+        PromiseOrValue::Promise(self.dispatch_claim(
+            holder_id,
+            winner_id,
+            payer_id,
+            claim_started_at,
+        ))
+    }
+
+    // Rejects a claim in pre-approval mode when a piece it would burn or
+    // transfer is not approved to this contract. Otherwise the claim takes a
+    // slot, unless it already holds one, and resolves with the ownership
+    // results already fetched.
+    #[private]
+    pub fn on_pre_approval_check(
+        &mut self,
+        holder_id: AccountId,
+        winner_id: AccountId,
+        payer_id: Option<AccountId>,
+        claim_started_at: Option<u64>,
+    ) -> PromiseOrValue<ClaimResult> {
+        if let Some(claim_started_at) = claim_started_at {
+            if !self.is_claim_pending(&winner_id, claim_started_at) {
+                return PromiseOrValue::Value(claim_released(&winner_id));
+            }
+        }
+        // Pieces that aren't owned are left to `on_claim` to report.
+        let claimed_pieces = self.claimed_pieces(self.ownership_contracts().len() as u64);
+        let unapproved_indices: Vec<u32> = (0..self.pieces.len())
+            .filter(|index| self.pieces[*index].action != PieceAction::Keep)
+            .filter(|index| {
+                claimed_pieces[*index as usize]
+                    .as_ref()
+                    .map_or(false, |tokens| {
+                        tokens
+                            .iter()
+                            .any(|token| !token.is_approved_for(&env::current_account_id()))
+                    })
+            })
+            .collect();
+        if !unapproved_indices.is_empty() {
+            log!(
+                "You must grant transfer approval for the challenge NFTs at indices {:?} before claiming",
+                unapproved_indices
+            );
+            self.reject_gated_claim(
+                winner_id,
+                payer_id,
+                claim_started_at.is_some(),
+                CLAIM_OUTCOME_MISSING_APPROVAL,
+            );
+            return PromiseOrValue::Value(claim_resolved(ClaimResult::NeedsApproval));
         }
+        // Other claims may have taken the last slot in the meantime, unless
+        // this claim already holds one.
+        if claim_started_at.is_none()
+            && (self.potential_winners_left == 0
+                || self.winner_count >= self.winner_limit
+                || self.challenge_completed)
+        {
+            log!("Challenge is not accepting any more winners");
+            self.refund_claim_fee(payer_id);
+            return PromiseOrValue::Value(claim_resolved(ClaimResult::ChallengeFull));
+        }
+        let claim_started_at =
+            claim_started_at.unwrap_or_else(|| self.take_claim_slot(&winner_id, payer_id));
+        self.on_claim(
+            holder_id,
+            winner_id,
+            self.ownership_contracts().len() as u64,
+            claim_started_at,
+        )
+    }
 
-        self.have_approvals_for_transfers(winner_id, token_ids_to_burn)
+    #[private]
+    pub fn on_claim(
+        &mut self,
+        holder_id: AccountId,
+        winner_id: AccountId,
+        number_promises: u64,
+        claim_started_at: u64,
+    ) -> PromiseOrValue<ClaimResult> {
+        if !self.is_claim_pending(&winner_id, claim_started_at) {
+            return PromiseOrValue::Value(claim_released(&winner_id));
+        }
+        // The challenge index of each token in `token_ids_to_burn`, since
+        // pieces that are kept or missing leave gaps.
+        let mut piece_indices: Vec<u32> = vec![];
+        let mut token_ids_to_burn: Vec<String> = vec![];
+        let claimed_pieces = self.claimed_pieces(number_promises);
+        let res: Vec<bool> = claimed_pieces.iter().map(Option::is_some).collect();
+        for (index, tokens) in claimed_pieces.into_iter().enumerate() {
+            if let Some(tokens) = tokens {
+                if self.pieces[index as u32].action != PieceAction::Keep {
+                    for token in tokens {
+                        piece_indices.push(index as u32);
+                        token_ids_to_burn.push(token.token_id);
+                    }
+                }
+            }
+        }
+        for i in 0..res.len() {
+            if res[i] {
+                self.observe_piece_ownership(i as u32);
+            }
+        }
+        // Bonus pieces only upgrade the reward, so they may be missing.
+        let is_required = |i: usize| self.pieces[i as u32].role == PieceRole::Required;
+        let required_owned = (0..res.len())
+            .filter(|i| res[*i] && is_required(*i))
+            .count() as u64;
+        if required_owned < self.required_pieces {
+            // Report the first required piece the account is missing.
+            let i = (0..res.len())
+                .find(|i| !res[*i] && is_required(*i))
+                .expect("A required piece must be missing");
+            self.record_failed_claim(winner_id.clone(), CLAIM_OUTCOME_MISSING_PIECE);
+            log!(
+                "{}",
+                message_or(
+                    &self.custom_messages.not_owner,
+                    &format!(
+                        "Account does not own any of the challenge nfts at index {}",
+                        i
+                    ),
+                )
+            );
+            return PromiseOrValue::Value(claim_resolved(ClaimResult::MissingPiece {
+                index: i as u32,
+            }));
+        }
+        let bonus_count = (0..res.len())
+            .filter(|i| res[*i] && self.pieces[*i as u32].role == PieceRole::Bonus)
+            .count() as u32;
+        if bonus_count > 0 {
+            self.bonus_pieces_owned
+                .insert(winner_id.clone(), bonus_count);
+        } else {
+            self.bonus_pieces_owned.remove(&winner_id);
+        }
+        if token_ids_to_burn.len() == 0 {
+            // Complete the claim process here since we have verified they
+            // own all challenge nfts and we do not need to burn or transfer any.
+            self.record_winner(winner_id);
+            return PromiseOrValue::Value(claim_resolved(ClaimResult::Won));
+        }
+
+        self.have_approvals_for_transfers(
+            holder_id,
+            winner_id,
+            piece_indices,
+            token_ids_to_burn,
+            claim_started_at,
+        )
+    }
+
+    #[payable]
+    #[private]
+    pub fn have_approvals_for_transfers(
+        &mut self,
+        holder_id: AccountId,
+        winner_id: AccountId,
+        piece_indices: Vec<u32>,
+        token_ids: Vec<String>,
+        claim_started_at: u64,
+    ) -> PromiseOrValue<ClaimResult> {
+        let mut is_approved_promises: Vec<Promise> = vec![];
+        for (piece_index, token_id) in piece_indices.iter().zip(token_ids.iter()) {
+            is_approved_promises.push(
+                mintbase_nft::ext(self.challenge_nft_ids[*piece_index].clone())
+                    .with_static_gas(Gas::from_tgas(1))
+                    .nft_approval_id(token_id.clone(), env::current_account_id()),
+            );
+        }
+        let compiled_promise = is_approved_promises.into_iter().reduce(|a, b| a.and(b));
+        if compiled_promise.is_none() {
+            panic!("No nfts to check approval for. Should not have reached here.");
+        } else {
+            PromiseOrValue::Promise(
+                compiled_promise.unwrap().then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas::from_tgas(token_ids.len() as u64 * 15))
+                        .on_approval_check(
+                            holder_id,
+                            winner_id,
+                            piece_indices,
+                            token_ids,
+                            claim_started_at,
+                        ),
+                ),
+            )
+        }
+    }
+
+    #[payable]
+    #[private]
+    pub fn on_approval_check(
+        &mut self,
+        holder_id: AccountId,
+        winner_id: AccountId,
+        piece_indices: Vec<u32>,
+        token_ids: Vec<String>,
+        claim_started_at: u64,
+    ) -> PromiseOrValue<ClaimResult> {
+        assert_promise_results_count(token_ids.len());
+        if !self.is_claim_pending(&winner_id, claim_started_at) {
+            return PromiseOrValue::Value(claim_released(&winner_id));
+        }
+        let approvals : Vec<Option<u64>> = (0..token_ids.len())
+            .map(|index| {
+                let result: PromiseResult = env::promise_result(index as u64);
+                match result {
+                    PromiseResult::Failed => {
+                        log!(
+                            "You must grant transfer approval for the challenge NFT at index {} for us to burn it",
+                            piece_indices[index]
+                        );
+                        None
+                    },
+                    PromiseResult::Successful(value) => {
+                        if let Ok(message) =
+                            near_sdk::serde_json::from_slice::<u64>(&value)
+                        {
+                           Some(message)
+                        } else {
+                            log!("Unable to get approval id for NFT at {}", piece_indices[index]);
+                            None
+                        }
+                    }
+                }
+            })
+            .collect();
+        for i in 0..approvals.len() {
+            if approvals[i] == None {
+                self.record_failed_claim(winner_id, CLAIM_OUTCOME_MISSING_APPROVAL);
+                return PromiseOrValue::Value(claim_resolved(ClaimResult::NeedsApproval));
+            }
+        }
+        // Transfer nfts to this contract so we can burn them, or directly to
+        // the account they should be routed to.
+        let mut transfer_promises: Vec<Promise> = vec![];
+        for j in 0..piece_indices.len() {
+            let i = piece_indices[j];
+            let receiver_id = match &self.pieces[i].action {
+                PieceAction::TransferTo(account_id) => account_id.clone(),
+                _ => env::current_account_id(),
+            };
+            transfer_promises.push(
+                mintbase_nft::ext(self.challenge_nft_ids[i].clone())
+                    .with_static_gas(Gas::from_tgas(2))
+                    .with_attached_deposit(NearToken::from_yoctonear(1))
+                    .nft_transfer(receiver_id, token_ids[j].clone(), approvals[j], None),
+            );
+        }
+        let compiled_promise = transfer_promises.into_iter().reduce(|a, b| a.and(b));
+        if compiled_promise.is_none() {
+            panic!("No nfts to transfer. Should not have reached here.");
+        } else {
+            PromiseOrValue::Promise(
+                compiled_promise.unwrap().then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas::from_tgas(token_ids.len() as u64 * 10))
+                        .burn_nfts(
+                            holder_id,
+                            winner_id,
+                            piece_indices,
+                            token_ids,
+                            claim_started_at,
+                        ),
+                ),
+            )
+        }
+    }
+
+    #[payable]
+    #[private]
+    pub fn burn_nfts(
+        &mut self,
+        holder_id: AccountId,
+        winner_id: AccountId,
+        piece_indices: Vec<u32>,
+        token_ids: Vec<String>,
+        claim_started_at: u64,
+    ) -> PromiseOrValue<ClaimResult> {
+        if !self.is_claim_pending(&winner_id, claim_started_at) {
+            // The pieces already moved into this contract to be burned are
+            // handed back, since the claim they were given up for is gone.
+            let transferred_tokens = self.transferred_burn_tokens(&piece_indices, &token_ids);
+            self.return_tokens(&holder_id, transferred_tokens);
+            return PromiseOrValue::Value(claim_released(&winner_id));
+        }
+        let mut transfers_failed = false;
+        for j in 0..token_ids.len() {
+            let i = piece_indices[j];
+            match env::promise_result(j as u64) {
+                PromiseResult::Failed => {
+                    log!(
+                        "There was an error transferring the challenge NFT at index {}",
+                        i
+                    );
+                    transfers_failed = true;
+                }
+                PromiseResult::Successful(_) => {
+                    log!("NFT transferred successfully at index {}", i);
+                }
+            }
+        }
+        if transfers_failed {
+            // The pieces that failed to transfer never left the holder. Those
+            // that moved into this contract are handed back, while those
+            // routed to another account are out of this contract's reach.
+            for j in 0..token_ids.len() {
+                if let PieceAction::TransferTo(account_id) = &self.pieces[piece_indices[j]].action {
+                    if matches!(env::promise_result(j as u64), PromiseResult::Successful(_)) {
+                        log!(
+                            "The challenge NFT at index {} was already transferred to {}",
+                            piece_indices[j],
+                            account_id
+                        );
+                    }
+                }
+            }
+            let transferred_tokens = self.transferred_burn_tokens(&piece_indices, &token_ids);
+            self.return_tokens(&holder_id, transferred_tokens);
+            self.record_failed_claim(winner_id, CLAIM_OUTCOME_BURN_FAILED);
+            return PromiseOrValue::Value(claim_resolved(ClaimResult::TransferFailed));
+        }
+        let mut burn_promises: Vec<Promise> = vec![];
+        let mut burned_tokens: Vec<NftToken> = vec![];
+        for j in 0..piece_indices.len() {
+            let i = piece_indices[j];
+            // Pieces routed to another account were already handed off in the
+            // transfer step, so there is nothing left to burn for them.
+            if self.pieces[i].action != PieceAction::Burn {
+                continue;
+            }
+            burn_promises.push(
+                mintbase_nft::ext(self.challenge_nft_ids[i].clone())
+                    .with_static_gas(Gas::from_tgas(2))
+                    .with_attached_deposit(NearToken::from_yoctonear(1))
+                    .nft_batch_burn(vec![token_ids[j].clone()]),
+            );
+            burned_tokens.push(NftToken {
+                nft_id: self.challenge_nft_ids[i].clone(),
+                token_id: token_ids[j].clone(),
+            });
+        }
+        let compiled_promise = burn_promises.into_iter().reduce(|a, b| a.and(b));
+        if compiled_promise.is_none() {
+            // Every consumed piece was transferred out, so the claim is complete.
+            self.record_winner(winner_id);
+            PromiseOrValue::Value(claim_resolved(ClaimResult::Won))
+        } else {
+            PromiseOrValue::Promise(
+                compiled_promise.unwrap().then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas::from_tgas(5))
+                        .on_burn_nfts(holder_id, winner_id, burned_tokens, claim_started_at),
+                ),
+            )
+        }
+    }
+
+    #[private]
+    pub fn on_burn_nfts(
+        &mut self,
+        holder_id: AccountId,
+        winner_id: AccountId,
+        burned_tokens: Vec<NftToken>,
+        claim_started_at: u64,
+    ) -> ClaimResult {
+        assert_promise_results_count(burned_tokens.len());
+        let mut unburned_tokens: Vec<NftToken> = vec![];
+        for index in 0..burned_tokens.len() {
+            // env::promise_result(i) has the result of the i-th call
+            let result: PromiseResult = env::promise_result(index as u64);
+            match result {
+                PromiseResult::Failed => {
+                    log!(
+                        "There was an error burning the challenge NFT at index {}",
+                        index
+                    );
+                    unburned_tokens.push(burned_tokens[index].clone());
+                }
+                PromiseResult::Successful(_) => {
+                    log!("NFT burned successfully at index {}", index);
+                    self.pieces_burned += 1;
+                }
+            }
+        }
+        // The contract still owns the tokens it failed to burn, so hand them
+        // back to the account they were taken from instead of orphaning them.
+        let burn_failed = !unburned_tokens.is_empty();
+        self.return_tokens(&holder_id, unburned_tokens);
+        if !self.is_claim_pending(&winner_id, claim_started_at) {
+            return claim_released(&winner_id);
+        }
+        // The returned pieces could be used to claim again, so a claim with
+        // a failed burn can't count as a win.
+        if burn_failed {
+            self.record_failed_claim(winner_id, CLAIM_OUTCOME_BURN_FAILED);
+            return claim_resolved(ClaimResult::BurnFailed);
+        }
+        self.record_winner(winner_id);
+        claim_resolved(ClaimResult::Won)
+    }
+
+    // Keeps the unburned tokens that could not be returned in the stuck token
+    // registry, so they can still be evacuated.
+    #[private]
+    pub fn on_unburned_returned(&mut self, tokens: Vec<NftToken>) {
+        for index in 0..tokens.len() {
+            if env::promise_result(index as u64) == PromiseResult::Failed {
+                log!(
+                    "There was an error returning token {} from {}",
+                    tokens[index].token_id,
+                    tokens[index].nft_id
+                );
+                self.stuck_tokens.push(tokens[index].clone());
+            }
+        }
+    }
+
+    // Transfers every token the contract is holding to `to` before the
+    // challenge is shut down, then closes the challenge and freezes minting.
+    // Tokens that fail to transfer are kept in the stuck token registry.
+    pub fn evacuate(&mut self, to: AccountId) -> Promise {
+        self.assert_challenge_owner();
+        let tokens: Vec<NftToken> = self.stuck_tokens.iter().cloned().collect();
+        self.stuck_tokens.clear();
+        self.record_mutation("evacuate", &self.challenge_completed.clone(), &true);
+        self.challenge_completed = true;
+        self.minting_frozen = true;
+
+        let transfer_promise = tokens
+            .iter()
+            .map(|token| {
+                mintbase_nft::ext(token.nft_id.clone())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .with_attached_deposit(NearToken::from_yoctonear(1))
+                    .nft_transfer(to.clone(), token.token_id.clone(), None, None)
+            })
+            .reduce(|a, b| a.and(b));
+        match transfer_promise {
+            Some(transfer_promise) => transfer_promise.then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .on_evacuate(tokens),
+            ),
+            None => Promise::new(env::current_account_id()).as_return(),
+        }
+    }
+
+    #[private]
+    pub fn on_evacuate(&mut self, tokens: Vec<NftToken>) {
+        for index in 0..tokens.len() {
+            if env::promise_result(index as u64) == PromiseResult::Failed {
+                log!(
+                    "There was an error evacuating token {} from {}",
+                    tokens[index].token_id,
+                    tokens[index].nft_id
+                );
+                self.stuck_tokens.push(tokens[index].clone());
+            }
+        }
+    }
+
+    pub fn update_challenge_completion_status(&mut self, is_complete: bool) {
+        self.assert_challenge_owner();
+        if self.creator_can_update {
+            self.record_mutation(
+                "update_challenge_completion_status",
+                &self.challenge_completed.clone(),
+                &is_complete,
+            );
+            self.challenge_completed = is_complete;
+        } else {
+            panic!("The creator cannot update the completion status of this challenge");
+        }
+    }
+
+    // Ends the challenge for good. Unlike `update_challenge_completion_status`,
+    // this only moves the challenge to completed and is always available to
+    // the owner.
+    pub fn end_challenge(&mut self) -> FinalReport {
+        self.assert_challenge_owner();
+        assert!(!self.challenge_completed, "Challenge is already complete");
+        self.record_mutation("end_challenge", &self.challenge_completed.clone(), &true);
+        self.challenge_completed = true;
+        log!("Challenge ended by {}", env::predecessor_account_id());
+        emit_challenge_completed("ended_by_owner");
+        let report = FinalReport {
+            total_winners: self.winner_count,
+            total_attempts: self.total_attempts,
+            pieces_burned: self.pieces_burned,
+            ended_at_ns: env::block_timestamp().into(),
+        };
+        emit_event(
+            "challenge_final_report",
+            near_sdk::serde_json::to_value(&report).unwrap(),
+        );
+        report
+    }
+
+    // Clears the winners and challenge pieces of a completed challenge, and
+    // refunds the storage staking they freed to the owner. Returns the
+    // amount refunded. Every win must have been minted or forfeited first,
+    // and former winners are still kept from the consolation reward.
+    pub fn cleanup_and_refund(&mut self) -> NearToken {
+        self.assert_challenge_owner();
+        assert!(self.challenge_completed, "Challenge is not complete yet");
+        assert!(!self.cleaned_up, "Challenge storage was already cleaned up");
+        assert!(
+            self.inflight_claims == 0,
+            "Claims are still in flight, release them before cleaning up"
+        );
+        assert!(
+            self.winners_in_order
+                .iter()
+                .all(|winner_id| !self.has_unminted_win(winner_id)),
+            "Every win must be minted or forfeited before cleaning up"
+        );
+        let storage_before = env::storage_usage();
+        for winner_id in self.winners_in_order.iter() {
+            self.winners.remove(winner_id);
+            self.wins_minted.remove(winner_id);
+            self.former_winners.insert(winner_id.clone());
+        }
+        self.winners_in_order.clear();
+        self.challenge_nft_ids.clear();
+        self.pieces.clear();
+        self.piece_ownership_observations.clear();
+        // Write the removals now, so the storage they free can be measured.
+        self.winners.flush();
+        self.wins_minted.flush();
+        self.former_winners.flush();
+        self.winners_in_order.flush();
+        self.challenge_nft_ids.flush();
+        self.pieces.flush();
+        self.piece_ownership_observations.flush();
+        self.record_mutation("cleanup_and_refund", &self.cleaned_up.clone(), &true);
+        self.cleaned_up = true;
+        let freed_bytes = storage_before.saturating_sub(env::storage_usage());
+        let refund = env::storage_byte_cost().saturating_mul(freed_bytes as u128);
+        log!(
+            "Cleaned up {} bytes, refunding {} yoctoNEAR",
+            freed_bytes,
+            refund.as_yoctonear()
+        );
+        if !refund.is_zero() {
+            Promise::new(self.owner_id.clone()).transfer(refund);
+        }
+        refund
+    }
+
+    // Sets the deposit `mint_nft` requires, e.g. after the reward store's
+    // storage costs change. Mints already under way keep the deposit they
+    // were charged.
+    pub fn set_mint_deposit(&mut self, mint_deposit_in_millinear: u64) {
+        self.assert_challenge_owner();
+        assert!(
+            mint_deposit_in_millinear > 0,
+            "The mint deposit must be positive"
+        );
+        self.record_mutation(
+            "set_mint_deposit",
+            &self.mint_deposit_in_millinear.clone(),
+            &mint_deposit_in_millinear,
+        );
+        self.mint_deposit_in_millinear = mint_deposit_in_millinear;
+    }
+
+    // Adds the attached deposit to the budget that pays for winner badges.
+    #[payable]
+    pub fn fund_badge_budget(&mut self) {
+        self.assert_challenge_owner();
+        assert!(
+            self.winner_badge_nft_id.is_some(),
+            "This challenge does not mint winner badges"
+        );
+        let badge_budget = self.badge_budget.saturating_add(env::attached_deposit());
+        self.record_mutation(
+            "fund_badge_budget",
+            &self.badge_budget.clone(),
+            &badge_budget,
+        );
+        self.badge_budget = badge_budget;
+    }
+
+    // Opens `additional_winners` more winner slots.
+    pub fn increase_winner_limit(&mut self, additional_winners: u64) {
+        self.assert_challenge_owner();
+        assert!(!self.challenge_completed, "Challenge is already complete");
+        let new_winner_limit = self
+            .winner_limit
+            .checked_add(additional_winners)
+            .expect("The new winner limit overflows");
+        let new_potential_winners_left = self
+            .potential_winners_left
+            .checked_add(additional_winners)
+            .expect("The new winner limit overflows");
+        self.record_mutation(
+            "increase_winner_limit",
+            &self.winner_limit.clone(),
+            &new_winner_limit,
+        );
+        let was_full = self.potential_winners_left == 0;
+        self.winner_limit = new_winner_limit;
+        self.potential_winners_left = new_potential_winners_left;
+        if was_full && self.potential_winners_left > 0 {
+            self.emit_slots_event("slots_available");
+        }
+    }
+
+    // Moves the expiration date to the later `new_expiration_date_in_ns`.
+    pub fn extend_expiration(&mut self, new_expiration_date_in_ns: u64) {
+        self.assert_challenge_owner();
+        assert!(!self.challenge_completed, "Challenge is already complete");
+        assert!(
+            new_expiration_date_in_ns > self.expiration_date_in_ns,
+            "The new expiration date must be later than the current one"
+        );
+        log!(
+            "Expiration date extended from {} to {}",
+            self.expiration_date_in_ns,
+            new_expiration_date_in_ns
+        );
+        self.record_mutation(
+            "extend_expiration",
+            &self.expiration_date_in_ns.clone(),
+            &new_expiration_date_in_ns,
+        );
+        self.expiration_date_in_ns = new_expiration_date_in_ns;
+    }
+
+    // Pushes the expiration date back by `additional_seconds`, sparing clients
+    // from computing nano second timestamps themselves.
+    pub fn extend_expiration_by(&mut self, additional_seconds: u64) {
+        self.assert_challenge_owner();
+        let new_expiration_date_in_ns = additional_seconds
+            .checked_mul(NS_IN_SECOND)
+            .and_then(|additional_ns| self.expiration_date_in_ns.checked_add(additional_ns))
+            .expect("The new expiration date overflows");
+        self.record_mutation(
+            "extend_expiration_by",
+            &self.expiration_date_in_ns.clone(),
+            &new_expiration_date_in_ns,
+        );
+        self.expiration_date_in_ns = new_expiration_date_in_ns;
+    }
+
+    // Updates the provided fields of the challenge details, e.g. to fix a
+    // broken media link. Either admin can make these fixes, but the terms
+    // can't change once anyone has won.
+    pub fn update_metadata(
+        &mut self,
+        name: Option<String>,
+        description: Option<String>,
+        media_link: Option<String>,
+    ) -> ChallengeMetaData {
+        self.assert_admin();
+        assert!(!self.metadata_locked, "Metadata locked");
+        assert!(
+            self.winner_count == 0,
+            "Challenge metadata can't be updated once there are winners"
+        );
+        if let Some(name) = name {
+            self.record_mutation("update_metadata.name", &self.name.clone(), &name);
+            self.name = name;
+        }
+        if let Some(description) = description {
+            self.record_mutation(
+                "update_metadata.description",
+                &self.description.clone(),
+                &description,
+            );
+            self.description = description;
+        }
+        if let Some(media_link) = media_link {
+            self.record_mutation(
+                "update_metadata.media_link",
+                &self.media_link.clone(),
+                &media_link,
+            );
+            self.media_link = media_link;
+        }
+        self.get_challenge_metadata()
+    }
+
+    // Replaces which pieces are burned on claim, e.g. to change the burn
+    // semantics before launch. Pieces that aren't burned are kept by the
+    // winner. Pieces transferred elsewhere on claim keep their action, and
+    // can't be flagged for burning.
+    pub fn set_burn_flags(&mut self, flags: Vec<bool>) {
+        self.assert_challenge_owner();
+        assert!(!self.metadata_locked, "Metadata locked");
+        assert!(
+            flags.len() as u32 == self.challenge_nft_ids.len(),
+            "{}",
+            ChallengeError::BurnFlagsLengthMismatch
+        );
+        assert!(
+            self.winner_count == 0,
+            "Burn flags can't be changed once there are winners"
+        );
+        // Claims in flight carry the pieces to burn or transfer through
+        // their callbacks, so changing the flags under them would apply
+        // the old actions to the new flags.
+        assert!(
+            self.inflight_claims == 0,
+            "Burn flags can't be changed while claims are in flight"
+        );
+        for (index, burn) in flags.iter().enumerate() {
+            if let PieceAction::TransferTo(_) = self.pieces[index as u32].action {
+                assert!(
+                    !burn,
+                    "The piece at index {} is transferred on claim, so it can't be burned",
+                    index
+                );
+            }
+        }
+        let old_flags: Vec<bool> = self
+            .pieces
+            .iter()
+            .map(|piece| piece.action == PieceAction::Burn)
+            .collect();
+        self.record_mutation("set_burn_flags", &old_flags, &flags);
+        for (index, burn) in flags.into_iter().enumerate() {
+            let piece = self.pieces.get_mut(index as u32).unwrap();
+            if let PieceAction::TransferTo(_) = piece.action {
+                continue;
+            }
+            piece.action = if burn {
+                PieceAction::Burn
+            } else {
+                PieceAction::Keep
+            };
+        }
+    }
+
+    // Sets the label of every challenge piece, in piece order.
+    pub fn set_piece_labels(&mut self, labels: Vec<String>) {
+        self.assert_challenge_owner();
+        assert!(!self.metadata_locked, "Metadata locked");
+        assert!(
+            labels.len() as u32 == self.challenge_nft_ids.len(),
+            "{}",
+            ChallengeError::PieceLabelsLengthMismatch
+        );
+        let old_labels: Vec<Option<String>> = self
+            .pieces
+            .iter()
+            .map(|piece| piece.label.clone())
+            .collect();
+        let new_labels: Vec<Option<String>> = labels.into_iter().map(Some).collect();
+        self.record_mutation("set_piece_labels", &old_labels, &new_labels);
+        for (index, label) in new_labels.into_iter().enumerate() {
+            self.pieces.get_mut(index as u32).unwrap().label = label;
+        }
+    }
+
+    // Permanently freezes the challenge details, so `update_metadata` can no
+    // longer change them.
+    pub fn lock_metadata(&mut self) {
+        self.assert_challenge_owner();
+        self.record_mutation("lock_metadata", &self.metadata_locked.clone(), &true);
+        self.metadata_locked = true;
+    }
+
+    pub fn set_co_owner(&mut self, co_owner_id: Option<AccountId>) {
+        self.assert_challenge_owner();
+        self.record_mutation("set_co_owner", &self.co_owner_id.clone(), &co_owner_id);
+        self.co_owner_id = co_owner_id;
+    }
+
+    // Proposes `new_owner_id` as the next owner. Ownership only moves once
+    // that account calls `accept_ownership`, so a mistyped account can't
+    // take over the challenge.
+    pub fn propose_new_owner(&mut self, new_owner_id: AccountId) {
+        self.assert_challenge_owner();
+        self.record_mutation(
+            "propose_new_owner",
+            &self.pending_owner_id.clone(),
+            &Some(new_owner_id.clone()),
+        );
+        self.pending_owner_id = Some(new_owner_id);
+    }
+
+    pub fn accept_ownership(&mut self) {
+        assert!(
+            self.pending_owner_id.as_ref() == Some(&env::predecessor_account_id()),
+            "Only the pending owner can accept ownership"
+        );
+        let new_owner_id = env::predecessor_account_id();
+        self.record_mutation("accept_ownership", &self.owner_id.clone(), &new_owner_id);
+        log!(
+            "Ownership transferred from {} to {}",
+            self.owner_id,
+            new_owner_id
+        );
+        self.owner_id = new_owner_id;
+        self.pending_owner_id = None;
+    }
+
+    pub fn cancel_ownership_transfer(&mut self) {
+        self.assert_challenge_owner();
+        assert!(
+            self.pending_owner_id.is_some(),
+            "No ownership transfer is pending"
+        );
+        self.record_mutation(
+            "cancel_ownership_transfer",
+            &self.pending_owner_id.clone(),
+            &None,
+        );
+        self.pending_owner_id = None;
+    }
+
+    pub fn add_delegate(&mut self, account_id: AccountId) {
+        self.assert_challenge_owner();
+        if self.delegates.insert(account_id.clone()) {
+            self.record_mutation("add_delegate", &None, &Some(account_id));
+        }
+    }
+
+    pub fn remove_delegate(&mut self, account_id: AccountId) {
+        self.assert_challenge_owner();
+        if self.delegates.remove(&account_id) {
+            self.record_mutation("remove_delegate", &Some(account_id), &None);
+        }
+    }
+
+    pub fn add_to_blacklist(&mut self, account_id: AccountId) {
+        self.assert_challenge_owner();
+        if self.blacklist.insert(account_id.clone()) {
+            self.record_mutation("add_to_blacklist", &None, &Some(account_id));
+        }
+    }
+
+    pub fn remove_from_blacklist(&mut self, account_id: AccountId) {
+        self.assert_challenge_owner();
+        if self.blacklist.remove(&account_id) {
+            self.record_mutation("remove_from_blacklist", &Some(account_id), &None);
+        }
+    }
+
+    pub fn add_mint_recipient(&mut self, account_id: AccountId) {
+        self.assert_challenge_owner();
+        if self.mint_recipient_allowlist.insert(account_id.clone()) {
+            self.record_mutation("add_mint_recipient", &None, &Some(account_id));
+        }
+    }
+
+    pub fn remove_mint_recipient(&mut self, account_id: AccountId) {
+        self.assert_challenge_owner();
+        if self.mint_recipient_allowlist.remove(&account_id) {
+            self.record_mutation("remove_mint_recipient", &Some(account_id), &None);
+        }
+    }
+
+    pub fn add_to_whitelist(&mut self, account_id: AccountId) {
+        self.assert_challenge_owner();
+        if self.whitelist.insert(account_id.clone()) {
+            self.whitelist_count += 1;
+            self.record_mutation("add_to_whitelist", &None, &Some(account_id));
+        }
+    }
+
+    // Removing the last whitelisted account opens the challenge to everyone.
+    pub fn remove_from_whitelist(&mut self, account_id: AccountId) {
+        self.assert_challenge_owner();
+        if self.whitelist.remove(&account_id) {
+            self.whitelist_count -= 1;
+            self.record_mutation("remove_from_whitelist", &Some(account_id), &None);
+        }
+    }
+
+    // Revokes the win of `account_id` if it has not minted its reward within
+    // `mint_deadline_ns_after_win` of winning, freeing its slot.
+    pub fn forfeit_unminted(&mut self, account_id: AccountId) {
+        self.assert_challenge_owner();
+        let mint_deadline_ns = self
+            .mint_deadline_ns_after_win
+            .expect("Challenge has no mint deadline");
+        let won_at = match self.winners.get(&account_id) {
+            Some(info) => info.last_won_at_ns,
+            None => panic!("Account has not won the challenge"),
+        };
+        assert!(
+            self.has_unminted_win(&account_id),
+            "Account has already minted its reward"
+        );
+        assert!(
+            env::block_timestamp() > won_at.saturating_add(mint_deadline_ns),
+            "The mint deadline has not passed yet"
+        );
+
+        self.remove_win("forfeit_unminted", &account_id);
+        log!("Forfeited the unminted win of {}", account_id);
+    }
+
+    // Revokes a win recorded against the wrong account, e.g. after a client
+    // bug, freeing its slot.
+    pub fn remove_winner(&mut self, account_id: AccountId) {
+        self.assert_challenge_owner();
+        if !self.is_account_winner(account_id.clone()) {
+            panic!("Account has not won the challenge");
+        }
+        self.remove_win("remove_winner", &account_id);
+        log!("Removed the win of {}", account_id);
+    }
+
+    // Releases the slots held by claims that started more than
+    // `older_than_ns` nano seconds ago and never resolved, e.g. because a
+    // cross-contract call was dropped.
+    pub fn sweep_stale_claims(&mut self, older_than_ns: u64) -> u64 {
+        self.assert_challenge_owner();
+        let inflight_claims = self.inflight_claims;
+        let released = self
+            .release_claims_started_before(env::block_timestamp().saturating_sub(older_than_ns));
+        self.record_mutation(
+            "sweep_stale_claims",
+            &inflight_claims,
+            &self.inflight_claims.clone(),
+        );
+        released
+    }
+
+    pub fn pause(&mut self) {
+        self.assert_admin();
+        self.record_mutation("pause", &self.paused.clone(), &true);
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.assert_admin();
+        self.record_mutation("unpause", &self.paused.clone(), &false);
+        self.paused = false;
+    }
+
+    pub fn reveal(&mut self) {
+        self.assert_challenge_owner();
+        assert!(!self.revealed, "The reward has already been revealed");
+        assert!(
+            self.revealed_reward_metadata.is_some(),
+            "This challenge has no revealed reward metadata"
+        );
+        self.record_mutation("reveal", &self.revealed.clone(), &true);
+        self.revealed = true;
+        log!("Reward revealed");
+    }
+
+    // Marks the challenge as completed once it has expired. Anyone may call
+    // this, since it only materializes a transition that has already happened.
+    pub fn ensure_challenge_not_expired(&mut self) -> bool {
+        if !self.challenge_completed && self.is_challenge_expired() {
+            self.challenge_completed = true;
+            emit_challenge_completed("expired");
+        }
+        self.challenge_completed
+    }
+
+    // Materializes every time-derived transition, completing the challenge
+    // once it has expired or every winner slot has been confirmed. Views only
+    // reflect these transitions after a mutating call, so clients should call
+    // this first. Anyone may call it and repeated calls have no further effect.
+    pub fn refresh(&mut self) -> bool {
+        if self.challenge_completed {
+            return true;
+        }
+        let reason = if self.is_challenge_expired() {
+            "expired"
+        } else if self.winner_count >= self.winner_limit {
+            "winner_limit_reached"
+        } else {
+            return false;
+        };
+        self.challenge_completed = true;
+        emit_challenge_completed(reason);
+        true
+    }
+
+    // -------------------------- private methods ---------------------------
+    #[private]
+    pub fn on_minter_checked(
+        &mut self,
+        winner_id: AccountId,
+        recipient_id: AccountId,
+        mint_deposit: NearToken,
+    ) -> Promise {
+        let is_minter = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(false)
+            }
+            PromiseResult::Failed => false,
+        };
+        if is_minter {
+            return self.start_reward_mint(winner_id, recipient_id, mint_deposit);
+        }
+        // Refund the minting fee before failing, since panicking here would
+        // leave it with this contract.
+        self.unmark_win_minted(&winner_id);
+        Promise::new(winner_id).transfer(mint_deposit).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(Gas::from_tgas(1))
+                .on_not_minter(),
+        )
+    }
+
+    #[private]
+    pub fn on_not_minter(&self) {
+        env::panic_str("Challenge contract is not an approved minter on the reward store");
+    }
+
+    // Mints the reward with exactly the deposit the reward store charges,
+    // refunding the rest of the mint deposit to the winner.
+    #[private]
+    pub fn on_storage_costs_resolved(
+        &mut self,
+        winner_id: AccountId,
+        recipient_id: AccountId,
+        royalty_args: Option<RoyaltyArgs>,
+        mint_deposit: NearToken,
+    ) -> Promise {
+        let storage_costs = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<StorageCosts>(&value).ok()
+            }
+            PromiseResult::Failed => None,
+        };
+        let Some(storage_costs) = storage_costs else {
+            log!("Could not resolve the storage costs of the reward store");
+            self.unmark_win_minted(&winner_id);
+            return Promise::new(winner_id).transfer(mint_deposit);
+        };
+        let metadata = self.reward_metadata_for(&winner_id);
+        let storage_cost = reward_mint_cost(
+            &storage_costs,
+            &metadata,
+            self.rewards_per_winner,
+            royalty_args.as_ref(),
+        );
+        if storage_cost > mint_deposit {
+            // Refund the minting fee before failing, since panicking here
+            // would leave it with this contract.
+            self.unmark_win_minted(&winner_id);
+            return Promise::new(winner_id).transfer(mint_deposit).then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(1))
+                    .on_mint_deposit_short(storage_cost),
+            );
+        }
+        let surplus = mint_deposit.saturating_sub(storage_cost);
+        if !surplus.is_zero() {
+            Promise::new(winner_id.clone()).transfer(surplus);
+        }
+        log!(
+            "Attaching {} yoctoNEAR to the reward mint",
+            storage_cost.as_yoctonear()
+        );
+        mintbase_nft::ext(self.reward_nft_id.parse().unwrap())
+            .with_static_gas(Gas::from_tgas(5))
+            .with_attached_deposit(storage_cost)
+            .nft_batch_mint(
+                recipient_id,
+                metadata,
+                self.rewards_per_winner,
+                royalty_args,
+                None,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .mint_nft_callback(winner_id, self.rewards_per_winner, storage_cost),
+            )
+    }
+
+    #[private]
+    pub fn on_mint_deposit_short(&self, storage_cost: NearToken) {
+        env::panic_str(&format!(
+            "The reward mint needs {} yoctoNEAR, more than the mint deposit",
+            storage_cost.as_yoctonear()
+        ));
+    }
+
+    #[private]
+    pub fn on_piece_owners_resolved(
+        &mut self,
+        winner_id: AccountId,
+        recipient_id: AccountId,
+        mint_deposit: NearToken,
+    ) -> Promise {
+        let mut owners: Vec<AccountId> = vec![];
+        for index in 0..self.challenge_nft_ids.len() {
+            let owner = match env::promise_result(index.into()) {
+                PromiseResult::Successful(value) => {
+                    near_sdk::serde_json::from_slice::<AccountId>(&value).ok()
+                }
+                PromiseResult::Failed => None,
+            };
+            match owner {
+                Some(owner) if !owners.contains(&owner) => owners.push(owner),
+                Some(_) => {}
+                None => {
+                    // Refund the minting fee the winner attached to `mint_nft`.
+                    log!(
+                        "Could not resolve the owner of the challenge nft at index {}",
+                        index
+                    );
+                    self.unmark_win_minted(&winner_id);
+                    return Promise::new(winner_id).transfer(mint_deposit);
+                }
+            }
+        }
+        // Split evenly, with any remainder going to the first owner.
+        let share = ROYALTY_BASIS_POINTS / owners.len() as u32;
+        let remainder = ROYALTY_BASIS_POINTS - share * owners.len() as u32;
+        let mut split_between = SplitBetweenUnparsed::new();
+        for (i, owner) in owners.into_iter().enumerate() {
+            split_between.insert(owner, if i == 0 { share + remainder } else { share });
+        }
+        self.reward_royalty_split = Some(split_between.clone());
+        self.mint_reward(
+            winner_id,
+            recipient_id,
+            Some(RoyaltyArgs {
+                split_between,
+                percentage: self.royalty_from_piece_owners.unwrap(),
+            }),
+            mint_deposit,
+        )
+    }
+
+    #[private]
+    pub fn on_preview_reward_metadata(&self, bonus_indices: Vec<u32>) -> NFTTokenMetadata {
+        let bonus_count = bonus_indices
+            .iter()
+            .enumerate()
+            .filter(
+                |(result_index, piece_index)| match env::promise_result(*result_index as u64) {
+                    PromiseResult::Successful(value) => near_sdk::serde_json::from_slice::<
+                        Vec<TokenCompliant>,
+                    >(&value)
+                    .map_or(false, |tokens| {
+                        tokens
+                            .iter()
+                            .any(|token| self.is_minted_in_time(**piece_index, token))
+                    }),
+                    PromiseResult::Failed => false,
+                },
+            )
+            .count() as u32;
+        self.reward_metadata_for_bonus_count(bonus_count)
+    }
+
+    #[private]
+    pub fn on_build_approval_calls(&self, piece_indices: Vec<u32>) -> Vec<ApprovalCall> {
+        piece_indices
+            .iter()
+            .enumerate()
+            .filter_map(|(result_index, piece_index)| {
+                let tokens = match env::promise_result(result_index as u64) {
+                    PromiseResult::Successful(value) => {
+                        near_sdk::serde_json::from_slice::<Vec<TokenCompliant>>(&value).ok()?
+                    }
+                    PromiseResult::Failed => return None,
+                };
+                // The same tokens `on_claim` would pick for the piece.
+                let tokens = self.claimed_tokens(*piece_index, tokens)?;
+                Some(
+                    tokens
+                        .into_iter()
+                        .filter(|token| !token.is_approved_for(&env::current_account_id()))
+                        .map(|token| ApprovalCall {
+                            contract: self.challenge_nft_ids[*piece_index].clone(),
+                            token_id: token.token_id,
+                            account_id: env::current_account_id(),
+                        })
+                        .collect::<Vec<ApprovalCall>>(),
+                )
+            })
+            .flatten()
+            .collect()
+    }
+
+    #[private]
+    pub fn on_resolve_piece_names(&self) -> Vec<(String, Option<String>)> {
+        self.challenge_nft_ids
+            .iter()
+            .enumerate()
+            .map(|(index, nft_id)| {
+                let name = match env::promise_result(index as u64) {
+                    PromiseResult::Successful(value) => {
+                        near_sdk::serde_json::from_slice::<NFTContractMetadataCompliant>(&value)
+                            .ok()
+                            .map(|metadata| metadata.name)
+                    }
+                    PromiseResult::Failed => None,
+                };
+                (nft_id.to_string(), name)
+            })
+            .collect()
+    }
+
+    #[private]
+    pub fn mint_nft_callback(
+        &mut self,
+        winner_id: AccountId,
+        num_minted: u64,
+        mint_deposit: NearToken,
+    ) -> bool {
+        // Reward contracts may resolve with another promise or synchronously
+        // with a value, which can be empty, so only the status of the result
+        // tells whether the mint succeeded. Contracts that return the minted
+        // token ids may have minted fewer than requested, e.g. after hitting
+        // a copies cap mid-batch.
+        let minted = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<Vec<String>>(&value)
+                    .map(|token_ids| (token_ids.len() as u64).min(num_minted))
+                    .unwrap_or(num_minted)
+            }
+            PromiseResult::Failed => 0,
+        };
+        match minted {
+            0 => {
+                // The reward contract refunded the mint deposit to this
+                // contract, so hand it back to the winner.
+                log!("There was an error minting the NFT");
+                self.unmark_win_minted(&winner_id);
+                Promise::new(winner_id).transfer(mint_deposit);
+                false
+            }
+            minted => {
+                if minted < num_minted {
+                    let refund = NearToken::from_yoctonear(
+                        mint_deposit.as_yoctonear() * (num_minted - minted) as u128
+                            / num_minted as u128,
+                    );
+                    log!(
+                        "Only {} of {} reward NFTs were minted, refunding {} yoctoNEAR",
+                        minted,
+                        num_minted,
+                        refund.as_yoctonear()
+                    );
+                    Promise::new(winner_id.clone()).transfer(refund);
+                }
+                // `mint_nft` already marked the win as minted.
+                self.rewards_minted_count += minted;
+                log!("Reward NFT minted");
+                if self.auto_end_on_full_mint && self.is_fully_distributed() {
+                    self.challenge_completed = true;
+                    emit_event(
+                        "challenge_ended",
+                        near_sdk::serde_json::json!({
+                            "winners_count": self.winner_count,
+                            "rewards_minted_count": self.rewards_minted_count,
+                        }),
+                    );
+                }
+                true
+            }
+        }
+    }
+
+    #[private]
+    pub fn on_winner_badge_minted(&mut self, winner_id: AccountId, mint_deposit: NearToken) {
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => log!("Winner badge minted for {}", winner_id),
+            PromiseResult::Failed => {
+                // The badge contract refunded the deposit, so it goes back
+                // into the budget.
+                log!("Failed to mint winner badge for {}", winner_id);
+                self.badge_budget = self.badge_budget.saturating_add(mint_deposit);
+            }
+        }
+    }
+
+    // Lets the account claim the consolation reward again, with its deposit
+    // refunded, when the mint failed.
+    #[private]
+    pub fn on_consolation_minted(
+        &mut self,
+        account_id: AccountId,
+        mint_deposit: NearToken,
+    ) -> bool {
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => {
+                log!("Consolation reward minted for {}", account_id);
+                true
+            }
+            PromiseResult::Failed => {
+                log!("Failed to mint the consolation reward for {}", account_id);
+                self.consolations_claimed.remove(&account_id);
+                Promise::new(account_id).transfer(mint_deposit);
+                false
+            }
+        }
+    }
+
+    #[private]
+    pub fn on_achievement_granted(&mut self, winner_id: AccountId) {
+        let granted = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if !granted {
+            log!("Failed to grant achievement for {}", winner_id);
+        }
+        self.achievement_grants.insert(winner_id, granted);
+    }
+
+    // -------------------------- internal methods ---------------------------
+    // Mints the reward for `winner_id` to `recipient_id`, first resolving the
+    // royalty split when royalties go to the piece owners. `mint_deposit` is
+    // what the winner was charged, refunded if the mint cannot go ahead.
+    fn start_reward_mint(
+        &self,
+        winner_id: AccountId,
+        recipient_id: AccountId,
+        mint_deposit: NearToken,
+    ) -> Promise {
+        if self.royalty_from_piece_owners.is_none() {
+            return self.mint_reward(winner_id, recipient_id, None, mint_deposit);
+        }
+
+        // Resolve the owners of the challenge piece contracts before minting,
+        // so the reward royalties can be split between them.
+        let owner_promises = self
+            .challenge_nft_ids
+            .iter()
+            .map(|nft_id| {
+                mintbase_nft::ext(nft_id.clone())
+                    .with_static_gas(Gas::from_tgas(2))
+                    .get_owner_id()
+            })
+            .reduce(|a, b| a.and(b))
+            .unwrap();
+        owner_promises.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(Gas::from_tgas(30))
+                .on_piece_owners_resolved(winner_id, recipient_id, mint_deposit),
+        )
+    }
+
+    // Looks up what the reward store charges for storage, so the mint can
+    // attach exactly that.
+    fn mint_reward(
+        &self,
+        winner_id: AccountId,
+        recipient_id: AccountId,
+        royalty_args: Option<RoyaltyArgs>,
+        mint_deposit: NearToken,
+    ) -> Promise {
+        mintbase_nft::ext(self.reward_nft_id.parse().unwrap())
+            .with_static_gas(Gas::from_tgas(2))
+            .get_storage_costs()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(15))
+                    .on_storage_costs_resolved(winner_id, recipient_id, royalty_args, mint_deposit),
+            )
+    }
+
+    fn start_claim(&mut self, holder_id: AccountId, winner_id: AccountId) -> Promise {
+        self.assert_claim_deposit();
+
+        self.reclaim_abandoned_claims();
+
+        if self.potential_winners_left == 0 {
+            panic!(
+                "{}",
+                message_or(
+                    &self.custom_messages.full,
+                    "Challenge currently at max potential winners"
+                )
+            );
+        }
+
+        if self.winner_count >= self.winner_limit {
+            panic!(
+                "{}",
+                message_or(
+                    &self.custom_messages.full,
+                    "Challenge is not accepting any more winners"
+                )
+            );
+        }
+
+        self.assert_claim_open(&winner_id);
+        self.throttle_claims();
+        self.count_attempt(&winner_id);
+
+        self.gate_claim(
+            holder_id,
+            winner_id,
+            Some(env::predecessor_account_id()),
+            None,
+        )
+    }
+
+    fn assert_claim_deposit(&self) {
+        // Need 2 YOCOTNEAR per challenge NFT token to claim the challenge, plus the claim fee.
+        let required_deposit = self
+            .pieces
+            .iter()
+            .map(|piece| piece.burn_quantity as u128 * 2)
+            .sum::<u128>()
+            + self.claim_fee.as_yoctonear();
+        if env::attached_deposit().as_yoctonear() < required_deposit {
+            panic!(
+                "You must attach at least {} YOCTONEAR to claim the challenge",
+                required_deposit
+            );
+        }
+    }
+
+    // Runs the checks every claim goes through before its ownership is
+    // verified, whether it was started directly, from a reservation or by
+    // the last claim stage. `payer_id` paid the claim fee, if one was
+    // charged. `claim_started_at` is set when the claim already holds a
+    // slot, which a failed check then releases.
+    fn gate_claim(
+        &mut self,
+        holder_id: AccountId,
+        winner_id: AccountId,
+        payer_id: Option<AccountId>,
+        claim_started_at: Option<u64>,
+    ) -> Promise {
+        if let Some((ft_contract_id, _)) = &self.ft_gate {
+            // The balance is checked before anything else, so a claim below
+            // the threshold never takes a slot.
+            return ft_contract::ext(ft_contract_id.clone())
+                .with_static_gas(Gas::from_tgas(5))
+                .ft_balance_of(winner_id.clone())
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas::from_tgas(60))
+                        .on_ft_gate_checked(holder_id, winner_id, payer_id, claim_started_at),
+                );
+        }
+
+        self.dispatch_claim(holder_id, winner_id, payer_id, claim_started_at)
+    }
+
+    // Starts the ownership checks for a claim that passed the fungible token
+    // gate.
+    fn dispatch_claim(
+        &mut self,
+        holder_id: AccountId,
+        winner_id: AccountId,
+        payer_id: Option<AccountId>,
+        claim_started_at: Option<u64>,
+    ) -> Promise {
+        if self.require_pre_approval {
+            // No slot is taken until the approvals are confirmed, so a claim
+            // missing one is rejected without a round-trip through the slot
+            // accounting.
+            return self.query_ownership(&holder_id).then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .on_pre_approval_check(holder_id, winner_id, payer_id, claim_started_at),
+            );
+        }
+
+        // To ensure we don't have more winners than the winner limit.
+        //
+        // A panic anywhere in this method, including after the slot is taken,
+        // reverts every state change made by the transaction, so the slot
+        // can't leak synchronously. Once the ownership checks are dispatched,
+        // the slot is released by the claim callbacks when the claim fails.
+        // The only way to leak it is for a callback itself to fail, e.g. by
+        // running out of gas, which leaves the claim in `pending_claims` for
+        // the owner to release with `sweep_stale_claims`.
+        let claim_started_at =
+            claim_started_at.unwrap_or_else(|| self.take_claim_slot(&winner_id, payer_id));
+
+        self.verify_ownership(holder_id, winner_id, claim_started_at)
+    }
+
+    // Takes a winner slot for the claim of `winner_id`, holding the claim fee
+    // paid by `payer_id` until the claim resolves. Returns the timestamp the
+    // claim started at, which its callbacks carry to check it is still
+    // pending.
+    fn take_claim_slot(&mut self, winner_id: &AccountId, payer_id: Option<AccountId>) -> u64 {
+        self.decrement_winners();
+        self.pending_claims
+            .insert(winner_id.clone(), env::block_timestamp());
+        if let Some(payer_id) = payer_id {
+            if !self.claim_fee.is_zero() {
+                self.pending_claim_fees
+                    .insert(winner_id.clone(), (payer_id, self.claim_fee));
+            }
+        }
+        env::block_timestamp()
+    }
+
+    // Rejects a claim that failed a gate check with `outcome`, releasing its
+    // slot if it holds one and refunding the claim fee either way.
+    fn reject_gated_claim(
+        &mut self,
+        winner_id: AccountId,
+        payer_id: Option<AccountId>,
+        holds_slot: bool,
+        outcome: u8,
+    ) {
+        if holds_slot {
+            self.record_failed_claim(winner_id, outcome);
+        } else {
+            self.refund_claim_fee(payer_id);
+            self.record_attempt(winner_id, outcome);
+        }
+    }
+
+    // Whether the claim of `winner_id` that started at `claim_started_at`
+    // still holds its slot. A claim released by `sweep_stale_claims` or the
+    // claim TTL while its callbacks were in flight must not resolve, even if
+    // the account has started another claim since.
+    fn is_claim_pending(&self, winner_id: &AccountId, claim_started_at: u64) -> bool {
+        self.pending_claims.get(winner_id) == Some(&claim_started_at)
+    }
+
+    // The pieces a claim moved into this contract to burn, out of the
+    // transfers whose results the current callback receives.
+    fn transferred_burn_tokens(
+        &self,
+        piece_indices: &[u32],
+        token_ids: &[String],
+    ) -> Vec<NftToken> {
+        (0..token_ids.len())
+            .filter(|j| self.pieces[piece_indices[*j]].action == PieceAction::Burn)
+            .filter(|j| matches!(env::promise_result(*j as u64), PromiseResult::Successful(_)))
+            .map(|j| NftToken {
+                nft_id: self.challenge_nft_ids[piece_indices[j]].clone(),
+                token_id: token_ids[j].clone(),
+            })
+            .collect()
+    }
+
+    // Hands `tokens` held by this contract back to `holder_id`. Tokens that
+    // fail to transfer are kept in the stuck token registry.
+    fn return_tokens(&self, holder_id: &AccountId, tokens: Vec<NftToken>) {
+        let return_promise = tokens
+            .iter()
+            .map(|token| {
+                mintbase_nft::ext(token.nft_id.clone())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .with_attached_deposit(NearToken::from_yoctonear(1))
+                    .nft_transfer(holder_id.clone(), token.token_id.clone(), None, None)
+            })
+            .reduce(|a, b| a.and(b));
+        if let Some(return_promise) = return_promise {
+            log!("Returning unburned NFTs to {}", holder_id);
+            return_promise.then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .on_unburned_returned(tokens),
+            );
+        }
+    }
+
+    // Refunds the claim fee to `payer_id` for a claim rejected before it
+    // took a slot.
+    fn refund_claim_fee(&self, payer_id: Option<AccountId>) {
+        if let Some(payer_id) = payer_id {
+            if !self.claim_fee.is_zero() {
+                Promise::new(payer_id).transfer(self.claim_fee);
+            }
+        }
+    }
+
+    // Checks that `holder_id` owns every challenge piece, resolving the claim
+    // for `winner_id` in `on_claim`.
+    fn verify_ownership(
+        &self,
+        holder_id: AccountId,
+        winner_id: AccountId,
+        claim_started_at: u64,
+    ) -> Promise {
+        self.query_ownership(&holder_id).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(Gas::from_tgas(5))
+                .on_claim(
+                    holder_id,
+                    winner_id,
+                    self.ownership_contracts().len() as u64,
+                    claim_started_at,
+                ),
+        )
+    }
+
+    // Queries the tokens `holder_id` owns on every challenge piece contract,
+    // once per contract in the order of `ownership_contracts`.
+    fn query_ownership(&self, holder_id: &AccountId) -> Promise {
+        self.ownership_contracts()
+            .iter()
+            .map(|x| {
+                mintbase_nft::ext(x.clone())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .nft_tokens_for_owner(holder_id.clone(), None, None)
+            })
+            .reduce(|a, b| a.and(b))
+            // Should never hit because we always have at least 1 challenge
+            .expect("Error in the challenge nft ownership promises")
+    }
+
+    // Panics if `winner_id` cannot currently start a claim.
+    fn assert_claim_open(&mut self, winner_id: &AccountId) {
+        if self.paused {
+            panic!("Challenge is paused");
+        }
+
+        // Blocked accounts can neither claim nor be claimed for, whatever
+        // other access they have been granted.
+        if self.blacklist.contains(&env::predecessor_account_id())
+            || self.blacklist.contains(winner_id)
+        {
+            panic!("Claims from this account are blocked");
+        }
+
+        if self.whitelist_count > 0 && !self.whitelist.contains(winner_id) {
+            panic!("Account not whitelisted");
+        }
+
+        match self.get_phase() {
+            ChallengePhase::Completed => panic!("Challenge is over"),
+            ChallengePhase::NotStarted => panic!("Challenge has not started yet"),
+            ChallengePhase::Expired => panic!(
+                "{}",
+                message_or(&self.custom_messages.expired, "Challenge is expired")
+            ),
+            // Slot availability is checked by the callers, since a staged
+            // claim keeps verifying after it has taken the last slot.
+            ChallengePhase::Active | ChallengePhase::Full => {}
+        }
+
+        if env::block_timestamp() < self.cooldown_ends_at(winner_id) {
+            panic!("Claims from this account are cooling down");
+        }
+
+        if self.is_account_winner(winner_id.clone()) {
+            if !self.allow_repeat_wins {
+                panic!(
+                    "{}",
+                    message_or(
+                        &self.custom_messages.already_won,
+                        "You have already won this challenge"
+                    )
+                );
+            }
+            assert!(
+                !self.has_unminted_win(winner_id),
+                "Mint the reward of your last win before claiming again"
+            );
+        }
+
+        if self.pending_claims.contains_key(winner_id) {
+            panic!("You already have a claim in progress");
+        }
+
+        if self.reservations.contains_key(winner_id) {
+            panic!("You already have a reserved slot, complete it with complete_reservation");
+        }
+    }
+
+    // When the cooldown after the last claim of `account_id` ends, or 0 if it
+    // has none.
+    fn cooldown_ends_at(&self, account_id: &AccountId) -> u64 {
+        match (self.claim_cooldown_ns, self.last_receipts.get(account_id)) {
+            (Some(claim_cooldown_ns), Some(receipt)) => {
+                receipt.resolved_at.saturating_add(claim_cooldown_ns)
+            }
+            _ => 0,
+        }
+    }
+
+    // Counts a claim started for `account_id`, panicking once it has used up
+    // `max_attempts_per_account`.
+    fn count_attempt(&mut self, account_id: &AccountId) {
+        let attempts = self.get_attempt_count(account_id.clone());
+        if let Some(max_attempts) = self.max_attempts_per_account {
+            if attempts >= max_attempts {
+                panic!("Attempt limit reached");
+            }
+        }
+        self.attempts_per_account
+            .insert(account_id.clone(), attempts + 1);
+    }
+
+    // Releases the slots of claims and reservations that have been in flight
+    // for longer than `claim_ttl_ns`, so abandoned ones don't need a sweeper.
+    fn reclaim_abandoned_claims(&mut self) {
+        let claim_ttl_ns = match self.claim_ttl_ns {
+            Some(claim_ttl_ns) => claim_ttl_ns,
+            None => return,
+        };
+        self.release_claims_started_before(env::block_timestamp().saturating_sub(claim_ttl_ns));
+        self.release_expired_reservations();
+    }
+
+    fn release_claims_started_before(&mut self, cutoff: u64) -> u64 {
+        let stale_claims: Vec<AccountId> = self
+            .pending_claims
+            .iter()
+            .filter(|(_, started_at)| **started_at < cutoff)
+            .map(|(account_id, _)| account_id.clone())
+            .collect();
+        for account_id in stale_claims.iter() {
+            log!("Releasing stale claim for {}", account_id);
+            self.record_failed_claim(account_id.clone(), CLAIM_OUTCOME_STALE);
+        }
+        stale_claims.len() as u64
+    }
+
+    // Counts a claim against the current block, rejecting it once the block
+    // has reached `max_claims_per_block`.
+    fn throttle_claims(&mut self) {
+        let max_claims_per_block = match self.max_claims_per_block {
+            Some(max_claims_per_block) => max_claims_per_block,
+            None => return,
+        };
+        let block_height = env::block_height();
+        if self.claims_in_block.0 != block_height {
+            self.claims_in_block = (block_height, 0);
+        }
+        if self.claims_in_block.1 >= max_claims_per_block {
+            panic!("Claims are rate limited, retry next block");
+        }
+        self.claims_in_block.1 += 1;
+    }
+
+    // The range of challenge piece indices verified by a claim stage.
+    fn claim_stage_bounds(&self, stage: u32) -> (u32, u32) {
+        let max_pieces = self
+            .max_pieces_per_claim_tx
+            .unwrap_or(self.challenge_nft_ids.len());
+        let start = stage * max_pieces;
+        (
+            start,
+            (start + max_pieces).min(self.challenge_nft_ids.len()),
+        )
+    }
+
+    // Identifies this challenge in win proofs.
+    fn challenge_hash(&self) -> Vec<u8> {
+        let mut preimage = format!("{}\n", env::current_account_id());
+        for nft_id in self.challenge_nft_ids.iter() {
+            preimage.push_str(nft_id.as_str());
+            preimage.push('\n');
+        }
+        env::sha256(preimage.as_bytes())
+    }
+
+    // Whether `account_id` has a win whose reward has not been minted yet,
+    // or is not being minted.
+    fn has_unminted_win(&self, account_id: &AccountId) -> bool {
+        let wins = self.winners.get(account_id).map_or(0, |info| info.count);
+        let wins_minted = self.wins_minted.get(account_id).copied().unwrap_or(0);
+        wins > wins_minted
+    }
+
+    // Rolls back the mark `mint_nft` put on a win of `winner_id` once its
+    // mint failed, so the reward can be minted again.
+    fn unmark_win_minted(&mut self, winner_id: &AccountId) {
+        match self.wins_minted.get(winner_id).copied() {
+            Some(wins_minted) if wins_minted > 1 => {
+                self.wins_minted.insert(winner_id.clone(), wins_minted - 1);
+            }
+            // The win may have been removed while the mint was in flight.
+            Some(_) => {
+                self.wins_minted.remove(winner_id);
+            }
+            None => {}
+        }
+    }
+
+    // Whether `token` was minted late enough to count as the challenge piece at
+    // `index`. Tokens that don't report a mint time never satisfy a minimum.
+    fn is_minted_in_time(&self, index: u32, token: &TokenCompliant) -> bool {
+        match self.pieces[index].min_mint_time_ns {
+            Some(min_mint_time_ns) => token
+                .mint_time_ns()
+                .map_or(false, |mint_time_ns| mint_time_ns >= min_mint_time_ns),
+            None => true,
+        }
+    }
+
+    // The tokens a claim uses as the piece at `index`, picked from `tokens` in
+    // order, or None when fewer than the piece needs were minted in time.
+    fn claimed_tokens(
+        &self,
+        index: u32,
+        tokens: Vec<TokenCompliant>,
+    ) -> Option<Vec<TokenCompliant>> {
+        let piece = &self.pieces[index];
+        let quantity = match piece.action {
+            PieceAction::Keep => 1,
+            _ => piece.burn_quantity as usize,
+        };
+        let tokens: Vec<TokenCompliant> = tokens
+            .into_iter()
+            .filter(|token| self.is_minted_in_time(index, token))
+            .take(quantity)
+            .collect();
+        if tokens.len() < quantity {
+            return None;
+        }
+        Some(tokens)
+    }
+
+    // The distinct challenge piece contracts, in the order of their first
+    // piece.
+    fn ownership_contracts(&self) -> Vec<AccountId> {
+        let mut contracts: Vec<AccountId> = vec![];
+        for nft_id in self.challenge_nft_ids.iter() {
+            if !contracts.contains(nft_id) {
+                contracts.push(nft_id.clone());
+            }
+        }
+        contracts
+    }
+
+    // Splits the tokens returned by the first `number_promises` ownership
+    // queries between the challenge pieces, in piece order. Pieces on the
+    // same contract each need tokens of their own. A piece is `None` when
+    // the holder doesn't own enough matching tokens for it.
+    fn claimed_pieces(&self, number_promises: u64) -> Vec<Option<Vec<TokenCompliant>>> {
+        let contracts = self.ownership_contracts();
+        let mut owned_tokens: Vec<Option<Vec<TokenCompliant>>> = (0..number_promises)
+            .map(|index| match env::promise_result(index) {
+                PromiseResult::Successful(value) => {
+                    near_sdk::serde_json::from_slice::<Vec<TokenCompliant>>(&value).ok()
+                }
+                PromiseResult::Failed => None,
+            })
+            .collect();
+        self.challenge_nft_ids
+            .iter()
+            .enumerate()
+            .map(|(index, nft_id)| {
+                let position = contracts.iter().position(|contract| contract == nft_id)?;
+                let tokens = owned_tokens.get_mut(position)?.as_mut()?;
+                let claimed = self.claimed_tokens(index as u32, tokens.clone())?;
+                tokens.retain(|token| {
+                    !claimed
+                        .iter()
+                        .any(|claimed_token| claimed_token.token_id == token.token_id)
+                });
+                Some(claimed)
+            })
+            .collect()
+    }
+
+    fn observe_piece_ownership(&mut self, index: u32) {
+        if let Some(observations) = self.piece_ownership_observations.get_mut(index) {
+            *observations += 1;
+        }
+    }
+
+    // Whether every winner slot has been won and every winner has minted
+    // their rewards. A reward total that overflows can never be reached.
+    fn is_fully_distributed(&self) -> bool {
+        self.winner_count >= self.winner_limit
+            && self
+                .winner_limit
+                .checked_mul(self.rewards_per_winner)
+                .map_or(false, |total_rewards| {
+                    self.rewards_minted_count >= total_rewards
+                })
+    }
+
+    fn decrement_winners(&mut self) {
+        self.potential_winners_left = self
+            .potential_winners_left
+            .checked_sub(1)
+            .expect("Cannot take a winner slot, none are left");
+        self.inflight_claims = self
+            .inflight_claims
+            .checked_add(1)
+            .expect("Too many claims in flight");
+        if self.potential_winners_left == 0 {
+            self.emit_slots_event("slots_exhausted");
+        }
+    }
+
+    // Gives a winner slot back. There are never more slots than the winner
+    // limit, so a release that wasn't matched by a take can't inflate them.
+    fn increment_winners(&mut self) {
+        self.potential_winners_left = self
+            .potential_winners_left
+            .checked_add(1)
+            .expect("Too many winner slots")
+            .min(self.winner_limit);
+        self.inflight_claims = self.inflight_claims.saturating_sub(1);
+        if self.potential_winners_left == 1 {
+            self.emit_slots_event("slots_available");
+        }
+    }
+
+    // Logs a transition of `potential_winners_left` to or from zero.
+    fn emit_slots_event(&self, event: &str) {
+        emit_event(
+            event,
+            near_sdk::serde_json::json!({
+                "potential_winners_left": self.potential_winners_left,
+                "winners_count": self.winner_count,
+            }),
+        );
+    }
+
+    fn current_reward_metadata(&self) -> NFTTokenMetadata {
+        match (&self.revealed_reward_metadata, self.revealed) {
+            (Some(revealed_metadata), true) => revealed_metadata.clone(),
+            _ => self.reward_nft_metadata.clone(),
+        }
+    }
+
+    // The reward minted to `account_id`, upgraded to a bonus tier if it owned
+    // bonus pieces when it won.
+    fn reward_metadata_for(&self, account_id: &AccountId) -> NFTTokenMetadata {
+        let bonus_count = self
+            .bonus_pieces_owned
+            .get(account_id)
+            .copied()
+            .unwrap_or(0);
+        self.reward_metadata_for_bonus_count(bonus_count)
+    }
+
+    // The reward minted to winners owning `bonus_count` bonus pieces.
+    fn reward_metadata_for_bonus_count(&self, bonus_count: u32) -> NFTTokenMetadata {
+        if bonus_count == 0 || self.bonus_reward_metadata.is_empty() {
+            return self.current_reward_metadata();
+        }
+        let tier = bonus_count.min(self.bonus_reward_metadata.len()) - 1;
+        self.bonus_reward_metadata[tier].clone()
+    }
+
+    // Undoes every `record_winner` for `account_id` and gives its slots back,
+    // recording the removal under `method`.
+    fn remove_win(&mut self, method: &str, account_id: &AccountId) {
+        let info = self
+            .winners
+            .remove(account_id)
+            .expect("Account has not won the challenge");
+        self.record_mutation(method, &Some(info.clone()), &None);
+        self.wins_minted.remove(account_id);
+        self.last_receipts.remove(account_id);
+        self.bonus_pieces_owned.remove(account_id);
+        // Shift the later winners down to keep the win order intact.
+        if let Some(position) = self
+            .winners_in_order
+            .iter()
+            .position(|winner_id| winner_id == account_id)
+        {
+            for i in position as u32..self.winners_in_order.len() - 1 {
+                let next_winner_id = self.winners_in_order[i + 1].clone();
+                self.winners_in_order.replace(i, next_winner_id);
+            }
+            self.winners_in_order.pop();
+        }
+        let was_full = self.potential_winners_left == 0;
+        self.winner_count = self
+            .winner_count
+            .checked_sub(info.count)
+            .expect("Challenge has fewer winners than the account's wins");
+        self.potential_winners_left = self
+            .potential_winners_left
+            .checked_add(info.count)
+            .expect("Too many winner slots")
+            .min(self.winner_limit);
+        if was_full && self.potential_winners_left > 0 {
+            self.emit_slots_event("slots_available");
+        }
+    }
+
+    fn record_winner(&mut self, winner_id: AccountId) {
+        self.winner_count += 1;
+        self.inflight_claims = self.inflight_claims.saturating_sub(1);
+        let info = match self.winners.get(&winner_id) {
+            Some(info) => WinnerInfo {
+                count: info.count + 1,
+                first_won_at_ns: info.first_won_at_ns,
+                last_won_at_ns: env::block_timestamp(),
+            },
+            None => WinnerInfo {
+                count: 1,
+                first_won_at_ns: env::block_timestamp(),
+                last_won_at_ns: env::block_timestamp(),
+            },
+        };
+        // Repeat winners keep the position of their first win.
+        if info.count == 1 {
+            self.winners_in_order.push(winner_id.clone());
+        }
+        self.winners.insert(winner_id.clone(), info);
+        self.pending_claims.remove(&winner_id);
+        // Winners' claim fees go to the challenge owner.
+        if let Some((_, fee)) = self.pending_claim_fees.remove(&winner_id) {
+            Promise::new(self.owner_id.clone()).transfer(fee);
+        }
+        if let Some(referral) = self.pending_referrals.remove(&winner_id) {
+            let referral_count = self.get_referral_count(referral.clone());
+            self.referrals.insert(referral, referral_count + 1);
+        }
+        self.mint_winner_badge(winner_id.clone());
+        self.grant_achievement(winner_id.clone());
+        emit_challenge_won(&winner_id, &self.name, self.winner_count);
+        self.record_attempt(winner_id, CLAIM_OUTCOME_WON);
+    }
+
+    // Mints a proof-of-win badge to the winner, paid for from the badge
+    // budget. The badge is best-effort, so a failed or unfunded mint is only
+    // logged and never affects the win.
+    fn mint_winner_badge(&mut self, winner_id: AccountId) {
+        let badge_nft_id = match &self.winner_badge_nft_id {
+            Some(badge_nft_id) => badge_nft_id.clone(),
+            None => return,
+        };
+        let metadata = NFTTokenMetadata {
+            title: Some(format!("{} winner", self.name)),
+            description: Some(format!(
+                "Awarded to {} for winning {}",
+                winner_id, self.name
+            )),
+            media: Some(self.media_link.clone()),
+            media_hash: None,
+            copies: None,
+            expires_at: None,
+            starts_at: None,
+            extra: None,
+            reference: None,
+            reference_hash: None,
+        };
+        let mint_deposit = mint_storage_cost(&metadata, 1);
+        if self.badge_budget < mint_deposit {
+            log!(
+                "The winner badge budget can't cover the badge for {}",
+                winner_id
+            );
+            return;
+        }
+        self.badge_budget = self.badge_budget.saturating_sub(mint_deposit);
+        mintbase_nft::ext(badge_nft_id)
+            .with_static_gas(Gas::from_tgas(5))
+            .with_attached_deposit(mint_deposit)
+            .nft_batch_mint(winner_id.clone(), metadata, 1, None, None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .on_winner_badge_minted(winner_id, mint_deposit),
+            );
+    }
+
+    // Stamps the win into the achievements contract. Like the winner badge,
+    // the grant is best-effort and a failure never affects the win.
+    fn grant_achievement(&self, winner_id: AccountId) {
+        let achievements_contract = match &self.achievements_contract {
+            Some(achievements_contract) => achievements_contract.clone(),
+            None => return,
+        };
+        achievements::ext(achievements_contract)
+            .with_static_gas(Gas::from_tgas(5))
+            .grant_achievement(winner_id.clone(), self.challenge_hash().into())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas::from_tgas(5))
+                    .on_achievement_granted(winner_id),
+            );
+    }
+
+    fn record_failed_claim(&mut self, account_id: AccountId, outcome: u8) {
+        self.increment_winners();
+        self.pending_claims.remove(&account_id);
+        // Refund the claim fee to whoever paid it, since the account did not win.
+        if let Some((payer_id, fee)) = self.pending_claim_fees.remove(&account_id) {
+            Promise::new(payer_id).transfer(fee);
+        }
+        self.pending_referrals.remove(&account_id);
+        self.record_attempt(account_id, outcome);
+    }
+
+    fn record_attempt(&mut self, account: AccountId, outcome: u8) {
+        self.total_attempts += 1;
+        self.last_receipts.insert(
+            account.clone(),
+            ClaimReceipt {
+                won: outcome == CLAIM_OUTCOME_WON,
+                outcome,
+                resolved_at: env::block_timestamp(),
+            },
+        );
+        let attempt = ClaimAttempt {
+            account,
+            ts: env::block_timestamp(),
+            outcome,
+        };
+        push_ring_buffer(
+            &mut self.recent_attempts,
+            &mut self.recent_attempts_head,
+            MAX_RECENT_ATTEMPTS,
+            attempt,
+        );
+    }
+
+    fn record_mutation<T: Serialize>(&mut self, method: &str, old_value: &T, new_value: &T) {
+        let record = MutationRecord {
+            method: method.to_string(),
+            old_value: near_sdk::serde_json::to_string(old_value).unwrap(),
+            new_value: near_sdk::serde_json::to_string(new_value).unwrap(),
+            timestamp: env::block_timestamp(),
+            caller: env::predecessor_account_id(),
+        };
+        push_ring_buffer(
+            &mut self.mutation_log,
+            &mut self.mutation_log_head,
+            MAX_MUTATION_RECORDS,
+            record,
+        );
+    }
+
+    fn assert_challenge_owner(&self) {
+        assert!(
+            self.owner_id == env::predecessor_account_id(),
+            "This method can only be called by the challenge owner"
+        );
+    }
+
+    // Asserts the caller is either the challenge owner or its co-owner.
+    fn assert_admin(&self) {
+        let caller = env::predecessor_account_id();
+        assert!(
+            self.owner_id == caller || self.co_owner_id.as_ref() == Some(&caller),
+            "This method can only be called by the challenge owner or co-owner"
+        );
+    }
+}
+
+// Logs how much of the prepaid gas a claim used by the time it resolved, so
+// creators can size the gas they attach to claims.
+fn claim_resolved(result: ClaimResult) -> ClaimResult {
+    log!(
+        "Claim resolved using {} of {} prepaid gas",
+        env::used_gas().as_gas(),
+        env::prepaid_gas().as_gas()
+    );
+    result
+}
+
+// Resolves a claim of `winner_id` that was released while its callbacks were
+// in flight. The release already refunded the fee and freed the slot.
+fn claim_released(winner_id: &AccountId) -> ClaimResult {
+    log!("The claim of {} was released before it resolved", winner_id);
+    claim_resolved(ClaimResult::Released)
+}
+
+// Panics unless the callback received exactly `expected` promise results,
+// rather than letting a mismatch index past them or skip some.
+fn assert_promise_results_count(expected: usize) {
+    let count = env::promise_results_count();
+    assert!(
+        count == expected as u64,
+        "Expected {} promise results, but got {}",
+        expected,
+        count
+    );
+}
+
+// The custom message if one was configured, otherwise `default`.
+fn message_or(custom: &Option<String>, default: &str) -> String {
+    custom.clone().unwrap_or_else(|| default.to_string())
+}
+
+// The storage deposit Mintbase charges to mint `num_to_mint` tokens sharing
+// `metadata`.
+fn mint_storage_cost(metadata: &NFTTokenMetadata, num_to_mint: u64) -> NearToken {
+    let metadata_bytes = borsh::to_vec(metadata).unwrap().len() as u128;
+    let storage_bytes =
+        MINT_COMMON_STORAGE_BYTES + metadata_bytes + MINT_TOKEN_STORAGE_BYTES * num_to_mint as u128;
+    env::storage_byte_cost().saturating_mul(storage_bytes)
+}
+
+// The storage deposit a Mintbase store with `storage_costs` charges to mint
+// `num_to_mint` tokens sharing `metadata`, with every royalty entry stored
+// like a mint call.
+fn reward_mint_cost(
+    storage_costs: &StorageCosts,
+    metadata: &NFTTokenMetadata,
+    num_to_mint: u64,
+    royalty_args: Option<&RoyaltyArgs>,
+) -> NearToken {
+    let metadata_bytes = borsh::to_vec(metadata).unwrap().len() as u128;
+    let royalty_entries = royalty_args.map_or(0, |args| args.split_between.len()) as u128;
+    NearToken::from_yoctonear(
+        storage_costs.common.0 * (1 + royalty_entries)
+            + storage_costs.storage_price_per_byte.0 * metadata_bytes
+            + storage_costs.token.0 * num_to_mint as u128,
+    )
+}
+
+// Compares two byte strings without returning early on the first mismatch.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// The commitment a `WinProof` attests to.
+fn win_proof_commitment(challenge_hash: &[u8], account_id: &AccountId, won_at: u64) -> Vec<u8> {
+    let mut preimage = challenge_hash.to_vec();
+    preimage.extend_from_slice(account_id.as_bytes());
+    preimage.extend_from_slice(&won_at.to_le_bytes());
+    env::sha256(&preimage)
+}
+
+// Appends `item` to a log capped at `cap` entries, overwriting the oldest
+// entry once the log is full.
+fn push_ring_buffer<T: BorshSerialize + BorshDeserialize>(
+    log: &mut Vector<T>,
+    head: &mut u32,
+    cap: u32,
+    item: T,
+) {
+    if log.len() < cap {
+        log.push(item);
+    } else {
+        log.replace(*head, item);
+        *head = (*head + 1) % cap;
+    }
+}
+
+// Reads entries of a log written by `push_ring_buffer`, from oldest to newest.
+fn read_ring_buffer<T: BorshSerialize + BorshDeserialize + Clone>(
+    log: &Vector<T>,
+    head: u32,
+    from: u32,
+    limit: u32,
+) -> Vec<T> {
+    let len = log.len();
+    (from..len.min(from.saturating_add(limit)))
+        .map(|i| log[(head + i) % len].clone())
+        .collect()
+}
+
+/*
+ * The rest of this file holds the inline tests for the code above
+ * Learn more about Rust tests: https://doc.rust-lang.org/book/ch11-01-writing-tests.html
+ */
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, str::FromStr};
+
+    use near_sdk::{
+        test_utils::{get_logs, VMContextBuilder},
+        testing_env,
+    };
+
+    use super::*;
+    #[test]
+    #[should_panic]
+    fn default_nft_challenge() {
+        Contract::default();
+    }
+
+    #[test]
+    fn new() -> Contract {
+        Contract::new(ChallengeConfig {
+            owner_id: "owner_id".to_string(),
+            name: "name".to_string(),
+            description: "description".to_string(),
+            media_link: "media_link".to_string(),
+            reward_nft_id: "reward_nft".to_string(),
+            challenge_nft_ids: vec![
+                "challenge_nft_id1".to_string(),
+                "challenge_nft_id2".to_string(),
+            ],
+            burn_challenge_piece_on_claim: vec![true, false],
+            expiration_date_in_ns: 1000000000000,
+            winner_limit: 1,
+            creator_can_update: true,
+            reward_nft_metadata: NFTTokenMetadata {
+                title: None,
+                description: None,
+                media: None,
+                copies: None,
+                expires_at: None,
+                starts_at: None,
+                extra: None,
+                reference: None,
+                reference_hash: None,
+                media_hash: None,
+            },
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn get_challenge_metadata() {
+        let challenge = new();
+        let metadata = challenge.get_challenge_metadata();
+        assert_eq!(metadata.owner_id, "owner_id");
+        assert_eq!(metadata.name, "name");
+        assert_eq!(metadata.description, "description");
+        assert_eq!(metadata.media_link.unwrap(), "media_link");
+        assert_eq!(metadata.reward_nft_id, "reward_nft");
+        assert_eq!(metadata.challenge_nft_ids[0], "challenge_nft_id1");
+        assert_eq!(metadata.challenge_nft_ids[1], "challenge_nft_id2");
+        assert_eq!(metadata.burn_challenge_piece_on_claim[0], true);
+        assert_eq!(metadata.burn_challenge_piece_on_claim[1], false);
+        assert_eq!(metadata.piece_actions[0], PieceAction::Burn);
+        assert_eq!(metadata.piece_actions[1], PieceAction::Keep);
+        assert_eq!(metadata.challenge_nft_ids.len(), 2);
+        assert_eq!(metadata.expiration_date_in_ns, 1000000000000);
+        assert_eq!(metadata.winner_limit, 1);
+        assert_eq!(metadata.challenge_completed, false);
+        assert_eq!(metadata.winners_count, 0);
+        assert_eq!(metadata.rewards_per_winner, 1);
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut challenge = new();
+        challenge.record_winner(AccountId::from_str("account_id").unwrap());
+        testing_env!(VMContextBuilder::new()
+            .block_index(42)
+            .block_timestamp(1000)
+            .build());
+        let snapshot = challenge.snapshot();
+        assert_eq!(snapshot.block_height, 42);
+        assert_eq!(snapshot.block_timestamp, 1000);
+        assert_eq!(snapshot.metadata, challenge.get_challenge_metadata());
+        assert_eq!(snapshot.capacity, challenge.get_capacity());
+        assert_eq!(snapshot.phase, challenge.get_phase());
+        assert_eq!(
+            snapshot.supported_standards,
+            challenge.supported_standards()
+        );
+        assert_eq!(snapshot.is_paused, challenge.is_paused());
+        assert_eq!(snapshot.is_minting_frozen, challenge.is_minting_frozen());
+        assert_eq!(
+            snapshot.effective_completion,
+            challenge.get_effective_completion()
+        );
+        assert_eq!(
+            snapshot.rewards_minted_count,
+            challenge.get_rewards_minted_count()
+        );
+        assert_eq!(
+            snapshot.claim_is_destructive,
+            challenge.claim_is_destructive()
+        );
+        assert_eq!(
+            snapshot.burn_transfer_deposit,
+            challenge.get_burn_transfer_deposit()
+        );
+        assert_eq!(
+            snapshot.claim_stage_count,
+            challenge.get_claim_stage_count()
+        );
+        assert_eq!(snapshot.has_stuck_tokens, challenge.has_stuck_tokens());
+        assert_eq!(snapshot.self_check, challenge.self_check());
+    }
+
+    #[test]
+    fn get_challenge_metadata_borsh() {
+        let challenge = new();
+        let encoded = challenge.get_challenge_metadata_borsh();
+        let decoded: ChallengeMetaData = borsh::from_slice(&encoded.0).unwrap();
+        assert_eq!(decoded, challenge.get_challenge_metadata());
+    }
+
+    fn try_new(
+        owner_id: &str,
+        challenge_nft_ids: Vec<&str>,
+        burn_challenge_piece_on_claim: Vec<bool>,
+        expiration_date_in_ns: u64,
+        piece_actions: Option<Vec<PieceAction>>,
+    ) -> Result<Contract, ChallengeError> {
+        Contract::try_new(ChallengeConfig {
+            owner_id: owner_id.to_string(),
+            name: "name".to_string(),
+            description: "description".to_string(),
+            media_link: "media_link".to_string(),
+            reward_nft_id: "reward_nft".to_string(),
+            challenge_nft_ids: challenge_nft_ids.iter().map(|id| id.to_string()).collect(),
+            burn_challenge_piece_on_claim,
+            expiration_date_in_ns,
+            winner_limit: 1,
+            creator_can_update: true,
+            reward_nft_metadata: NFTTokenMetadata {
+                title: None,
+                description: None,
+                media: None,
+                copies: None,
+                expires_at: None,
+                starts_at: None,
+                extra: None,
+                reference: None,
+                reference_hash: None,
+                media_hash: None,
+            },
+            piece_actions,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn try_new_with_invalid_owner_id() {
+        let result = try_new("Not An Account!", vec!["nft1"], vec![false], 1000, None);
+        assert_eq!(result.err(), Some(ChallengeError::InvalidOwnerId));
+    }
+
+    #[test]
+    fn try_new_with_invalid_challenge_nft_id() {
+        let result = try_new(
+            "owner_id",
+            vec!["nft1", "Not An Account!"],
+            vec![false, false],
+            1000,
+            None,
+        );
+        let err = result.err().unwrap();
+        assert_eq!(err, ChallengeError::InvalidChallengeNftId { index: 1 });
+        assert_eq!(
+            err.to_string(),
+            "Challenge NFT contract's account ID at index 1 is invalid"
+        );
+    }
+
+    #[test]
+    fn try_new_with_start_date_after_expiration() {
+        let result = Contract::try_new(ChallengeConfig {
+            owner_id: "owner_id".to_string(),
+            name: "name".to_string(),
+            description: "description".to_string(),
+            media_link: "media_link".to_string(),
+            reward_nft_id: "reward_nft".to_string(),
+            challenge_nft_ids: vec!["nft1".to_string()],
+            burn_challenge_piece_on_claim: vec![false],
+            expiration_date_in_ns: 1000,
+            winner_limit: 1,
+            creator_can_update: true,
+            reward_nft_metadata: new().get_challenge_metadata().reward_nft_metadata,
+            start_date_in_ns: Some(1000),
+            ..Default::default()
+        });
+        assert_eq!(result.err(), Some(ChallengeError::StartDateAfterExpiration));
+    }
+
+    #[test]
+    fn try_new_with_mismatched_piece_labels() {
+        let reward_nft_metadata = new().get_challenge_metadata().reward_nft_metadata;
+        let result = Contract::try_new(ChallengeConfig {
+            owner_id: "owner_id".to_string(),
+            name: "name".to_string(),
+            description: "description".to_string(),
+            media_link: "media_link".to_string(),
+            reward_nft_id: "reward_nft".to_string(),
+            challenge_nft_ids: vec!["nft1".to_string()],
+            burn_challenge_piece_on_claim: vec![false],
+            expiration_date_in_ns: 1000,
+            winner_limit: 1,
+            creator_can_update: true,
+            reward_nft_metadata,
+            piece_labels: Some(vec!["Fire Shard".to_string(), "Water Shard".to_string()]),
+            ..Default::default()
+        });
+        assert_eq!(
+            result.err(),
+            Some(ChallengeError::PieceLabelsLengthMismatch)
+        );
+    }
+
+    #[test]
+    fn set_piece_labels() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.set_piece_labels(vec!["Fire Shard".to_string(), "Water Shard".to_string()]);
+        let labels: Vec<Option<String>> = challenge
+            .get_challenge_metadata()
+            .pieces
+            .into_iter()
+            .map(|piece| piece.spec.label)
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                Some("Fire Shard".to_string()),
+                Some("Water Shard".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The challenge nft ids and piece labels must be the same length")]
+    fn set_piece_labels_with_mismatched_length() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.set_piece_labels(vec!["Fire Shard".to_string()]);
+    }
+
+    #[test]
+    fn try_new_without_reward_media_hash() {
+        let mut reward_nft_metadata = new().get_challenge_metadata().reward_nft_metadata;
+        reward_nft_metadata.media = Some("media_link".to_string());
+        let result = Contract::try_new(ChallengeConfig {
+            owner_id: "owner_id".to_string(),
+            name: "name".to_string(),
+            description: "description".to_string(),
+            media_link: "media_link".to_string(),
+            reward_nft_id: "reward_nft".to_string(),
+            challenge_nft_ids: vec!["nft1".to_string()],
+            burn_challenge_piece_on_claim: vec![false],
+            expiration_date_in_ns: 1000,
+            winner_limit: 1,
+            creator_can_update: true,
+            reward_nft_metadata,
+            ..Default::default()
+        });
+        assert_eq!(result.err(), Some(ChallengeError::MissingRewardMediaHash));
+    }
+
+    #[test]
+    fn verify_reward_media() {
+        let mut challenge = new();
+        assert_eq!(challenge.verify_reward_media(vec![1, 2, 3]), false);
+        challenge.reward_nft_metadata.media_hash = Some(Base64VecU8::from(vec![1, 2, 3]));
+        assert_eq!(challenge.verify_reward_media(vec![1, 2, 3]), true);
+        assert_eq!(challenge.verify_reward_media(vec![1, 2, 4]), false);
+        assert_eq!(challenge.verify_reward_media(vec![1, 2]), false);
+    }
+
+    #[test]
+    fn try_new_with_mismatched_burn_flags() {
+        let result = try_new("owner_id", vec!["nft1"], vec![false, true], 1000, None);
+        assert_eq!(result.err(), Some(ChallengeError::BurnFlagsLengthMismatch));
+    }
+
+    #[test]
+    fn try_new_with_mismatched_piece_actions() {
+        let result = try_new(
+            "owner_id",
+            vec!["nft1"],
+            vec![false],
+            1000,
+            Some(vec![PieceAction::Keep, PieceAction::Burn]),
+        );
+        assert_eq!(
+            result.err(),
+            Some(ChallengeError::PieceActionsLengthMismatch)
+        );
+    }
+
+    #[test]
+    fn try_new_without_challenge_pieces() {
+        let result = try_new("owner_id", vec![], vec![], 1000, None);
+        assert_eq!(result.err(), Some(ChallengeError::NoChallengePieces));
+    }
+
+    #[test]
+    fn try_new_with_pieces_on_one_contract() {
+        let challenge = try_new(
+            "owner_id",
+            vec!["nft1", "nft1"],
+            vec![false, false],
+            1000,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            challenge.ownership_contracts(),
+            vec![AccountId::from_str("nft1").unwrap()]
+        );
+    }
+
+    #[test]
+    fn try_new_with_past_expiration() {
+        testing_env!(VMContextBuilder::new().block_timestamp(1000).build());
+        let result = try_new("owner_id", vec!["nft1"], vec![false], 1000, None);
+        assert_eq!(result.err(), Some(ChallengeError::ExpirationInPast));
+    }
+
+    #[test]
+    fn try_new_with_valid_arguments() {
+        let result = try_new("owner_id", vec!["nft1"], vec![false], 1000, None);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn mint_time_ns_reads_epoch_milliseconds() {
+        let token = |issued_at: Option<&str>, starts_at: Option<&str>| TokenCompliant {
+            token_id: "1".to_string(),
+            metadata: Some(TokenMetadataCompliant {
+                issued_at: issued_at.map(str::to_string),
+                starts_at: starts_at.map(str::to_string),
+            }),
+            approved_account_ids: None,
+        };
+        assert_eq!(
+            token(Some("1700000000000"), Some("1")).mint_time_ns(),
+            Some(1700000000000000000)
+        );
+        // ISO 8601 dates are skipped in favour of the next numeric time.
+        assert_eq!(
+            token(Some("2023-11-14T22:13:20Z"), Some("1700000000000")).mint_time_ns(),
+            Some(1700000000000000000)
+        );
+        assert_eq!(
+            token(Some("2023-11-14T22:13:20Z"), None).mint_time_ns(),
+            None
+        );
+    }
+
+    #[test]
+    fn migrate_from_parallel_piece_vectors() {
+        let winner_id = AccountId::from_str("winner_id").unwrap();
+        let mut challenge_nft_ids = Vector::new(b"a");
+        let mut piece_actions = Vector::new(b"c");
+        let mut min_mint_time_ns = Vector::new(b"n");
+        let mut piece_roles = Vector::new(b"e");
+        let mut piece_ownership_observations = Vector::new(b"o");
+        for (nft_id, action, role) in [
+            ("nft1", PieceAction::Burn, PieceRole::Required),
+            ("nft2", PieceAction::Keep, PieceRole::Bonus),
+        ] {
+            challenge_nft_ids.push(AccountId::from_str(nft_id).unwrap());
+            piece_actions.push(action);
+            min_mint_time_ns.push(Some(5));
+            piece_roles.push(role);
+            piece_ownership_observations.push(0);
+        }
+        let mut winners = LookupMap::new(b"z");
+        winners.insert(winner_id.clone(), 1);
+        let mut won_at = LookupMap::new(b"w");
+        won_at.insert(winner_id.clone(), 7);
+        let mut winners_in_order = Vector::new(b"v");
+        winners_in_order.push(winner_id.clone());
+        challenge_nft_ids.flush();
+        piece_actions.flush();
+        min_mint_time_ns.flush();
+        piece_roles.flush();
+        piece_ownership_observations.flush();
+        winners.flush();
+        won_at.flush();
+        winners_in_order.flush();
+        env::state_write(&OldContract {
+            owner_id: "owner_id".to_string(),
+            creator_id: "owner_id".to_string(),
+            name: "name".to_string(),
+            description: "description".to_string(),
+            media_link: "media_link".to_string(),
+            reward_nft_id: "reward_nft".to_string(),
+            reward_nft_metadata: NFTTokenMetadata::default(),
+            challenge_nft_ids,
+            piece_actions,
+            expiration_date_in_ns: 1000,
+            winner_limit: 2,
+            winner_count: 1,
+            winners,
+            potential_winners_left: 1,
+            challenge_completed: false,
+            creator_can_update: true,
+            recent_attempts: Vector::new(b"r"),
+            recent_attempts_head: 0,
+            rewards_per_winner: 1,
+            revealed_reward_metadata: None,
+            revealed: false,
+            co_owner_id: None,
+            paused: false,
+            inflight_claims: 0,
+            claim_fee: NearToken::from_yoctonear(0),
+            pending_claim_fees: LookupMap::new(b"f"),
+            mutation_log: Vector::new(b"m"),
+            mutation_log_head: 0,
+            delegates: LookupSet::new(b"d"),
+            pending_claims: UnorderedMap::new(b"p"),
+            max_pieces_per_claim_tx: None,
+            staged_claims: LookupMap::new(b"g"),
+            stuck_tokens: Vector::new(b"k"),
+            min_mint_time_ns,
+            won_at,
+            rewards_minted_count: 0,
+            auto_end_on_full_mint: false,
+            custom_messages: CustomMessages::default(),
+            piece_ownership_observations,
+            max_claims_per_block: None,
+            claims_in_block: (0, 0),
+            piece_roles,
+            bonus_reward_metadata: Vector::new(b"x"),
+            bonus_pieces_owned: LookupMap::new(b"q"),
+            royalty_from_piece_owners: None,
+            reward_royalty_split: None,
+            last_receipts: LookupMap::new(b"l"),
+            blacklist: LookupSet::new(b"b"),
+            winners_in_order,
+            reservation_ttl_ns: None,
+            reservations: UnorderedMap::new(b"s"),
+        });
+
+        let challenge = Contract::migrate();
+        let metadata = challenge.get_challenge_metadata();
+        assert_eq!(metadata.pieces.len(), 2);
+        assert_eq!(
+            metadata.pieces[1].spec,
+            PieceSpec {
+                action: PieceAction::Keep,
+                role: PieceRole::Bonus,
+                min_mint_time_ns: Some(5),
+                burn_quantity: 1,
+                label: None,
+            }
+        );
+        assert_eq!(metadata.required_pieces, 1);
+        assert_eq!(
+            challenge.winners.get(&winner_id),
+            Some(&WinnerInfo {
+                count: 1,
+                first_won_at_ns: 7,
+                last_won_at_ns: 7,
+            })
+        );
+        // The reward of the existing win hasn't been minted yet.
+        assert!(challenge.has_unminted_win(&winner_id));
+        assert_eq!(challenge.winner_count, 1);
+    }
+
+    #[test]
+    fn piece_specs_match_metadata() {
+        let treasury = AccountId::from_str("treasury").unwrap();
+        let challenge = try_new(
+            "owner_id",
+            vec!["nft1", "nft2", "nft3"],
+            vec![false, false, false],
+            1000,
+            Some(vec![
+                PieceAction::Burn,
+                PieceAction::Keep,
+                PieceAction::TransferTo(treasury.clone()),
+            ]),
+        )
+        .unwrap();
+        let metadata = challenge.get_challenge_metadata();
+        assert_eq!(metadata.pieces.len(), 3);
+        assert_eq!(
+            metadata.pieces[2].spec,
+            PieceSpec {
+                action: PieceAction::TransferTo(treasury),
+                role: PieceRole::Required,
+                min_mint_time_ns: None,
+                burn_quantity: 1,
+                label: None,
+            }
+        );
+        for i in 0..metadata.pieces.len() {
+            let spec = &metadata.pieces[i].spec;
+            assert_eq!(spec.action, metadata.piece_actions[i]);
+            assert_eq!(spec.role, metadata.piece_roles[i]);
+            assert_eq!(spec.min_mint_time_ns, metadata.min_mint_time_ns[i]);
+            assert_eq!(
+                metadata.burn_challenge_piece_on_claim[i],
+                spec.action == PieceAction::Burn
+            );
+        }
+        assert_eq!(challenge.self_check(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn challenge_pieces_keep_index_order() {
+        let mut challenge = try_new(
+            "owner_id",
+            vec!["nft1", "nft2", "nft3"],
+            vec![false, false, false],
+            1000,
+            None,
+        )
+        .unwrap();
+        let before = challenge.get_challenge_metadata();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.pieces.get_mut(1).unwrap().action = PieceAction::Burn;
+        challenge.extend_expiration(2000);
+        challenge.record_winner(AccountId::from_str("account_id").unwrap());
+        let after = challenge.get_challenge_metadata();
+
+        for metadata in [&before, &after] {
+            assert_eq!(metadata.challenge_nft_ids, vec!["nft1", "nft2", "nft3"]);
+            for (position, piece) in metadata.pieces.iter().enumerate() {
+                assert_eq!(piece.index, position as u32);
+                assert_eq!(piece.nft_id, metadata.challenge_nft_ids[position]);
+            }
+        }
+        assert_eq!(
+            after.burn_challenge_piece_on_claim,
+            vec![false, true, false]
+        );
+    }
+
+    #[test]
+    fn get_challenge_pieces() {
+        let challenge = try_new(
+            "owner_id",
+            vec!["nft1", "nft2", "nft3"],
+            vec![true, false, true],
+            1000,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            challenge.get_challenge_pieces(None, None),
+            vec![
+                ("nft1".to_string(), true),
+                ("nft2".to_string(), false),
+                ("nft3".to_string(), true),
+            ]
+        );
+        assert_eq!(
+            challenge.get_challenge_pieces(Some(1), Some(1)),
+            vec![("nft2".to_string(), false)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Challenge must reward at least 1 NFT per winner")]
+    fn new_with_zero_rewards_per_winner() {
+        Contract::new(ChallengeConfig {
+            owner_id: "owner_id".to_string(),
+            name: "name".to_string(),
+            description: "description".to_string(),
+            media_link: "media_link".to_string(),
+            reward_nft_id: "reward_nft".to_string(),
+            challenge_nft_ids: vec!["challenge_nft_id1".to_string()],
+            burn_challenge_piece_on_claim: vec![false],
+            expiration_date_in_ns: 1000000000000,
+            winner_limit: 1,
+            creator_can_update: true,
+            reward_nft_metadata: NFTTokenMetadata {
+                title: None,
+                description: None,
+                media: None,
+                copies: None,
+                expires_at: None,
+                starts_at: None,
+                extra: None,
+                reference: None,
+                reference_hash: None,
+                media_hash: None,
+            },
+            rewards_per_winner: Some(0),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn get_owner_id() {
+        let challenge = new();
+        assert_eq!(challenge.get_owner_id(), "owner_id");
+    }
+
+    #[test]
+    fn get_owner_info_for_owner() {
+        let challenge = new();
+        let owner_info = challenge.get_owner_info(AccountId::from_str("owner_id").unwrap());
+        assert_eq!(owner_info.owner_id, "owner_id");
+        assert_eq!(owner_info.is_owner, true);
+        assert_eq!(owner_info.is_creator, false);
+        assert_eq!(owner_info.can_update_completion, true);
+    }
+
+    #[test]
+    fn get_owner_info_for_creator() {
+        let challenge = new();
+        let creator_id = AccountId::from_str(&challenge.creator_id).unwrap();
+        let owner_info = challenge.get_owner_info(creator_id.clone());
+        assert_eq!(owner_info.creator_id, creator_id.to_string());
+        assert_eq!(owner_info.is_owner, false);
+        assert_eq!(owner_info.is_creator, true);
+        assert_eq!(owner_info.can_update_completion, false);
+    }
+
+    #[test]
+    fn get_owner_info_for_unrelated_account() {
+        let challenge = new();
+        let owner_info = challenge.get_owner_info(AccountId::from_str("stranger").unwrap());
+        assert_eq!(owner_info.is_owner, false);
+        assert_eq!(owner_info.is_creator, false);
+        assert_eq!(owner_info.can_update_completion, false);
+    }
+
+    #[test]
+    fn supported_standards() {
+        let challenge = new();
+        let standards: Vec<String> = challenge
+            .supported_standards()
+            .into_iter()
+            .map(|pair| pair.standard)
+            .collect();
+        assert_eq!(standards, vec!["nep297", "nep330"]);
+    }
+
+    #[test]
+    fn is_challenge_active() {
+        let mut challenge = new();
+        challenge.start_date_in_ns = 1000;
+        testing_env!(VMContextBuilder::new().block_timestamp(999).build());
+        assert_eq!(challenge.is_challenge_active(), false);
+        assert_eq!(challenge.get_phase(), ChallengePhase::NotStarted);
+        testing_env!(VMContextBuilder::new().block_timestamp(1000).build());
+        assert_eq!(challenge.is_challenge_active(), true);
+        testing_env!(VMContextBuilder::new()
+            .block_timestamp(1000000000000)
+            .build());
+        assert_eq!(challenge.is_challenge_active(), false);
+    }
+
+    #[test]
+    #[should_panic(expected = "Challenge has not started yet")]
+    fn initiate_claim_before_start_date() {
+        let mut challenge = new();
+        challenge.start_date_in_ns = 1000;
+        testing_env!(VMContextBuilder::new()
+            .block_timestamp(999)
+            .attached_deposit(NearToken::from_yoctonear(4))
+            .build());
+        challenge.initiate_claim(None);
+    }
+
+    #[test]
+    fn reservation_counts_attempt_and_holds_claim_fee() {
+        let mut challenge = new();
+        challenge.reservation_ttl_ns = Some(1000);
+        challenge.claim_fee = NearToken::from_yoctonear(10);
+        let account_id = AccountId::from_str("account_id").unwrap();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id.clone())
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        challenge.reserve_slot();
+        assert_eq!(challenge.get_attempt_count(account_id.clone()), 1);
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id.clone())
+            .attached_deposit(NearToken::from_yoctonear(14))
+            .build());
+        challenge.complete_reservation();
+        assert_eq!(
+            challenge.pending_claim_fees.get(&account_id),
+            Some(&(account_id.clone(), NearToken::from_yoctonear(10)))
+        );
+        assert_eq!(challenge.potential_winners_left(), 0);
+        assert_eq!(challenge.get_capacity().inflight, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "You must attach at least 14 YOCTONEAR to claim the challenge")]
+    fn complete_reservation_without_claim_fee() {
+        let mut challenge = new();
+        challenge.reservation_ttl_ns = Some(1000);
+        challenge.claim_fee = NearToken::from_yoctonear(10);
+        let account_id = AccountId::from_str("account_id").unwrap();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id.clone())
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        challenge.reserve_slot();
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id)
+            .attached_deposit(NearToken::from_yoctonear(4))
+            .build());
+        challenge.complete_reservation();
+    }
+
+    #[test]
+    #[should_panic(expected = "Attempt limit reached")]
+    fn reserve_slot_after_attempt_limit() {
+        let mut challenge = new();
+        challenge.reservation_ttl_ns = Some(1000);
+        challenge.max_attempts_per_account = Some(1);
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.attempts_per_account.insert(account_id.clone(), 1);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id)
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        challenge.reserve_slot();
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot take a winner slot, none are left")]
+    fn decrement_winners_with_no_slots_left() {
+        let mut challenge = new();
+        challenge.decrement_winners();
+        assert_eq!(challenge.potential_winners_left(), 0);
+        challenge.decrement_winners();
+    }
+
+    #[test]
+    fn increment_winners_caps_at_winner_limit() {
+        let mut challenge = new();
+        assert_eq!(challenge.potential_winners_left(), 1);
+        challenge.increment_winners();
+        assert_eq!(challenge.potential_winners_left(), 1);
+        challenge.decrement_winners();
+        challenge.increment_winners();
+        assert_eq!(challenge.potential_winners_left(), 1);
+    }
+
+    #[test]
+    fn get_phase() {
+        let mut challenge = new();
+        assert_eq!(challenge.get_phase(), ChallengePhase::Active);
+        challenge.decrement_winners();
+        assert_eq!(challenge.get_phase(), ChallengePhase::Full);
+        challenge.increment_winners();
+        assert_eq!(challenge.get_phase(), ChallengePhase::Active);
+        // The challenge is expired from its expiration date on, like
+        // `is_challenge_expired` reports.
+        testing_env!(VMContextBuilder::new()
+            .block_timestamp(1000000000000)
+            .build());
+        assert_eq!(challenge.get_phase(), ChallengePhase::Expired);
+        assert_eq!(challenge.is_challenge_expired(), true);
+        challenge.challenge_completed = true;
+        assert_eq!(challenge.get_phase(), ChallengePhase::Completed);
+    }
+
+    #[test]
+    fn next_claimable_at_combines_start_date_and_cooldown() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        let other_account_id = AccountId::from_str("other_account_id").unwrap();
+        assert_eq!(challenge.next_claimable_at(account_id.clone()), 0);
+
+        challenge.claim_cooldown_ns = Some(500);
+        testing_env!(VMContextBuilder::new().block_timestamp(100).build());
+        challenge.record_attempt(account_id.clone(), CLAIM_OUTCOME_MISSING_PIECE);
+        assert_eq!(challenge.next_claimable_at(account_id.clone()), 600);
+
+        // A start date after the cooldown ends pushes the claim back further.
+        challenge.start_date_in_ns = 1000;
+        assert_eq!(challenge.next_claimable_at(account_id.clone()), 1000);
+        assert_eq!(challenge.next_claimable_at(other_account_id.clone()), 1000);
+
+        // A cooldown that outlasts the start date does too.
+        challenge.claim_cooldown_ns = Some(2000);
+        assert_eq!(challenge.next_claimable_at(account_id.clone()), 2100);
+        assert_eq!(challenge.next_claimable_at(other_account_id.clone()), 1000);
+
+        testing_env!(VMContextBuilder::new().block_timestamp(2100).build());
+        assert_eq!(challenge.next_claimable_at(account_id), 0);
+        assert_eq!(challenge.next_claimable_at(other_account_id), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Claims from this account are cooling down")]
+    fn assert_claim_open_during_cooldown() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.claim_cooldown_ns = Some(500);
+        testing_env!(VMContextBuilder::new().block_timestamp(100).build());
+        challenge.record_attempt(account_id.clone(), CLAIM_OUTCOME_MISSING_PIECE);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id.clone())
+            .block_timestamp(599)
+            .build());
+        challenge.assert_claim_open(&account_id);
+    }
+
+    #[test]
+    fn is_live() {
+        let mut challenge = new();
+        assert_eq!(challenge.is_live(), true);
+
+        challenge.paused = true;
+        assert_eq!(challenge.is_live(), false);
+        challenge.paused = false;
+
+        challenge.start_date_in_ns = 1000;
+        testing_env!(VMContextBuilder::new().block_timestamp(999).build());
+        assert_eq!(challenge.is_live(), false);
+        testing_env!(VMContextBuilder::new().block_timestamp(1000).build());
+        assert_eq!(challenge.is_live(), true);
+
+        challenge.decrement_winners();
+        assert_eq!(challenge.is_live(), false);
+        challenge.increment_winners();
+        assert_eq!(challenge.is_live(), true);
+
+        challenge.challenge_completed = true;
+        assert_eq!(challenge.is_live(), false);
+        challenge.challenge_completed = false;
+
+        testing_env!(VMContextBuilder::new()
+            .block_timestamp(1000000000001)
+            .build());
+        assert_eq!(challenge.is_live(), false);
+    }
+
+    #[test]
+    fn is_challenge_expired() {
+        let mut challenge = new();
+        assert_eq!(challenge.is_challenge_expired(), false);
+        challenge.challenge_completed = true;
+        challenge.expiration_date_in_ns = 0;
+        assert_eq!(challenge.is_challenge_expired(), true);
+    }
+
+    #[test]
+    fn time_remaining_while_active() {
+        let challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .block_timestamp(400000000000)
+            .build());
+        assert_eq!(challenge.time_remaining_in_ns(), 600000000000);
+        assert_eq!(challenge.seconds_remaining(), 600);
+    }
+
+    #[test]
+    fn time_remaining_once_expired() {
+        let challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .block_timestamp(1000000000001)
+            .build());
+        assert_eq!(challenge.time_remaining_in_ns(), 0);
+        assert_eq!(challenge.seconds_remaining(), 0);
+    }
+
+    #[test]
+    fn ensure_challenge_not_expired_by_non_owner() {
+        let mut challenge = new();
+        assert_eq!(challenge.ensure_challenge_not_expired(), false);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("not_owner").unwrap())
+            .block_timestamp(1000000000001)
+            .build());
+        assert_eq!(challenge.ensure_challenge_not_expired(), true);
+        assert_eq!(challenge.is_challenge_complete(), true);
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"reason":"expired"}],"event":"challenge_completed","standard":"nft_challenge","version":"1.0.0"}"#
+            ]
+        );
+    }
+
+    #[test]
+    fn refresh_when_expired() {
+        let mut challenge = new();
+        assert_eq!(challenge.refresh(), false);
+        assert_eq!(get_logs().len(), 0);
+        testing_env!(VMContextBuilder::new()
+            .block_timestamp(1000000000000)
+            .build());
+        assert_eq!(challenge.refresh(), true);
+        assert_eq!(challenge.is_challenge_complete(), true);
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"data":[{"reason":"expired"}],"event":"challenge_completed","standard":"nft_challenge","version":"1.0.0"}"#
+            ]
+        );
+        // Refreshing again does not emit the event twice.
+        assert_eq!(challenge.refresh(), true);
+        assert_eq!(get_logs().len(), 1);
+    }
+
+    #[test]
+    fn get_effective_completion_when_expired() {
+        let challenge = new();
+        assert_eq!(challenge.get_effective_completion(), false);
+        testing_env!(VMContextBuilder::new()
+            .block_timestamp(1000000000000)
+            .build());
+        assert_eq!(challenge.is_challenge_complete(), false);
+        assert_eq!(challenge.get_effective_completion(), true);
+    }
+
+    #[test]
+    fn on_pre_approval_check() {
+        let mut challenge = new();
+        challenge.require_pre_approval = true;
+        let owned_token = |approved_account_ids: Option<HashMap<AccountId, u64>>| {
+            PromiseResult::Successful(
+                near_sdk::serde_json::to_vec(&vec![TokenCompliant {
+                    token_id: "1".to_string(),
+                    metadata: None,
+                    approved_account_ids,
+                }])
+                .unwrap(),
+            )
+        };
+        let account_id = AccountId::from_str("account_id").unwrap();
+
+        // The burned piece is not approved, so the claim is rejected before
+        // it takes a slot.
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![owned_token(None), owned_token(None)],
+        );
+        challenge.on_pre_approval_check(
+            account_id.clone(),
+            account_id.clone(),
+            Some(account_id.clone()),
+            None,
+        );
+        let logs = get_logs();
+        assert_eq!(
+            logs[0],
+            "You must grant transfer approval for the challenge NFTs at indices [0] before claiming"
+        );
+        assert!(logs[1].starts_with("Claim resolved using "));
+        assert_eq!(challenge.potential_winners_left(), 1);
+        assert_eq!(
+            challenge
+                .get_last_receipt(account_id.clone())
+                .unwrap()
+                .outcome,
+            CLAIM_OUTCOME_MISSING_APPROVAL
+        );
+
+        // Once approved, the claim takes a slot and moves on to the transfers.
+        let approvals = HashMap::from([(env::current_account_id(), 1)]);
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![owned_token(Some(approvals)), owned_token(None)],
+        );
+        challenge.on_pre_approval_check(
+            account_id.clone(),
+            account_id.clone(),
+            Some(account_id),
+            None,
+        );
+        assert_eq!(challenge.potential_winners_left(), 0);
+    }
+
+    #[test]
+    fn on_ft_gate_checked() {
+        let mut challenge = new();
+        challenge.ft_gate = Some((AccountId::from_str("ft").unwrap(), U128(10)));
+        let balance = |balance: u128| {
+            PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(balance)).unwrap())
+        };
+        let account_id = AccountId::from_str("account_id").unwrap();
+
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![balance(9)],
+        );
+        challenge.on_ft_gate_checked(
+            account_id.clone(),
+            account_id.clone(),
+            Some(account_id.clone()),
+            None,
+        );
+        let logs = get_logs();
+        assert_eq!(logs[0], "Claiming requires holding at least 10 of ft");
+        assert!(logs[1].starts_with("Claim resolved using "));
+        assert_eq!(challenge.potential_winners_left(), 1);
+        assert_eq!(
+            challenge
+                .get_last_receipt(account_id.clone())
+                .unwrap()
+                .outcome,
+            CLAIM_OUTCOME_BELOW_FT_GATE
+        );
+
+        // A failed balance query counts as holding none.
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        challenge.on_ft_gate_checked(
+            account_id.clone(),
+            account_id.clone(),
+            Some(account_id.clone()),
+            None,
+        );
+        assert_eq!(challenge.potential_winners_left(), 1);
+
+        // At the threshold, the claim takes a slot and checks ownership.
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![balance(10)],
+        );
+        challenge.on_ft_gate_checked(
+            account_id.clone(),
+            account_id.clone(),
+            Some(account_id.clone()),
+            None,
+        );
+        assert_eq!(challenge.potential_winners_left(), 0);
+    }
+
+    #[test]
+    fn on_ft_gate_checked_releases_held_slot() {
+        let mut challenge = new();
+        challenge.ft_gate = Some((AccountId::from_str("ft").unwrap(), U128(10)));
+        let account_id = AccountId::from_str("account_id").unwrap();
+        // A completed reservation already holds its slot when it is gated.
+        let claim_started_at = challenge.take_claim_slot(&account_id, None);
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(
+                near_sdk::serde_json::to_vec(&U128(9)).unwrap()
+            )],
+        );
+        assert!(matches!(
+            challenge.on_ft_gate_checked(
+                account_id.clone(),
+                account_id.clone(),
+                None,
+                Some(claim_started_at),
+            ),
+            PromiseOrValue::Value(ClaimResult::BelowFtGate)
+        ));
+        assert_eq!(challenge.potential_winners_left(), 1);
+        assert_eq!(challenge.get_capacity().inflight, 0);
+        assert_eq!(challenge.pending_claims.contains_key(&account_id), false);
+    }
+
+    #[test]
+    fn get_effective_completion_when_completed() {
+        let mut challenge = new();
+        challenge.challenge_completed = true;
+        assert_eq!(challenge.is_challenge_expired(), false);
+        assert_eq!(challenge.get_effective_completion(), true);
+    }
+
+    #[test]
+    fn on_claim_with_missing_piece() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        let claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed, PromiseResult::Failed],
+        );
+        assert!(matches!(
+            challenge.on_claim(account_id.clone(), account_id, 2, claim_started_at),
+            PromiseOrValue::Value(ClaimResult::MissingPiece { index: 0 })
+        ));
+        assert_eq!(challenge.potential_winners_left(), 1);
+    }
+
+    #[test]
+    fn on_claim_with_required_pieces_owned() {
+        let mut challenge = new();
+        assert_eq!(challenge.get_challenge_metadata().required_pieces, 2);
+        challenge.required_pieces = 1;
+        let account_id = AccountId::from_str("account_id").unwrap();
+        let claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        let owned_token = PromiseResult::Successful(
+            near_sdk::serde_json::to_vec(&vec![TokenCompliant {
+                token_id: "1".to_string(),
+                metadata: None,
+                approved_account_ids: None,
+            }])
+            .unwrap(),
+        );
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed, owned_token],
+        );
+        assert!(matches!(
+            challenge.on_claim(account_id.clone(), account_id.clone(), 2, claim_started_at),
+            PromiseOrValue::Value(ClaimResult::Won)
+        ));
+        assert_eq!(challenge.is_account_winner(account_id), true);
+    }
+
+    #[test]
+    fn on_claim_with_pieces_on_one_contract() {
+        let mut challenge = new();
+        let nft_id = challenge.challenge_nft_ids[0].clone();
+        challenge.challenge_nft_ids.set(1, nft_id);
+        challenge.pieces.get_mut(0).unwrap().action = PieceAction::Keep;
+        let owned_tokens = |token_ids: Vec<&str>| {
+            PromiseResult::Successful(
+                near_sdk::serde_json::to_vec(
+                    &token_ids
+                        .into_iter()
+                        .map(|token_id| TokenCompliant {
+                            token_id: token_id.to_string(),
+                            metadata: None,
+                            approved_account_ids: None,
+                        })
+                        .collect::<Vec<TokenCompliant>>(),
+                )
+                .unwrap(),
+            )
+        };
+        let account_id = AccountId::from_str("account_id").unwrap();
+
+        // One token can't stand in for both pieces.
+        let claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![owned_tokens(vec!["1"])],
+        );
+        assert!(matches!(
+            challenge.on_claim(account_id.clone(), account_id.clone(), 1, claim_started_at),
+            PromiseOrValue::Value(ClaimResult::MissingPiece { index: 1 })
+        ));
+
+        let claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![owned_tokens(vec!["1", "2"])],
+        );
+        assert!(matches!(
+            challenge.on_claim(account_id.clone(), account_id.clone(), 1, claim_started_at),
+            PromiseOrValue::Value(ClaimResult::Won)
+        ));
+        assert_eq!(challenge.is_account_winner(account_id), true);
+    }
+
+    #[test]
+    fn on_claim_with_burn_quantity() {
+        let mut challenge = new();
+        challenge.pieces.get_mut(0).unwrap().burn_quantity = 2;
+        let owned_tokens = |token_ids: Vec<&str>| {
+            PromiseResult::Successful(
+                near_sdk::serde_json::to_vec(
+                    &token_ids
+                        .into_iter()
+                        .map(|token_id| TokenCompliant {
+                            token_id: token_id.to_string(),
+                            metadata: None,
+                            approved_account_ids: None,
+                        })
+                        .collect::<Vec<TokenCompliant>>(),
+                )
+                .unwrap(),
+            )
+        };
+        let account_id = AccountId::from_str("account_id").unwrap();
+
+        // A single token of the burned piece isn't enough.
+        let claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![owned_tokens(vec!["0"]), owned_tokens(vec!["1"])],
+        );
+        assert!(matches!(
+            challenge.on_claim(account_id.clone(), account_id.clone(), 2, claim_started_at),
+            PromiseOrValue::Value(ClaimResult::MissingPiece { index: 0 })
+        ));
+
+        // With two, both move on to the approval checks.
+        let claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![owned_tokens(vec!["0", "2", "3"]), owned_tokens(vec!["1"])],
+        );
+        assert!(matches!(
+            challenge.on_claim(account_id.clone(), account_id, 2, claim_started_at),
+            PromiseOrValue::Promise(_)
+        ));
+        assert_eq!(challenge.get_burn_transfer_deposit().as_yoctonear(), 2);
+    }
+
+    #[test]
+    fn on_claim_with_non_numeric_token_id() {
+        let mut challenge = new();
+        challenge.pieces.get_mut(1).unwrap().action = PieceAction::Burn;
+        let account_id = AccountId::from_str("account_id").unwrap();
+        let claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        let owned_token = |token_id: &str| {
+            PromiseResult::Successful(
+                near_sdk::serde_json::to_vec(&vec![TokenCompliant {
+                    token_id: token_id.to_string(),
+                    metadata: None,
+                    approved_account_ids: None,
+                }])
+                .unwrap(),
+            )
+        };
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![owned_token("fire-shard"), owned_token("1")],
+        );
+        challenge.on_claim(account_id.clone(), account_id.clone(), 2, claim_started_at);
+        // The claim moves on to the approval checks instead of failing.
+        assert_eq!(challenge.potential_winners_left(), 0);
+        assert_eq!(challenge.get_recent_attempts(0, 1).len(), 0);
+    }
+
+    #[test]
+    fn mint_nft_callback_with_immediate_value() {
+        let mut challenge = new();
+        // An empty result, or a serialized `()`, both mean the mint succeeded.
+        for value in [vec![], b"null".to_vec()] {
+            testing_env!(
+                VMContextBuilder::new().build(),
+                near_sdk::test_vm_config(),
+                near_sdk::RuntimeFeesConfig::test(),
+                Default::default(),
+                vec![PromiseResult::Successful(value)],
+            );
+            assert_eq!(
+                challenge.mint_nft_callback(
+                    AccountId::from_str("account_id").unwrap(),
+                    1,
+                    NearToken::from_millinear(6),
+                ),
+                true
+            );
+        }
+        assert_eq!(challenge.get_rewards_minted_count(), 2);
+    }
+
+    #[test]
+    fn mint_nft_callback_tracks_mints_per_win() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        // An account that has won twice can mint once per win.
+        challenge.winners.insert(
+            account_id.clone(),
+            WinnerInfo {
+                count: 2,
+                first_won_at_ns: 0,
+                last_won_at_ns: 0,
+            },
+        );
+        for _ in 0..2 {
+            assert_eq!(challenge.has_unminted_win(&account_id), true);
+            testing_env!(VMContextBuilder::new()
+                .predecessor_account_id(account_id.clone())
+                .attached_deposit(NearToken::from_near(1))
+                .build());
+            challenge.mint_nft(None);
+            testing_env!(
+                VMContextBuilder::new().build(),
+                near_sdk::test_vm_config(),
+                near_sdk::RuntimeFeesConfig::test(),
+                Default::default(),
+                vec![PromiseResult::Successful(vec![])],
+            );
+            challenge.mint_nft_callback(account_id.clone(), 1, NearToken::from_millinear(6));
+        }
+        assert_eq!(challenge.has_unminted_win(&account_id), false);
+        assert_eq!(challenge.has_minted(account_id), true);
+    }
+
+    #[test]
+    fn is_fully_distributed_when_reward_total_overflows() {
+        let mut challenge = new();
+        challenge.winner_limit = u64::MAX;
+        challenge.winner_count = u64::MAX;
+        challenge.rewards_per_winner = 2;
+        challenge.rewards_minted_count = u64::MAX;
+        assert_eq!(challenge.is_fully_distributed(), false);
+    }
+
+    #[test]
+    #[should_panic(expected = "Reward already minted")]
+    fn mint_nft_when_already_minted() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.winners.insert(
+            account_id.clone(),
+            WinnerInfo {
+                count: 1,
+                first_won_at_ns: 0,
+                last_won_at_ns: 0,
+            },
+        );
+        challenge.wins_minted.insert(account_id.clone(), 1);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id)
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        challenge.mint_nft(None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "To cover minting fees, you need to attach at least 100000000000000000000000 yoctoNEAR to this transaction."
+    )]
+    fn mint_nft_with_configured_deposit() {
+        let mut challenge = new();
+        assert_eq!(
+            challenge.get_challenge_metadata().mint_deposit_in_millinear,
+            54
+        );
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.winners.insert(
+            account_id.clone(),
+            WinnerInfo {
+                count: 1,
+                first_won_at_ns: 0,
+                last_won_at_ns: 0,
+            },
+        );
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.set_mint_deposit(100);
+        assert_eq!(challenge.get_mint_deposit(), NearToken::from_millinear(100));
+        // The default deposit is no longer enough.
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id)
+            .attached_deposit(NearToken::from_millinear(99))
+            .build());
+        challenge.mint_nft(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The reward can't be minted to bot_id")]
+    fn mint_nft_to_disallowed_recipient() {
+        let mut challenge = new();
+        challenge.restrict_mint_recipient = true;
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.winners.insert(
+            account_id.clone(),
+            WinnerInfo {
+                count: 1,
+                first_won_at_ns: 0,
+                last_won_at_ns: 0,
+            },
+        );
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.add_mint_recipient(AccountId::from_str("friend_id").unwrap());
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id.clone())
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        // The caller and allowlisted accounts can receive the reward.
+        challenge.mint_nft(Some(account_id.clone()));
+        challenge.wins_minted.remove(&account_id);
+        challenge.mint_nft(Some(AccountId::from_str("friend_id").unwrap()));
+        challenge.wins_minted.remove(&account_id);
+        challenge.mint_nft(Some(AccountId::from_str("bot_id").unwrap()));
+    }
+
+    #[test]
+    fn mint_nft_marks_win_until_mint_fails() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.winners.insert(
+            account_id.clone(),
+            WinnerInfo {
+                count: 1,
+                first_won_at_ns: 0,
+                last_won_at_ns: 0,
+            },
+        );
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id.clone())
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        challenge.mint_nft(None);
+        // The win can't be minted again while its mint is in flight.
+        assert_eq!(challenge.has_unminted_win(&account_id), false);
+
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        challenge.mint_nft_callback(account_id.clone(), 1, NearToken::from_millinear(6));
+        assert_eq!(challenge.has_unminted_win(&account_id), true);
+        assert_eq!(challenge.wins_minted.get(&account_id), None);
+    }
+
+    #[test]
+    fn mint_nft_callback_when_mint_partially_succeeded() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.winners.insert(
+            account_id.clone(),
+            WinnerInfo {
+                count: 1,
+                first_won_at_ns: 0,
+                last_won_at_ns: 0,
+            },
+        );
+        challenge.wins_minted.insert(account_id.clone(), 1);
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"[\"1\"]".to_vec())],
+        );
+        assert_eq!(
+            challenge.mint_nft_callback(account_id.clone(), 3, NearToken::from_millinear(6)),
+            true
+        );
+        assert_eq!(
+            get_logs(),
+            vec![
+                "Only 1 of 3 reward NFTs were minted, refunding 4000000000000000000000 yoctoNEAR",
+                "Reward NFT minted",
+            ]
+        );
+        assert_eq!(challenge.get_rewards_minted_count(), 1);
+        assert_eq!(challenge.has_minted(account_id), true);
+    }
+
+    #[test]
+    fn reward_mint_cost_counts_royalty_entries() {
+        let storage_costs = StorageCosts {
+            storage_price_per_byte: U128(10),
+            common: U128(800),
+            token: U128(3600),
+        };
+        let metadata = NFTTokenMetadata::default();
+        let metadata_cost = 10 * borsh::to_vec(&metadata).unwrap().len() as u128;
+        assert_eq!(
+            reward_mint_cost(&storage_costs, &metadata, 2, None),
+            NearToken::from_yoctonear(800 + metadata_cost + 2 * 3600)
+        );
+        let mut split_between = SplitBetweenUnparsed::new();
+        split_between.insert(AccountId::from_str("owner_a").unwrap(), 5000);
+        split_between.insert(AccountId::from_str("owner_b").unwrap(), 5000);
+        let royalty_args = RoyaltyArgs {
+            split_between,
+            percentage: 1000,
+        };
+        assert_eq!(
+            reward_mint_cost(&storage_costs, &metadata, 2, Some(&royalty_args)),
+            NearToken::from_yoctonear(3 * 800 + metadata_cost + 2 * 3600)
+        );
+    }
+
+    #[test]
+    fn on_storage_costs_resolved_above_mint_deposit() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.winners.insert(
+            account_id.clone(),
+            WinnerInfo {
+                count: 1,
+                first_won_at_ns: 0,
+                last_won_at_ns: 0,
+            },
+        );
+        challenge.wins_minted.insert(account_id.clone(), 1);
+        let storage_costs = StorageCosts {
+            storage_price_per_byte: U128(10u128.pow(19)),
+            common: U128(NearToken::from_millinear(60).as_yoctonear()),
+            token: U128(0),
+        };
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(
+                near_sdk::serde_json::to_vec(&storage_costs).unwrap()
+            )],
+        );
+        challenge.on_storage_costs_resolved(
+            account_id.clone(),
+            account_id.clone(),
+            None,
+            NearToken::from_millinear(54),
+        );
+        // The mint never went out, so the win can be minted again.
+        assert!(challenge.has_unminted_win(&account_id));
+        assert_eq!(challenge.get_rewards_minted_count(), 0);
+    }
+
+    #[test]
+    fn mint_nft_callback_when_mint_failed() {
+        let mut challenge = new();
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        assert_eq!(
+            challenge.mint_nft_callback(
+                AccountId::from_str("account_id").unwrap(),
+                1,
+                NearToken::from_millinear(6),
+            ),
+            false
+        );
+        assert_eq!(get_logs(), vec!["There was an error minting the NFT"]);
+        assert_eq!(challenge.get_rewards_minted_count(), 0);
+    }
+
+    #[test]
+    fn on_burn_nfts_returns_unburned_tokens() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        let claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![]), PromiseResult::Failed],
+        );
+        let burned_tokens = vec![
+            NftToken {
+                nft_id: AccountId::from_str("challenge_nft_id1").unwrap(),
+                token_id: "0".to_string(),
+            },
+            NftToken {
+                nft_id: AccountId::from_str("challenge_nft_id2").unwrap(),
+                token_id: "1".to_string(),
+            },
+        ];
+        assert_eq!(
+            challenge.on_burn_nfts(
+                account_id.clone(),
+                account_id.clone(),
+                burned_tokens,
+                claim_started_at
+            ),
+            ClaimResult::BurnFailed
+        );
+        assert!(get_logs().contains(&"Returning unburned NFTs to account_id".to_string()));
+        // The failed burn is handed back rather than kept in the contract,
+        // and the claim fails so the returned piece can't win again.
+        assert_eq!(challenge.has_stuck_tokens(), false);
+        assert_eq!(challenge.is_account_winner(account_id.clone()), false);
+        assert_eq!(challenge.potential_winners_left(), 1);
+        assert_eq!(
+            challenge.get_last_receipt(account_id).unwrap().outcome,
+            CLAIM_OUTCOME_BURN_FAILED
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected 2 promise results, but got 1")]
+    fn on_burn_nfts_with_missing_promise_result() {
+        let mut challenge = new();
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        let account_id = AccountId::from_str("account_id").unwrap();
+        let token = NftToken {
+            nft_id: AccountId::from_str("challenge_nft_id1").unwrap(),
+            token_id: "0".to_string(),
+        };
+        challenge.on_burn_nfts(
+            account_id.clone(),
+            account_id,
+            vec![token.clone(), token],
+            0,
+        );
+    }
+
+    #[test]
+    fn on_unburned_returned_keeps_failed_returns() {
+        let mut challenge = new();
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        let token = NftToken {
+            nft_id: AccountId::from_str("challenge_nft_id2").unwrap(),
+            token_id: "1".to_string(),
+        };
+        challenge.on_unburned_returned(vec![token.clone()]);
+        assert_eq!(challenge.get_stuck_tokens(0, 10), vec![token]);
+    }
+
+    #[test]
+    fn burn_nfts_maps_transfers_to_burnable_pieces() {
+        let mut challenge = try_new(
+            "owner_id",
+            vec!["nft1", "nft2", "nft3"],
+            vec![true, false, true],
+            1000000000000,
+            None,
+        )
+        .unwrap();
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![
+                PromiseResult::Successful(vec![]),
+                PromiseResult::Successful(vec![]),
+            ],
+        );
+        let account_id = AccountId::from_str("account_id").unwrap();
+        let claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        challenge.burn_nfts(
+            account_id.clone(),
+            account_id,
+            vec![0, 2],
+            vec!["token0".to_string(), "token2".to_string()],
+            claim_started_at,
+        );
+        assert_eq!(
+            get_logs(),
+            vec![
+                "NFT transferred successfully at index 0",
+                "NFT transferred successfully at index 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn burn_nfts_returns_transferred_pieces_when_a_transfer_fails() {
+        let mut challenge = try_new(
+            "owner_id",
+            vec!["nft1", "nft2", "nft3"],
+            vec![true, false, true],
+            1000000000000,
+            None,
+        )
+        .unwrap();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        let claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![]), PromiseResult::Failed],
+        );
+        let result = challenge.burn_nfts(
+            account_id.clone(),
+            account_id.clone(),
+            vec![0, 2],
+            vec!["token0".to_string(), "token2".to_string()],
+            claim_started_at,
+        );
+        assert!(matches!(
+            result,
+            PromiseOrValue::Value(ClaimResult::TransferFailed)
+        ));
+        // Only the piece that made it into this contract is handed back.
+        assert_eq!(
+            get_logs()[..3],
+            [
+                "NFT transferred successfully at index 0",
+                "There was an error transferring the challenge NFT at index 2",
+                "Returning unburned NFTs to account_id",
+            ]
+        );
+        assert_eq!(challenge.get_capacity().inflight, 0);
+        assert_eq!(
+            challenge.get_last_receipt(account_id).unwrap().outcome,
+            CLAIM_OUTCOME_BURN_FAILED
+        );
+    }
+
+    #[test]
+    fn on_approval_check_reports_challenge_index() {
+        let mut challenge = try_new(
+            "owner_id",
+            vec!["nft1", "nft2", "nft3"],
+            vec![true, false, true],
+            1000000000000,
+            None,
+        )
+        .unwrap();
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![
+                PromiseResult::Successful(near_sdk::serde_json::to_vec(&1u64).unwrap()),
+                PromiseResult::Failed,
+            ],
+        );
+        let account_id = AccountId::from_str("account_id").unwrap();
+        let claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        challenge.on_approval_check(
+            account_id.clone(),
+            account_id,
+            vec![0, 2],
+            vec!["token0".to_string(), "token2".to_string()],
+            claim_started_at,
+        );
+        assert!(get_logs().contains(
+            &"You must grant transfer approval for the challenge NFT at index 2 for us to burn it"
+                .to_string()
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "You already hold the Crown")]
+    fn initiate_claim_with_custom_already_won_message() {
+        let mut challenge = new();
+        challenge.custom_messages.already_won = Some("You already hold the Crown".to_string());
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.winners.insert(
+            account_id.clone(),
+            WinnerInfo {
+                count: 1,
+                first_won_at_ns: 0,
+                last_won_at_ns: 0,
+            },
+        );
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id)
+            .attached_deposit(NearToken::from_yoctonear(4))
+            .build());
+        challenge.initiate_claim(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Claims from this account are blocked")]
+    fn initiate_claim_when_blacklisted() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.add_to_blacklist(account_id.clone());
+        assert_eq!(challenge.is_blacklisted(account_id.clone()), true);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id)
+            .attached_deposit(NearToken::from_yoctonear(4))
+            .build());
+        challenge.initiate_claim(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Claims from this account are blocked")]
+    fn initiate_claim_for_blacklisted_winner_by_delegate() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        let delegate_id = AccountId::from_str("delegate_id").unwrap();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.add_delegate(delegate_id.clone());
+        challenge.add_to_blacklist(account_id.clone());
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(delegate_id)
+            .attached_deposit(NearToken::from_yoctonear(4))
+            .build());
+        challenge.initiate_claim_for(account_id.clone(), account_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Account not whitelisted")]
+    fn initiate_claim_when_not_whitelisted() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.add_to_whitelist(AccountId::from_str("whitelisted").unwrap());
+        assert_eq!(challenge.has_whitelist(), true);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("account_id").unwrap())
+            .attached_deposit(NearToken::from_yoctonear(4))
+            .build());
+        challenge.initiate_claim(None);
+    }
+
+    #[test]
+    fn remove_from_whitelist() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.add_to_whitelist(account_id.clone());
+        challenge.add_to_whitelist(account_id.clone());
+        assert_eq!(challenge.is_whitelisted(account_id.clone()), true);
+        challenge.remove_from_whitelist(account_id.clone());
+        assert_eq!(challenge.is_whitelisted(account_id), false);
+        // An empty whitelist leaves the challenge open to everyone.
+        assert_eq!(challenge.has_whitelist(), false);
+        // Adding an account that is already whitelisted isn't a mutation.
+        let methods: Vec<String> = challenge
+            .get_mutation_log(0, 10)
+            .into_iter()
+            .map(|record| record.method)
+            .collect();
+        assert_eq!(methods, vec!["add_to_whitelist", "remove_from_whitelist"]);
+    }
+
+    #[test]
+    fn owner_access_changes_are_logged() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.add_delegate(account_id.clone());
+        challenge.remove_delegate(account_id.clone());
+        challenge.add_to_blacklist(account_id.clone());
+        challenge.remove_from_blacklist(account_id.clone());
+        challenge.propose_new_owner(account_id.clone());
+        challenge.cancel_ownership_transfer();
+        let mutations = challenge.get_mutation_log(0, 10);
+        let methods: Vec<&str> = mutations
+            .iter()
+            .map(|record| record.method.as_str())
+            .collect();
+        assert_eq!(
+            methods,
+            vec![
+                "add_delegate",
+                "remove_delegate",
+                "add_to_blacklist",
+                "remove_from_blacklist",
+                "propose_new_owner",
+                "cancel_ownership_transfer"
+            ]
+        );
+        assert_eq!(mutations[0].old_value, "null");
+        assert_eq!(mutations[0].new_value, "\"account_id\"");
+    }
+
+    #[test]
+    fn remove_from_blacklist() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.add_to_blacklist(account_id.clone());
+        challenge.remove_from_blacklist(account_id.clone());
+        assert_eq!(challenge.is_blacklisted(account_id), false);
+    }
+
+    #[test]
+    fn potential_winners_left() {
+        let mut challenge = new();
+        assert_eq!(challenge.potential_winners_left(), 1);
+        challenge.decrement_winners();
+        assert_eq!(challenge.potential_winners_left(), 0);
+        challenge.increment_winners();
+        assert_eq!(challenge.potential_winners_left(), 1);
+    }
+
+    #[test]
+    fn is_account_winner() {
+        let mut challenge = new();
+        assert_eq!(
+            challenge.is_account_winner(AccountId::from_str("account_id").unwrap()),
+            false
+        );
+        challenge.winners.insert(
+            AccountId::from_str("account_id").unwrap(),
+            WinnerInfo {
+                count: 1,
+                first_won_at_ns: 0,
+                last_won_at_ns: 0,
+            },
+        );
+        assert_eq!(
+            challenge.is_account_winner(AccountId::from_str("account_id").unwrap()),
+            true
+        );
+    }
+
+    #[test]
+    fn self_check() {
+        let mut challenge = new();
+        assert_eq!(challenge.self_check(), Vec::<String>::new());
+        challenge.winner_count = 2;
+        let issues = challenge.self_check();
+        assert!(issues.contains(&"winner_count (2) is greater than winner_limit (1)".to_string()));
+    }
+
+    #[test]
+    fn get_winners() {
+        let mut challenge = new();
+        assert_eq!(challenge.get_winners(None, None), vec![]);
+        let first = AccountId::from_str("first").unwrap();
+        let second = AccountId::from_str("second").unwrap();
+        challenge.winner_limit = 2;
+        for winner_id in [first.clone(), second.clone()] {
+            challenge.record_winner(winner_id);
+        }
+        assert_eq!(
+            challenge.get_winners(None, None),
+            vec![(first.clone(), 1), (second.clone(), 1)]
+        );
+        assert_eq!(challenge.get_winners(Some(1), None), vec![(second, 1)]);
+        assert_eq!(challenge.get_winners(None, Some(1)), vec![(first, 1)]);
+        assert_eq!(challenge.get_winners(Some(2), Some(10)), vec![]);
+    }
+
+    #[test]
+    fn get_win_proof() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        assert_eq!(challenge.get_win_proof(account_id.clone()), None);
+        testing_env!(VMContextBuilder::new().block_timestamp(42).build());
+        challenge.decrement_winners();
+        challenge.record_winner(account_id.clone());
+        let proof = challenge.get_win_proof(account_id.clone()).unwrap();
+        assert_eq!(proof.account_id, account_id);
+        assert_eq!(proof.won_at, 42);
+
+        // Recompute the commitment the way an off-chain verifier would.
+        let mut preimage = proof.challenge_hash.0.clone();
+        preimage.extend_from_slice(b"account_id");
+        preimage.extend_from_slice(&42u64.to_le_bytes());
+        assert_eq!(proof.commitment.0, env::sha256(&preimage));
+
+        // A repeat win doesn't change the proof.
+        testing_env!(VMContextBuilder::new().block_timestamp(84).build());
+        challenge.winner_limit = 2;
+        challenge.potential_winners_left = 1;
+        challenge.decrement_winners();
+        challenge.record_winner(account_id.clone());
+        assert_eq!(challenge.get_win_proof(account_id).unwrap(), proof);
+    }
+
+    #[test]
+    fn get_capacity() {
+        let mut challenge = new();
+        challenge.decrement_winners();
+        let capacity = challenge.get_capacity();
+        assert_eq!(capacity.potential_left, 0);
+        assert_eq!(capacity.inflight, 1);
+        assert_eq!(capacity.warning, None);
+        challenge.record_winner(AccountId::from_str("account_id").unwrap());
+        let capacity = challenge.get_capacity();
+        assert_eq!(capacity.limit, 1);
+        assert_eq!(capacity.confirmed_winners, 1);
+        assert_eq!(capacity.potential_left, 0);
+        assert_eq!(capacity.inflight, 0);
+        assert_eq!(capacity.warning, None);
+        challenge.potential_winners_left = 1;
+        assert!(challenge.get_capacity().warning.is_some());
+    }
+
+    #[test]
+    fn remove_winner() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.decrement_winners();
+        challenge.record_winner(account_id.clone());
+        assert_eq!(challenge.winner_count, 1);
+        assert_eq!(challenge.potential_winners_left, 0);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.wins_minted.insert(account_id.clone(), 1);
+        challenge.remove_winner(account_id.clone());
+        assert_eq!(challenge.is_account_winner(account_id.clone()), false);
+        assert_eq!(challenge.winner_count, 0);
+        assert_eq!(challenge.potential_winners_left, 1);
+        assert_eq!(challenge.get_winners(None, None), vec![]);
+        assert_eq!(challenge.wins_minted.get(&account_id), None);
+        assert_eq!(challenge.get_last_receipt(account_id), None);
+        let mutations = challenge.get_mutation_log(0, 10);
+        assert_eq!(mutations.len(), 1);
+        assert_eq!(mutations[0].method, "remove_winner");
+        assert_eq!(mutations[0].new_value, "null");
+    }
+
+    #[test]
+    fn remove_winner_with_repeat_wins() {
+        let mut challenge = new();
+        challenge.winner_limit = 3;
+        challenge.potential_winners_left = 3;
+        let account_id = AccountId::from_str("account_id").unwrap();
+        for _ in 0..2 {
+            challenge.decrement_winners();
+            challenge.record_winner(account_id.clone());
+        }
+        assert_eq!(challenge.winner_count, 2);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.remove_winner(account_id);
+        assert_eq!(challenge.winner_count, 0);
+        assert_eq!(challenge.potential_winners_left, 3);
+        assert_eq!(challenge.self_check(), Vec::<String>::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "Account has not won the challenge")]
+    fn remove_winner_when_not_a_winner() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.remove_winner(AccountId::from_str("account_id").unwrap());
+    }
+
+    #[test]
+    fn sweep_stale_claims() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.decrement_winners();
+        challenge.pending_claims.insert(account_id.clone(), 100);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .block_timestamp(1000)
+            .build());
+        // The claim is not old enough to be released yet.
+        assert_eq!(challenge.sweep_stale_claims(1000), 0);
+        assert_eq!(challenge.potential_winners_left(), 0);
+        assert_eq!(challenge.sweep_stale_claims(500), 1);
+        assert_eq!(challenge.potential_winners_left(), 1);
+        assert_eq!(challenge.get_capacity().inflight, 0);
+        assert_eq!(challenge.pending_claims.contains_key(&account_id), false);
+        assert_eq!(
+            challenge.get_recent_attempts(0, 1)[0].outcome,
+            CLAIM_OUTCOME_STALE
+        );
+        let mutations = challenge.get_mutation_log(0, 10);
+        assert_eq!(mutations.len(), 2);
+        assert_eq!(mutations[1].method, "sweep_stale_claims");
+        assert_eq!(mutations[1].old_value, "1");
+        assert_eq!(mutations[1].new_value, "0");
+    }
+
+    #[test]
+    fn reclaim_abandoned_claims() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.decrement_winners();
+        challenge.pending_claims.insert(account_id.clone(), 100);
+        testing_env!(VMContextBuilder::new().block_timestamp(1000).build());
+        // Without a claim TTL, abandoned claims are only released by a sweep.
+        challenge.reclaim_abandoned_claims();
+        assert_eq!(challenge.potential_winners_left(), 0);
+        challenge.claim_ttl_ns = Some(500);
+        challenge.reclaim_abandoned_claims();
+        assert_eq!(challenge.potential_winners_left(), 1);
+        assert_eq!(challenge.pending_claims.contains_key(&account_id), false);
+    }
+
+    #[test]
+    fn callbacks_of_released_claims_are_no_ops() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .block_timestamp(100)
+            .build());
+        let claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .block_timestamp(1000)
+            .build());
+        assert_eq!(challenge.sweep_stale_claims(500), 1);
+        // The account starts another claim before the swept one resolves.
+        let new_claim_started_at = challenge.take_claim_slot(&account_id, Some(account_id.clone()));
+        assert_eq!(challenge.potential_winners_left(), 0);
+
+        let owned_token = PromiseResult::Successful(
+            near_sdk::serde_json::to_vec(&vec![TokenCompliant {
+                token_id: "1".to_string(),
+                metadata: None,
+                approved_account_ids: None,
+            }])
+            .unwrap(),
+        );
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![owned_token.clone(), owned_token],
+        );
+        assert!(matches!(
+            challenge.on_claim(account_id.clone(), account_id.clone(), 2, claim_started_at),
+            PromiseOrValue::Value(ClaimResult::Released)
+        ));
+        assert_eq!(
+            get_logs()[0],
+            "The claim of account_id was released before it resolved"
+        );
+
+        // A released burn neither counts as a win nor touches the new claim.
+        let token = NftToken {
+            nft_id: AccountId::from_str("challenge_nft_id1").unwrap(),
+            token_id: "0".to_string(),
+        };
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        assert_eq!(
+            challenge.on_burn_nfts(
+                account_id.clone(),
+                account_id.clone(),
+                vec![token],
+                claim_started_at
+            ),
+            ClaimResult::Released
+        );
+        assert_eq!(challenge.is_account_winner(account_id.clone()), false);
+        assert_eq!(challenge.winner_count, 0);
+        assert_eq!(challenge.potential_winners_left(), 0);
+        assert_eq!(
+            challenge.pending_claims.get(&account_id),
+            Some(&new_claim_started_at)
+        );
+    }
+
+    #[test]
+    fn get_stuck_tokens() {
+        let mut challenge = new();
+        assert_eq!(challenge.has_stuck_tokens(), false);
+        assert_eq!(challenge.get_stuck_tokens(0, 10).len(), 0);
+        let stuck_token = NftToken {
+            nft_id: AccountId::from_str("challenge_nft_id1").unwrap(),
+            token_id: "1".to_string(),
+        };
+        challenge.stuck_tokens.push(stuck_token.clone());
+        assert_eq!(challenge.has_stuck_tokens(), true);
+        assert_eq!(challenge.get_stuck_tokens(0, 10), vec![stuck_token]);
+        assert_eq!(challenge.get_stuck_tokens(1, 10).len(), 0);
+    }
+
+    #[test]
+    fn evacuate() {
+        let mut challenge = new();
+        let stuck_token = NftToken {
+            nft_id: AccountId::from_str("challenge_nft_id1").unwrap(),
+            token_id: "1".to_string(),
+        };
+        challenge.stuck_tokens.push(stuck_token.clone());
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.evacuate(AccountId::from_str("treasury").unwrap());
+        assert_eq!(challenge.has_stuck_tokens(), false);
+        assert_eq!(challenge.is_challenge_complete(), true);
+        assert_eq!(challenge.is_minting_frozen(), true);
+
+        // Tokens that could not be transferred stay in the registry.
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        challenge.on_evacuate(vec![stuck_token.clone()]);
+        assert_eq!(challenge.get_stuck_tokens(0, 10), vec![stuck_token]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Minting is frozen")]
+    fn mint_nft_after_evacuate() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.evacuate(AccountId::from_str("treasury").unwrap());
+        challenge.mint_nft(None);
+    }
+
+    #[test]
+    fn end_challenge() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.end_challenge();
+        assert_eq!(challenge.is_challenge_complete(), true);
+        assert_eq!(
+            get_logs(),
+            vec![
+                "Challenge ended by owner_id",
+                r#"EVENT_JSON:{"data":[{"reason":"ended_by_owner"}],"event":"challenge_completed","standard":"nft_challenge","version":"1.0.0"}"#,
+                r#"EVENT_JSON:{"data":[{"ended_at_ns":"0","pieces_burned":0,"total_attempts":0,"total_winners":0}],"event":"challenge_final_report","standard":"nft_challenge","version":"1.0.0"}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn end_challenge_reports_final_stats() {
+        let mut challenge = new();
+        challenge.record_winner(AccountId::from_str("winner").unwrap());
+        challenge.record_attempt(
+            AccountId::from_str("loser").unwrap(),
+            CLAIM_OUTCOME_MISSING_PIECE,
+        );
+        challenge.pieces_burned = 1;
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .block_timestamp(500)
+            .build());
+        assert_eq!(
+            challenge.end_challenge(),
+            FinalReport {
+                total_winners: 1,
+                total_attempts: 2,
+                pieces_burned: 1,
+                ended_at_ns: U64(500),
+            }
+        );
+    }
+
+    #[test]
+    fn winner_badge_paid_from_budget() {
+        let mut challenge = new();
+        challenge.winner_badge_nft_id = Some(AccountId::from_str("badge_nft").unwrap());
+        // Without a budget, the badge is skipped instead of drawing on the
+        // contract's balance.
+        challenge.record_winner(AccountId::from_str("account0").unwrap());
+        assert!(get_logs()
+            .contains(&"The winner badge budget can't cover the badge for account0".to_string()));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        challenge.fund_badge_budget();
+        assert_eq!(challenge.get_badge_budget(), NearToken::from_near(1));
+        challenge.record_winner(AccountId::from_str("account1").unwrap());
+        let mint_deposit = NearToken::from_near(1).saturating_sub(challenge.get_badge_budget());
+        assert!(!mint_deposit.is_zero());
+
+        // A failed mint puts its deposit back into the budget.
+        testing_env!(
+            VMContextBuilder::new().build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        challenge.on_winner_badge_minted(AccountId::from_str("account1").unwrap(), mint_deposit);
+        assert_eq!(challenge.get_badge_budget(), NearToken::from_near(1));
+    }
+
+    #[test]
+    fn record_winner_emits_challenge_won() {
+        let mut challenge = new();
+        challenge.record_winner(AccountId::from_str("account_id").unwrap());
+        assert!(get_logs().contains(
+            &r#"EVENT_JSON:{"data":[{"account_id":"account_id","challenge_name":"name","winners_count":1}],"event":"challenge_won","standard":"nft_challenge","version":"1.0.0"}"#
+                .to_string()
+        ));
+    }
+
+    #[test]
+    fn get_winner_info() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        assert_eq!(challenge.get_winner_info(account_id.clone()), None);
+        testing_env!(VMContextBuilder::new().block_timestamp(100).build());
+        challenge.record_winner(account_id.clone());
+        // A second win keeps the time of the first one.
+        testing_env!(VMContextBuilder::new().block_timestamp(200).build());
+        challenge.record_winner(account_id.clone());
+        assert_eq!(
+            challenge.get_winner_info(account_id.clone()),
+            Some(WinnerInfo {
+                count: 2,
+                first_won_at_ns: 100,
+                last_won_at_ns: 200,
+            })
+        );
+        assert_eq!(challenge.is_account_winner(account_id), true);
+    }
+
+    #[test]
+    fn repeat_win_after_minting() {
+        let mut challenge = new();
+        challenge.allow_repeat_wins = true;
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.record_winner(account_id.clone());
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id.clone())
+            .build());
+        challenge.wins_minted.insert(account_id.clone(), 1);
+        challenge.assert_claim_open(&account_id);
+        challenge.record_winner(account_id.clone());
+        // The second win doesn't add the account to the win order again.
+        assert_eq!(
+            challenge.get_winners(None, None),
+            vec![(account_id.clone(), 2)]
+        );
+        assert_eq!(challenge.get_winner_at(1), None);
+        assert!(challenge.has_unminted_win(&account_id));
+    }
+
+    #[test]
+    #[should_panic(expected = "Mint the reward of your last win before claiming again")]
+    fn repeat_win_before_minting() {
+        let mut challenge = new();
+        challenge.allow_repeat_wins = true;
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.record_winner(account_id.clone());
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id.clone())
+            .build());
+        challenge.assert_claim_open(&account_id);
+    }
+
+    #[test]
+    fn cleanup_and_refund() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.decrement_winners();
+        challenge.record_winner(account_id.clone());
+        challenge.wins_minted.insert(account_id.clone(), 1);
+        challenge.challenge_completed = true;
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.cleanup_and_refund();
+        assert_eq!(challenge.is_account_winner(account_id.clone()), false);
+        assert_eq!(challenge.get_challenge_pieces(None, None).len(), 0);
+        assert_eq!(challenge.piece_ownership_observations.len(), 0);
+        assert_eq!(challenge.former_winners.contains(&account_id), true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Winners can't claim the consolation reward")]
+    fn claim_consolation_after_cleanup_by_former_winner() {
+        let mut challenge = new();
+        challenge.consolation_reward = Some((
+            AccountId::from_str("consolation_nft").unwrap(),
+            NFTTokenMetadata::default(),
+        ));
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.decrement_winners();
+        challenge.record_winner(account_id.clone());
+        challenge.wins_minted.insert(account_id.clone(), 1);
+        challenge.challenge_completed = true;
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.cleanup_and_refund();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account_id)
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        challenge.claim_consolation();
+    }
+
+    #[test]
+    #[should_panic(expected = "Every win must be minted or forfeited before cleaning up")]
+    fn cleanup_and_refund_with_unminted_win() {
+        let mut challenge = new();
+        challenge.decrement_winners();
+        challenge.record_winner(AccountId::from_str("account_id").unwrap());
+        challenge.challenge_completed = true;
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.cleanup_and_refund();
+    }
+
+    #[test]
+    #[should_panic(expected = "Challenge storage was already cleaned up")]
+    fn cleanup_and_refund_twice() {
+        let mut challenge = new();
+        challenge.challenge_completed = true;
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.cleanup_and_refund();
+        challenge.cleanup_and_refund();
+    }
+
+    #[test]
+    #[should_panic(expected = "Challenge is already complete")]
+    fn end_challenge_when_already_complete() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.end_challenge();
+        challenge.end_challenge();
+    }
+
+    #[test]
+    fn increase_winner_limit() {
+        let mut challenge = new();
+        challenge.decrement_winners();
+        challenge.record_winner(AccountId::from_str("account_id").unwrap());
+        assert_eq!(challenge.potential_winners_left(), 0);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.increase_winner_limit(2);
+        assert_eq!(challenge.potential_winners_left(), 2);
+        assert_eq!(challenge.get_challenge_metadata().winner_limit, 3);
+        assert_eq!(challenge.get_capacity().warning, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The new winner limit overflows")]
+    fn increase_winner_limit_overflow() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.increase_winner_limit(u64::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "Challenge is already complete")]
+    fn increase_winner_limit_when_complete() {
+        let mut challenge = new();
+        challenge.challenge_completed = true;
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.increase_winner_limit(1);
+    }
+
+    #[test]
+    fn accept_ownership() {
+        let mut challenge = new();
+        let new_owner_id = AccountId::from_str("new_owner_id").unwrap();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.propose_new_owner(new_owner_id.clone());
+        assert_eq!(challenge.get_owner_id(), "owner_id");
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(new_owner_id.clone())
+            .build());
+        challenge.accept_ownership();
+        assert_eq!(challenge.get_owner_id(), "new_owner_id");
+        assert_eq!(challenge.get_pending_owner_id(), None);
+        assert_eq!(
+            challenge.get_mutation_log(0, 1)[0].method,
+            "accept_ownership"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the pending owner can accept ownership")]
+    fn accept_ownership_by_other_account() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.propose_new_owner(AccountId::from_str("new_owner_id").unwrap());
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("new_owner_idd").unwrap())
+            .build());
+        challenge.accept_ownership();
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the pending owner can accept ownership")]
+    fn accept_ownership_after_cancel() {
+        let mut challenge = new();
+        let new_owner_id = AccountId::from_str("new_owner_id").unwrap();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.propose_new_owner(new_owner_id.clone());
+        challenge.cancel_ownership_transfer();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(new_owner_id)
+            .build());
+        challenge.accept_ownership();
+    }
+
+    #[test]
+    fn extend_expiration() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.extend_expiration(2000000000000);
+        assert_eq!(
+            challenge.get_challenge_metadata().expiration_date_in_ns,
+            2000000000000
+        );
+        assert_eq!(
+            get_logs(),
+            vec!["Expiration date extended from 1000000000000 to 2000000000000"]
+        );
     }
 
-    #[payable]
-    #[private]
-    pub fn have_approvals_for_transfers(
-        &mut self,
-        winner_id: AccountId,
-        token_ids: Vec<U64>,
-    ) -> Promise {
-        let mut is_approved_promises: Vec<Promise> = vec![];
-        for i in 0..self.burn_challenge_piece_on_claim.len() {
-            is_approved_promises.push(
-                mintbase_nft::ext(
-                    self.challenge_nft_ids[i.try_into().unwrap()]
-                        .parse()
-                        .unwrap(),
-                )
-                .with_static_gas(Gas::from_tgas(1))
-                .nft_approval_id(token_ids[i as usize], env::current_account_id()),
-            );
-        }
-        let compiled_promise = is_approved_promises.into_iter().reduce(|a, b| a.and(b));
-        if compiled_promise.is_none() {
-            panic!("No nfts to check approval for. Should not have reached here.");
-        } else {
-            compiled_promise.unwrap().then(
-                Self::ext(env::current_account_id())
-                    .with_static_gas(Gas::from_tgas(token_ids.len() as u64 * 15))
-                    .on_approval_check(winner_id, token_ids),
-            )
-        }
+    #[test]
+    #[should_panic(expected = "The new expiration date must be later than the current one")]
+    fn extend_expiration_to_earlier_date() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.extend_expiration(1000000000000);
     }
 
-    #[payable]
-    #[private]
-    pub fn on_approval_check(&mut self, winner_id: AccountId, token_ids: Vec<U64>) -> Promise {
-        let approvals : Vec<Option<u64>> = (0..token_ids.len())
-            .map(|index| {
-                let result: PromiseResult = env::promise_result(index as u64);
-                match result {
-                    PromiseResult::Failed => {
-                        log!(
-                            "You must grant transfer approval for the challenge NFT at index {} for us to burn it",
-                            index
-                        );
-                        None
-                    },
-                    PromiseResult::Successful(value) => {
-                        if let Ok(message) =
-                            near_sdk::serde_json::from_slice::<u64>(&value)
-                        {
-                           Some(message)
-                        } else {
-                            log!("Unable to get approval id for NFT at {}",index);
-                            None
-                        }
-                    }
-                }
-            })
-            .collect();
-        for i in 0..approvals.len() {
-            if approvals[i] == None {
-                self.increment_winners();
-                return Promise::new(env::current_account_id()).as_return();
-            }
-        }
-        // Transfer nfts to this contract so we can burn them
-        let mut transfer_promises: Vec<Promise> = vec![];
-        for i in 0..self.burn_challenge_piece_on_claim.len() {
-            transfer_promises.push(
-                mintbase_nft::ext(
-                    self.challenge_nft_ids[i.try_into().unwrap()]
-                        .parse()
-                        .unwrap(),
-                )
-                .with_static_gas(Gas::from_tgas(2))
-                .with_attached_deposit(NearToken::from_yoctonear(1))
-                .nft_transfer(
-                    env::current_account_id(),
-                    token_ids[i as usize],
-                    approvals[i as usize].unwrap(),
-                    None,
-                ),
-            );
-        }
-        let compiled_promise = transfer_promises.into_iter().reduce(|a, b| a.and(b));
-        if compiled_promise.is_none() {
-            panic!("No nfts to transfer. Should not have reached here.");
-        } else {
-            compiled_promise.unwrap().then(
-                Self::ext(env::current_account_id())
-                    .with_static_gas(Gas::from_tgas(token_ids.len() as u64 * 10))
-                    .burn_nfts(winner_id, token_ids, approvals),
-            )
-        }
+    #[test]
+    #[should_panic(expected = "Challenge is already complete")]
+    fn extend_expiration_when_complete() {
+        let mut challenge = new();
+        challenge.challenge_completed = true;
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.extend_expiration(2000000000000);
     }
 
-    #[payable]
-    #[private]
-    pub fn burn_nfts(
-        &mut self,
-        winner_id: AccountId,
-        token_ids: Vec<U64>,
-        approvals: Vec<Option<u64>>,
-    ) -> Promise {
-        let mut challenge_nfts_to_burn: Vec<String> = vec![];
-        for i in 0..self.burn_challenge_piece_on_claim.len() {
-            if self.burn_challenge_piece_on_claim[i] {
-                challenge_nfts_to_burn.push(self.challenge_nft_ids[i.try_into().unwrap()].clone());
-            }
-        }
+    #[test]
+    fn update_metadata() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        let metadata = challenge.update_metadata(None, None, Some("fixed_link".to_string()));
+        assert_eq!(metadata.media_link, Some("fixed_link".to_string()));
+        assert_eq!(metadata.name, "name");
+        assert_eq!(metadata.description, "description");
+        assert_eq!(challenge.get_mutation_log(0, 10).len(), 1);
+    }
 
-        let mut unsuccessful_token_id_transfers: Vec<TransferTokenArgs> = vec![];
-        for i in 0..token_ids.len() {
-            let result: PromiseResult = env::promise_result(i as u64);
-            match result {
-                PromiseResult::Failed => {
-                    log!(
-                        "There was an error transferring the challenge NFT at index {}",
-                        i
-                    );
-                    unsuccessful_token_id_transfers.push(TransferTokenArgs {
-                        token_id: token_ids[i].clone(),
-                        approval_id: approvals[i as usize].unwrap(),
-                        nft_id: challenge_nfts_to_burn[i].clone(),
-                    });
-                }
-                PromiseResult::Successful(_) => {
-                    log!("NFT transferred successfully at index {}", i);
-                }
-            }
-        }
-        // Refund the transferred nfts, since we weren't able to
-        // transfer all challenge nfts that needed to be burned to this contract.
-        let mut transfer_refund: Vec<Promise> = vec![];
-        for i in 0..unsuccessful_token_id_transfers.len() {
-            transfer_refund.push(
-                mintbase_nft::ext(
-                    unsuccessful_token_id_transfers[i as usize]
-                        .nft_id
-                        .parse()
-                        .unwrap(),
-                )
-                .with_static_gas(Gas::from_tgas(2))
-                .with_attached_deposit(NearToken::from_yoctonear(1))
-                .nft_transfer(
-                    winner_id.clone(),
-                    unsuccessful_token_id_transfers[i as usize].token_id,
-                    unsuccessful_token_id_transfers[i as usize].approval_id,
-                    None,
-                ),
-            );
-        }
-        let transfer_refund_promise = transfer_refund.into_iter().reduce(|a, b| a.and(b));
-        if transfer_refund_promise.is_some() {
-            log!("Refunding NFTs");
-            self.increment_winners();
-            return transfer_refund_promise.unwrap();
-        }
-        let mut burn_promises: Vec<Promise> = vec![];
-        for i in 0..self.burn_challenge_piece_on_claim.len() {
-            burn_promises.push(
-                mintbase_nft::ext(
-                    self.challenge_nft_ids[i.try_into().unwrap()]
-                        .parse()
-                        .unwrap(),
-                )
-                .with_static_gas(Gas::from_tgas(2))
-                .with_attached_deposit(NearToken::from_yoctonear(1))
-                .nft_batch_burn(vec![token_ids[i as usize].clone()]),
-            );
-        }
-        let burn_count = burn_promises.len() as u64; // Convert usize to u64
-        let compiled_promise = burn_promises.into_iter().reduce(|a, b| a.and(b));
-        if compiled_promise.is_none() {
-            panic!("No nfts to burn. Should not have reached here.");
-        } else {
-            compiled_promise.unwrap().then(
-                Self::ext(env::current_account_id())
-                    .with_static_gas(Gas::from_tgas(1))
-                    .on_burn_nfts(winner_id, burn_count),
-            )
-        }
+    #[test]
+    #[should_panic(expected = "Challenge metadata can't be updated once there are winners")]
+    fn update_metadata_after_a_win() {
+        let mut challenge = new();
+        challenge.winner_count = 1;
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.update_metadata(Some("name".to_string()), None, None);
     }
 
-    #[private]
-    pub fn on_burn_nfts(&mut self, winner_id: AccountId, number_promises: u64) -> bool {
-        for index in 0..number_promises {
-            // env::promise_result(i) has the result of the i-th call
-            let result: PromiseResult = env::promise_result(index);
-            if result == PromiseResult::Failed {}
-            match result {
-                PromiseResult::Failed => {
-                    log!(
-                        "There was an error burning the challenge NFT at index {}",
-                        index
-                    );
-                }
-                PromiseResult::Successful(_) => {
-                    log!("NFT burned successfully at index {}", index);
-                }
-            }
-        }
-        // Even if burn failed we want to increment winners and mark them down
-        // as a winner, since the contract now owns all the challenge NFTs, and
-        // the user has none. This is to prevent them from claiming again, an
-        // unofficial burn.
-        self.winner_count += 1;
-        self.winners.insert(winner_id, 1);
-        true
+    #[test]
+    #[should_panic(expected = "Metadata locked")]
+    fn update_metadata_after_lock() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.lock_metadata();
+        assert_eq!(challenge.is_metadata_locked(), true);
+        challenge.update_metadata(None, Some("description".to_string()), None);
     }
 
-    pub fn update_challenge_completion_status(&mut self, is_complete: bool) {
-        self.assert_challenge_owner();
-        if self.creator_can_update {
-            self.challenge_completed = is_complete;
-        } else {
-            panic!("The creator cannot update the completion status of this challenge");
-        }
+    #[test]
+    fn set_burn_flags() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.set_burn_flags(vec![false, true]);
+        assert_eq!(
+            challenge
+                .get_challenge_metadata()
+                .burn_challenge_piece_on_claim,
+            vec![false, true]
+        );
     }
 
-    pub fn ensure_challenge_not_expired(&mut self) -> bool {
-        if env::block_timestamp() > self.expiration_date_in_ns {
-            self.challenge_completed = true;
-        }
-        self.challenge_completed
+    #[test]
+    #[should_panic(expected = "Burn flags can't be changed once there are winners")]
+    fn set_burn_flags_after_a_win() {
+        let mut challenge = new();
+        challenge.record_winner(AccountId::from_str("account_id").unwrap());
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.set_burn_flags(vec![false, false]);
     }
 
-    // -------------------------- private methods ---------------------------
-    #[private]
-    pub fn mint_nft_callback(
-        &self,
-        #[callback_result] call_result: Result<(), near_sdk::PromiseError>,
-    ) {
-        if call_result.is_err() {
-            panic!("There was an error minting the NFT");
-        }
+    #[test]
+    #[should_panic(expected = "Burn flags can't be changed while claims are in flight")]
+    fn set_burn_flags_with_claim_in_flight() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        challenge.take_claim_slot(&account_id, None);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.set_burn_flags(vec![false, false]);
     }
 
-    // -------------------------- internal methods ---------------------------
-    fn decrement_winners(&mut self) {
-        self.potential_winners_left -= 1;
+    #[test]
+    fn set_burn_flags_keeps_transferred_pieces() {
+        let mut challenge = new();
+        let treasury = AccountId::from_str("treasury").unwrap();
+        challenge.pieces.get_mut(1).unwrap().action = PieceAction::TransferTo(treasury.clone());
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.set_burn_flags(vec![false, false]);
+        assert_eq!(challenge.pieces[0].action, PieceAction::Keep);
+        assert_eq!(
+            challenge.pieces[1].action,
+            PieceAction::TransferTo(treasury)
+        );
     }
 
-    fn increment_winners(&mut self) {
-        self.potential_winners_left += 1;
+    #[test]
+    #[should_panic(
+        expected = "The piece at index 1 is transferred on claim, so it can't be burned"
+    )]
+    fn set_burn_flags_burning_transferred_piece() {
+        let mut challenge = new();
+        challenge.pieces.get_mut(1).unwrap().action =
+            PieceAction::TransferTo(AccountId::from_str("treasury").unwrap());
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.set_burn_flags(vec![true, true]);
     }
 
-    fn assert_challenge_owner(&self) {
-        assert!(
-            self.owner_id == env::predecessor_account_id(),
-            "This method can only be called by the challenge owner"
+    #[test]
+    fn extend_expiration_by() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.extend_expiration_by(86400);
+        assert_eq!(
+            challenge.get_challenge_metadata().expiration_date_in_ns,
+            1000000000000 + 86400 * 1000000000
         );
     }
-}
 
-/*
- * The rest of this file holds the inline tests for the code above
- * Learn more about Rust tests: https://doc.rust-lang.org/book/ch11-01-writing-tests.html
- */
-#[cfg(test)]
-mod tests {
-    use std::str::FromStr;
+    #[test]
+    #[should_panic(expected = "The new expiration date overflows")]
+    fn extend_expiration_by_overflow() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.extend_expiration_by(u64::MAX);
+    }
 
-    use super::*;
     #[test]
-    #[should_panic]
-    fn default_nft_challenge() {
-        Contract::default();
+    fn co_owner_can_pause() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.set_co_owner(Some(AccountId::from_str("co_owner_id").unwrap()));
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("co_owner_id").unwrap())
+            .build());
+        challenge.pause();
+        assert_eq!(challenge.is_paused(), true);
+        challenge.unpause();
+        assert_eq!(challenge.is_paused(), false);
     }
 
     #[test]
-    fn new() -> Contract {
-        Contract::new(
-            "owner_id".to_string(),
-            "name".to_string(),
-            "description".to_string(),
-            "media_link".to_string(),
-            "reward_nft".to_string(),
-            vec![
-                "challenge_nft_id1".to_string(),
-                "challenge_nft_id2".to_string(),
-            ],
-            vec![true, false],
-            1000000000000,
-            1,
-            true,
-            NFTTokenMetadata {
-                title: None,
-                description: None,
-                media: None,
-                copies: None,
-                expires_at: None,
-                starts_at: None,
-                extra: None,
-                reference: None,
-                reference_hash: None,
-                media_hash: None,
-            },
-        )
+    fn co_owner_can_update_metadata() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .build());
+        challenge.set_co_owner(Some(AccountId::from_str("co_owner_id").unwrap()));
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("co_owner_id").unwrap())
+            .build());
+        let metadata = challenge.update_metadata(None, Some("fixed".to_string()), None);
+        assert_eq!(metadata.description, "fixed");
     }
 
     #[test]
-    fn get_challenge_metadata() {
-        let challenge = new();
-        let metadata = challenge.get_challenge_metadata();
-        assert_eq!(metadata.owner_id, "owner_id");
-        assert_eq!(metadata.name, "name");
-        assert_eq!(metadata.description, "description");
-        assert_eq!(metadata.media_link.unwrap(), "media_link");
-        assert_eq!(metadata.reward_nft_id, "reward_nft");
-        assert_eq!(metadata.challenge_nft_ids[0], "challenge_nft_id1");
-        assert_eq!(metadata.challenge_nft_ids[1], "challenge_nft_id2");
-        assert_eq!(metadata.burn_challenge_piece_on_claim[0], true);
-        assert_eq!(metadata.burn_challenge_piece_on_claim[1], false);
-        assert_eq!(metadata.challenge_nft_ids.len(), 2);
-        assert_eq!(metadata.expiration_date_in_ns, 1000000000000);
-        assert_eq!(metadata.winner_limit, 1);
-        assert_eq!(metadata.challenge_completed, false);
-        assert_eq!(metadata.winners_count, 0);
+    #[should_panic(expected = "This method can only be called by the challenge owner")]
+    fn co_owner_cannot_set_co_owner() {
+        let mut challenge = new();
+        challenge.co_owner_id = Some(AccountId::from_str("co_owner_id").unwrap());
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("co_owner_id").unwrap())
+            .build());
+        challenge.set_co_owner(None);
     }
 
     #[test]
-    fn get_owner_id() {
-        let challenge = new();
-        assert_eq!(challenge.get_owner_id(), "owner_id");
+    #[should_panic(expected = "This method can only be called by the challenge owner or co-owner")]
+    fn non_admin_cannot_pause() {
+        let mut challenge = new();
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("not_owner").unwrap())
+            .build());
+        challenge.pause();
     }
 
     #[test]
-    fn is_challenge_expired() {
+    fn get_mutation_log() {
         let mut challenge = new();
-        assert_eq!(challenge.is_challenge_expired(), false);
-        challenge.challenge_completed = true;
-        challenge.expiration_date_in_ns = 0;
-        assert_eq!(challenge.is_challenge_expired(), true);
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(AccountId::from_str("owner_id").unwrap())
+            .block_timestamp(10)
+            .build());
+        challenge.set_co_owner(Some(AccountId::from_str("co_owner_id").unwrap()));
+        challenge.pause();
+        let log = challenge.get_mutation_log(0, 10);
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].method, "set_co_owner");
+        assert_eq!(log[0].old_value, "null");
+        assert_eq!(log[0].new_value, "\"co_owner_id\"");
+        assert_eq!(log[0].timestamp, 10);
+        assert_eq!(log[0].caller, AccountId::from_str("owner_id").unwrap());
+        assert_eq!(log[1].method, "pause");
+        assert_eq!(log[1].old_value, "false");
+        assert_eq!(log[1].new_value, "true");
     }
 
     #[test]
-    fn potential_winners_left() {
+    fn claim_is_destructive_with_burnable_piece() {
+        let challenge = new();
+        assert_eq!(challenge.claim_is_destructive(), true);
+    }
+
+    #[test]
+    fn claim_is_destructive_without_burnable_pieces() {
         let mut challenge = new();
-        assert_eq!(challenge.potential_winners_left(), 1);
-        challenge.decrement_winners();
-        assert_eq!(challenge.potential_winners_left(), 0);
-        challenge.increment_winners();
-        assert_eq!(challenge.potential_winners_left(), 1);
+        challenge.pieces.get_mut(0).unwrap().action = PieceAction::Keep;
+        assert_eq!(challenge.claim_is_destructive(), false);
     }
 
     #[test]
-    fn is_account_winner() {
+    fn get_burn_transfer_deposit() {
         let mut challenge = new();
         assert_eq!(
-            challenge.is_account_winner(AccountId::from_str("account_id").unwrap()),
-            false
+            challenge.get_burn_transfer_deposit(),
+            NearToken::from_yoctonear(1)
         );
-        challenge
-            .winners
-            .insert(AccountId::from_str("account_id").unwrap(), 1);
+        challenge.pieces.get_mut(1).unwrap().action =
+            PieceAction::TransferTo(AccountId::from_str("treasury").unwrap());
         assert_eq!(
-            challenge.is_account_winner(AccountId::from_str("account_id").unwrap()),
-            true
+            challenge.get_burn_transfer_deposit(),
+            NearToken::from_yoctonear(2)
+        );
+    }
+
+    #[test]
+    fn get_recent_attempts() {
+        let mut challenge = new();
+        let account_id = AccountId::from_str("account_id").unwrap();
+        for _ in 0..MAX_RECENT_ATTEMPTS {
+            challenge.record_attempt(account_id.clone(), CLAIM_OUTCOME_MISSING_PIECE);
+        }
+        challenge.record_attempt(account_id.clone(), CLAIM_OUTCOME_WON);
+        let attempts = challenge.get_recent_attempts(0, MAX_RECENT_ATTEMPTS);
+        assert_eq!(attempts.len(), MAX_RECENT_ATTEMPTS as usize);
+        assert_eq!(attempts.last().unwrap().outcome, CLAIM_OUTCOME_WON);
+        let attempts = challenge.get_recent_attempts(MAX_RECENT_ATTEMPTS - 1, 10);
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].outcome, CLAIM_OUTCOME_WON);
+        assert_eq!(
+            challenge.get_recent_attempts(MAX_RECENT_ATTEMPTS, 10).len(),
+            0
         );
     }
 }
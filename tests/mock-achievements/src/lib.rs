@@ -0,0 +1,24 @@
+use near_sdk::{json_types::Base64VecU8, near, AccountId};
+
+// Records every achievement granted to it, so the sandbox tests can check
+// the challenge made the grant.
+#[near(contract_state)]
+#[derive(Default)]
+pub struct Contract {
+    // Every grant received, in order, as (account id, challenge hash).
+    grants: Vec<(AccountId, Vec<u8>)>,
+}
+
+#[near]
+impl Contract {
+    pub fn grant_achievement(&mut self, account_id: AccountId, challenge_hash: Base64VecU8) {
+        self.grants.push((account_id, challenge_hash.into()));
+    }
+
+    pub fn get_grants(&self) -> Vec<(AccountId, Base64VecU8)> {
+        self.grants
+            .iter()
+            .map(|(account_id, challenge_hash)| (account_id.clone(), challenge_hash.clone().into()))
+            .collect()
+    }
+}
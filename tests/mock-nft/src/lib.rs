@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, HashMap};
+
+use near_sdk::{env, json_types::U128, near, serde_json, AccountId, PromiseOrValue};
+
+// The NEP-177 times of a token, in epoch milliseconds.
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub struct TokenMetadata {
+    pub issued_at: Option<String>,
+    pub starts_at: Option<String>,
+}
+
+// A token as returned by `nft_tokens_for_owner`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone)]
+pub struct Token {
+    pub token_id: String,
+    pub owner_id: AccountId,
+    pub metadata: TokenMetadata,
+    pub approved_account_ids: HashMap<AccountId, u64>,
+}
+
+// Mintbase's storage costs, in yoctoNEAR.
+#[near(serializers = [json])]
+pub struct StorageCosts {
+    pub storage_price_per_byte: U128,
+    pub common: U128,
+    pub token: U128,
+}
+
+// The bare NEP-171 calls the challenge makes on a piece contract, with
+// switches to make them fail, so the sandbox tests can exercise the claim
+// flow's failure handling. It also stands in for a reward store that mints
+// synchronously.
+#[near(contract_state)]
+#[derive(Default)]
+pub struct Contract {
+    // Every token, by token id.
+    tokens: BTreeMap<String, Token>,
+    next_approval_id: u64,
+    next_token_id: u64,
+    // Whether `nft_batch_burn` panics.
+    fail_burns: bool,
+    // An account `nft_transfer` refuses to transfer to.
+    blocked_receiver: Option<AccountId>,
+    // How many tokens `nft_batch_mint` mints in total before it stops.
+    mint_cap: Option<u64>,
+}
+
+#[near]
+impl Contract {
+    pub fn set_fail_burns(&mut self, fail_burns: bool) {
+        self.fail_burns = fail_burns;
+    }
+
+    pub fn set_blocked_receiver(&mut self, blocked_receiver: Option<AccountId>) {
+        self.blocked_receiver = blocked_receiver;
+    }
+
+    pub fn set_mint_cap(&mut self, mint_cap: Option<u64>) {
+        self.mint_cap = mint_cap;
+    }
+
+    // Mints `token_id` to `owner_id`, issued at the current block time unless
+    // `issued_at` says otherwise. Token ids are free-form strings.
+    pub fn nft_mint(
+        &mut self,
+        owner_id: AccountId,
+        token_id: String,
+        issued_at: Option<String>,
+        starts_at: Option<String>,
+    ) {
+        assert!(!self.tokens.contains_key(&token_id), "Token already exists");
+        self.tokens.insert(
+            token_id.clone(),
+            Token {
+                token_id,
+                owner_id,
+                metadata: TokenMetadata {
+                    issued_at: Some(
+                        issued_at.unwrap_or_else(|| env::block_timestamp_ms().to_string()),
+                    ),
+                    starts_at,
+                },
+                approved_account_ids: HashMap::new(),
+            },
+        );
+    }
+
+    pub fn check_is_minter(&self, account_id: AccountId) -> bool {
+        let _ = account_id;
+        true
+    }
+
+    pub fn get_storage_costs(&self) -> StorageCosts {
+        let storage_price_per_byte = env::storage_byte_cost().as_yoctonear();
+        StorageCosts {
+            storage_price_per_byte: U128(storage_price_per_byte),
+            common: U128(storage_price_per_byte * 80),
+            token: U128(storage_price_per_byte * 360),
+        }
+    }
+
+    // Mints `num_to_mint` tokens to `owner_id` and resolves with a value
+    // instead of a promise, unlike Mintbase. The value is null, like
+    // `PromiseOrValue::Value(())`, unless a mint cap is set, in which case it
+    // lists the ids of the tokens minted before hitting the cap.
+    #[payable]
+    pub fn nft_batch_mint(
+        &mut self,
+        owner_id: AccountId,
+        metadata: serde_json::Value,
+        num_to_mint: u64,
+        royalty_args: Option<serde_json::Value>,
+        split_owners: Option<serde_json::Value>,
+    ) -> PromiseOrValue<Option<Vec<String>>> {
+        let _ = (metadata, royalty_args, split_owners);
+        let num_to_mint = match self.mint_cap {
+            Some(mint_cap) => num_to_mint.min(mint_cap.saturating_sub(self.next_token_id)),
+            None => num_to_mint,
+        };
+        let mut token_ids = vec![];
+        for _ in 0..num_to_mint {
+            let token_id = format!("reward-{}", self.next_token_id);
+            self.next_token_id += 1;
+            self.nft_mint(owner_id.clone(), token_id.clone(), None, None);
+            token_ids.push(token_id);
+        }
+        PromiseOrValue::Value(self.mint_cap.map(|_| token_ids))
+    }
+
+    pub fn nft_token(&self, token_id: String) -> Option<Token> {
+        self.tokens.get(&token_id).cloned()
+    }
+
+    pub fn nft_tokens_for_owner(
+        &self,
+        account_id: AccountId,
+        from_index: Option<String>,
+        limit: Option<u32>,
+    ) -> Vec<Token> {
+        let _ = (from_index, limit);
+        self.tokens
+            .values()
+            .filter(|token| token.owner_id == account_id)
+            .cloned()
+            .collect()
+    }
+
+    #[payable]
+    pub fn nft_approve(&mut self, token_id: String, account_id: AccountId, msg: Option<String>) {
+        let _ = msg;
+        self.next_approval_id += 1;
+        let approval_id = self.next_approval_id;
+        let token = self.tokens.get_mut(&token_id).expect("Token not found");
+        assert_eq!(
+            token.owner_id,
+            env::predecessor_account_id(),
+            "Only the owner can approve"
+        );
+        token.approved_account_ids.insert(account_id, approval_id);
+    }
+
+    pub fn nft_approval_id(&self, token_id: String, account_id: AccountId) -> Option<u64> {
+        self.tokens
+            .get(&token_id)
+            .and_then(|token| token.approved_account_ids.get(&account_id).copied())
+    }
+
+    #[payable]
+    pub fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: String,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        let _ = (approval_id, memo);
+        assert!(
+            self.blocked_receiver.as_ref() != Some(&receiver_id),
+            "Transfers to this receiver are blocked"
+        );
+        let token = self.tokens.get_mut(&token_id).expect("Token not found");
+        let sender_id = env::predecessor_account_id();
+        assert!(
+            token.owner_id == sender_id || token.approved_account_ids.contains_key(&sender_id),
+            "Not allowed to transfer this token"
+        );
+        token.owner_id = receiver_id;
+        token.approved_account_ids.clear();
+    }
+
+    #[payable]
+    pub fn nft_batch_burn(&mut self, token_ids: Vec<String>) {
+        assert!(!self.fail_burns, "Burning is disabled");
+        for token_id in token_ids {
+            let token = self.tokens.get(&token_id).expect("Token not found");
+            assert_eq!(
+                token.owner_id,
+                env::predecessor_account_id(),
+                "Only the owner can burn"
+            );
+            self.tokens.remove(&token_id);
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use near_sdk::{near, serde_json::Value};
+
+// Serves whatever config it was given, so the sandbox tests can create
+// challenges from it.
+#[near(contract_state)]
+#[derive(Default)]
+pub struct Contract {
+    // JSON encoding of the config returned by `get_config`.
+    config: String,
+}
+
+#[near]
+impl Contract {
+    pub fn set_config(&mut self, config: Value) {
+        self.config = config.to_string();
+    }
+
+    pub fn get_config(&self) -> Value {
+        near_sdk::serde_json::from_str(&self.config).unwrap()
+    }
+}
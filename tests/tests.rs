@@ -2,10 +2,13 @@ use near_sdk::{json_types::Base64VecU8, log};
 use near_sdk::{AccountId, Gas, NearToken};
 use near_workspaces::error::Error;
 use near_workspaces::network::Sandbox;
+use near_workspaces::operations::Function;
 use near_workspaces::result::ExecutionFinalResult;
 use near_workspaces::{Contract, Worker};
 use nft_challenge::{
-    ChallengeMetaData, NFTTokenMetadata, RoyaltyArgs, SplitBetweenUnparsed, TokenCompliant,
+    ApprovalCall, Capacity, ChallengeMetaData, ClaimAttempt, ClaimReceipt, ClaimResult,
+    NFTTokenMetadata, PieceAction, PieceRole, RoyaltyArgs, SplitBetweenUnparsed, StorageCosts,
+    TokenCompliant, CLAIM_OUTCOME_MISSING_PIECE, CLAIM_OUTCOME_WON,
 };
 
 use serde_json::json;
@@ -41,6 +44,29 @@ async fn create_challenge(
     winner_limit: u64,
     owner_id: AccountId,
     sandbox: &Worker<Sandbox>,
+) -> Result<Contract, Box<dyn std::error::Error>> {
+    create_challenge_with_args(
+        challenge_nft_ids,
+        burn_challenge_nft,
+        reward_nft_id,
+        winner_limit,
+        owner_id,
+        json!({}),
+        sandbox,
+    )
+    .await
+}
+
+// Same as `create_challenge`, but merges `extra_args` into the config passed
+// to `new`, overriding any defaults with the same key.
+async fn create_challenge_with_args(
+    challenge_nft_ids: Vec<String>,
+    burn_challenge_nft: Vec<bool>,
+    reward_nft_id: String,
+    winner_limit: u64,
+    owner_id: AccountId,
+    extra_args: serde_json::Value,
+    sandbox: &Worker<Sandbox>,
 ) -> Result<Contract, Box<dyn std::error::Error>> {
     let contract_wasm = near_workspaces::compile_project("./").await?;
 
@@ -53,32 +79,39 @@ async fn create_challenge(
         .unwrap();
     let timestamp_nanos = duration_since_epoch.as_nanos() as u64 + SECONDS_IN_DAY * NS_IN_SECONDS;
 
+    let mut args = json!({
+        "owner_id":owner_id.to_string(),
+        "name": "Test challenge".to_string(),
+        "description": "A description for a test chalenge",
+        "media_link": "A link to an image!",
+        "reward_nft_id": reward_nft_id,
+        "challenge_nft_ids": challenge_nft_ids,
+        "burn_challenge_piece_on_claim":burn_challenge_nft,
+        "expiration_date_in_ns": timestamp_nanos as u64,
+        "winner_limit": winner_limit,
+        "creator_can_update": true,
+        "reward_nft_metadata": NFTTokenMetadata{
+            title: Some("Reward NFT".to_string()),
+            description: Some("A description for a reward NFT".to_string()),
+            media: Some("A link to an image!".to_string()),
+            media_hash: Some(Base64VecU8::from(vec![0; 32])),
+            copies: None,
+            expires_at: None,
+            starts_at: None,
+            extra: None,
+            reference: None,
+            reference_hash: None,
+        },
+    });
+    if let (Some(args), Some(extra_args)) = (args.as_object_mut(), extra_args.as_object()) {
+        for (key, value) in extra_args {
+            args.insert(key.clone(), value.clone());
+        }
+    }
+
     let outcome = user_account
         .call(contract.id(), "new")
-        .args_json(json!({
-            "owner_id":owner_id.to_string(),
-            "name": "Test challenge".to_string(),
-            "description": "A description for a test chalenge",
-            "media_link": "A link to an image!",
-            "reward_nft_id": reward_nft_id,
-            "_challenge_nft_ids": challenge_nft_ids,
-            "_burn_challenge_piece_on_claim":burn_challenge_nft,
-            "expiration_date_in_ns": timestamp_nanos as u64,
-            "winner_limit": winner_limit,
-            "creator_can_update": true,
-            "reward_nft_metadata": NFTTokenMetadata{
-                title: Some("Reward NFT".to_string()),
-                description: Some("A description for a reward NFT".to_string()),
-                media: Some("A link to an image!".to_string()),
-                media_hash: None,
-                copies: None,
-                expires_at: None,
-                starts_at: None,
-                extra: None,
-                reference: None,
-                reference_hash: None,
-            },
-        }))
+        .args_json(json!({ "config": args }))
         .max_gas()
         .transact()
         .await?;
@@ -173,6 +206,7 @@ async fn test_complete_challenge_without_all_pieces() -> Result<(), Box<dyn std:
 
     let outcome_with_none = user_account
         .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
         .max_gas()
         .deposit(NearToken::from_near(1))
         .transact()
@@ -180,11 +214,7 @@ async fn test_complete_challenge_without_all_pieces() -> Result<(), Box<dyn std:
 
     log!("The original outcome: {:?}", outcome_with_none.logs());
 
-    assert!(outcome_with_none
-        .logs()
-        .last()
-        .unwrap()
-        .contains("Account does not own any of the challenge nfts at index 0"));
+    assert!(outcome_with_none.json::<ClaimResult>()? == ClaimResult::MissingPiece { index: 0 });
 
     // Try with some but not all pieces.
     let mint_outcome = user_account
@@ -215,15 +245,15 @@ async fn test_complete_challenge_without_all_pieces() -> Result<(), Box<dyn std:
 
     let outcome_with_some_challenge_pieces = user_account
         .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
         .max_gas()
         .deposit(NearToken::from_near(1))
         .transact()
         .await?;
-    assert!(outcome_with_some_challenge_pieces
-        .logs()
-        .last()
-        .unwrap()
-        .contains("Account does not own any of the challenge nfts at index 1"));
+    assert!(
+        outcome_with_some_challenge_pieces.json::<ClaimResult>()?
+            == ClaimResult::MissingPiece { index: 1 }
+    );
     Ok(())
 }
 
@@ -289,12 +319,14 @@ async fn test_complete_challenge_with_max_potential_winners(
 
     let promise_for_account0 = user_account0
         .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
         .max_gas()
         .deposit(NearToken::from_near(1))
         .transact();
 
     let promise_for_account1 = user_account1
         .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
         .max_gas()
         .deposit(NearToken::from_near(1))
         .transact();
@@ -350,6 +382,7 @@ async fn test_complete_challenge_with_max_potential_winners(
 
     let outcome_for_loser = loser_account
         .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
         .max_gas()
         .deposit(NearToken::from_near(1))
         .transact()
@@ -422,6 +455,7 @@ async fn test_complete_challenge_already_complete() -> Result<(), Box<dyn std::e
 
     let outcome_for_account0 = user_account0
         .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
         .max_gas()
         .deposit(NearToken::from_near(1))
         .transact()
@@ -441,6 +475,7 @@ async fn test_complete_challenge_already_complete() -> Result<(), Box<dyn std::e
 
     let outcome_for_account0_again = user_account0
         .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
         .max_gas()
         .deposit(NearToken::from_near(1))
         .transact()
@@ -464,6 +499,7 @@ async fn test_complete_challenge_already_complete() -> Result<(), Box<dyn std::e
 
     let outcome_for_account1 = user_account1
         .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
         .max_gas()
         .deposit(NearToken::from_near(1))
         .transact()
@@ -566,6 +602,7 @@ async fn test_mint_nft() -> Result<(), Box<dyn std::error::Error>> {
 
     let outcome_for_account0 = user_account0
         .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
         .max_gas()
         .deposit(NearToken::from_near(1))
         .transact()
@@ -598,6 +635,7 @@ async fn test_mint_nft() -> Result<(), Box<dyn std::error::Error>> {
 
     let outcome_for_challenge_mint = user_account0
         .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
         .deposit(NearToken::from_near(1))
         .max_gas()
         .transact()
@@ -625,6 +663,360 @@ async fn test_mint_nft() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_burn_failure_returns_piece() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+
+    // The mock piece contract refuses every burn, after the piece has
+    // already been moved into the challenge.
+    let piece_wasm = near_workspaces::compile_project("./tests/mock-nft").await?;
+    let piece = sandbox.dev_deploy(&piece_wasm).await?;
+    let outcome = piece
+        .call("set_fail_burns")
+        .args_json(json!({ "fail_burns": true }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let outcome = piece
+        .call("nft_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id(),
+            "token_id": "0",
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let challenge_contract = create_challenge(
+        vec![piece.id().to_string()],
+        vec![true],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let approve_outcome = user_account0
+        .call(piece.id(), "nft_approve")
+        .args_json(json!({
+            "token_id": "0",
+            "account_id": challenge_contract.id(),
+        }))
+        .deposit(NearToken::from_millinear(8))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(approve_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(outcome.json::<ClaimResult>()?, ClaimResult::BurnFailed);
+
+    // The piece lands back in the user's wallet, and the claim didn't win.
+    let token: serde_json::Value = piece
+        .view("nft_token")
+        .args_json(json!({ "token_id": "0" }))
+        .await?
+        .json()?;
+    assert_eq!(token["owner_id"], json!(user_account0.id()));
+
+    let winner_status: bool = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(!winner_status);
+
+    let capacity: Capacity = challenge_contract
+        .view("get_capacity")
+        .await?
+        .json()
+        .unwrap();
+    assert!(capacity.potential_left == 1);
+    assert!(capacity.inflight == 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_failure_returns_transferred_piece() -> Result<(), Box<dyn std::error::Error>>
+{
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+
+    let piece_wasm = near_workspaces::compile_project("./tests/mock-nft").await?;
+    let mut pieces = vec![];
+    for _ in 0..2 {
+        let piece = sandbox.dev_deploy(&piece_wasm).await?;
+        let outcome = piece
+            .call("nft_mint")
+            .args_json(json!({
+                "owner_id": user_account0.id(),
+                "token_id": "0",
+            }))
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+        pieces.push(piece);
+    }
+
+    let challenge_contract = create_challenge(
+        pieces.iter().map(|piece| piece.id().to_string()).collect(),
+        vec![true, true],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    // The second piece can't move into the challenge, while the first one
+    // does.
+    let outcome = pieces[1]
+        .call("set_blocked_receiver")
+        .args_json(json!({ "blocked_receiver": challenge_contract.id() }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    for piece in pieces.iter() {
+        let approve_outcome = user_account0
+            .call(piece.id(), "nft_approve")
+            .args_json(json!({
+                "token_id": "0",
+                "account_id": challenge_contract.id(),
+            }))
+            .deposit(NearToken::from_millinear(8))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(approve_outcome.is_success());
+    }
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(4))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(outcome.json::<ClaimResult>()?, ClaimResult::TransferFailed);
+
+    // Both pieces are with the user again, and nothing is left parked.
+    for piece in pieces.iter() {
+        let token: serde_json::Value = piece
+            .view("nft_token")
+            .args_json(json!({ "token_id": "0" }))
+            .await?
+            .json()?;
+        assert_eq!(token["owner_id"], json!(user_account0.id()));
+    }
+    let stuck_tokens: Vec<serde_json::Value> = challenge_contract
+        .view("get_stuck_tokens")
+        .args_json(json!({ "from_index": 0, "limit": 10 }))
+        .await?
+        .json()?;
+    assert!(stuck_tokens.is_empty());
+
+    let capacity: Capacity = challenge_contract
+        .view("get_capacity")
+        .await?
+        .json()
+        .unwrap();
+    assert!(capacity.potential_left == 1);
+    assert!(capacity.inflight == 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_evacuate_parked_tokens() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let to_account = sandbox.dev_create_account().await?;
+
+    // Both mock piece contracts refuse to burn and to hand the pieces back
+    // to the user, so the failed claim leaves them parked in the challenge.
+    let piece_wasm = near_workspaces::compile_project("./tests/mock-nft").await?;
+    let mut pieces = vec![];
+    for _ in 0..2 {
+        let piece = sandbox.dev_deploy(&piece_wasm).await?;
+        let outcome = piece
+            .call("set_fail_burns")
+            .args_json(json!({ "fail_burns": true }))
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+        let outcome = piece
+            .call("set_blocked_receiver")
+            .args_json(json!({ "blocked_receiver": user_account0.id() }))
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+        let outcome = piece
+            .call("nft_mint")
+            .args_json(json!({
+                "owner_id": user_account0.id(),
+                "token_id": "0",
+            }))
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+        pieces.push(piece);
+    }
+
+    let challenge_contract = create_challenge(
+        pieces.iter().map(|piece| piece.id().to_string()).collect(),
+        vec![true, true],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    for piece in pieces.iter() {
+        let approve_outcome = user_account0
+            .call(piece.id(), "nft_approve")
+            .args_json(json!({
+                "token_id": "0",
+                "account_id": challenge_contract.id(),
+            }))
+            .deposit(NearToken::from_millinear(8))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(approve_outcome.is_success());
+    }
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(4))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(outcome.json::<ClaimResult>()?, ClaimResult::BurnFailed);
+    let stuck_tokens: Vec<serde_json::Value> = challenge_contract
+        .view("get_stuck_tokens")
+        .args_json(json!({ "from_index": 0, "limit": 10 }))
+        .await?
+        .json()?;
+    assert_eq!(stuck_tokens.len(), 2);
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "evacuate")
+        .args_json(json!({ "to": to_account.id() }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // Every parked token lands at `to`, and the challenge is closed.
+    for piece in pieces.iter() {
+        let token: serde_json::Value = piece
+            .view("nft_token")
+            .args_json(json!({ "token_id": "0" }))
+            .await?
+            .json()?;
+        assert_eq!(token["owner_id"], json!(to_account.id()));
+    }
+    let stuck_tokens: Vec<serde_json::Value> = challenge_contract
+        .view("get_stuck_tokens")
+        .args_json(json!({ "from_index": 0, "limit": 10 }))
+        .await?
+        .json()?;
+    assert!(stuck_tokens.is_empty());
+    let is_complete: bool = challenge_contract
+        .view("is_challenge_complete")
+        .await?
+        .json()?;
+    assert!(is_complete);
+    let is_minting_frozen: bool = challenge_contract.view("is_minting_frozen").await?.json()?;
+    assert!(is_minting_frozen);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_burn_piece_with_string_token_id() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+
+    // NEP-171 token ids are free-form strings, unlike Mintbase's numeric ids.
+    let piece_wasm = near_workspaces::compile_project("./tests/mock-nft").await?;
+    let piece = sandbox.dev_deploy(&piece_wasm).await?;
+    let outcome = piece
+        .call("nft_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id(),
+            "token_id": "piece-abc",
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let challenge_contract = create_challenge(
+        vec![piece.id().to_string()],
+        vec![true],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let approve_outcome = user_account0
+        .call(piece.id(), "nft_approve")
+        .args_json(json!({
+            "token_id": "piece-abc",
+            "account_id": challenge_contract.id(),
+        }))
+        .deposit(NearToken::from_millinear(8))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(approve_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(outcome.json::<ClaimResult>()?, ClaimResult::Won);
+
+    // The piece was burned.
+    let token: Option<serde_json::Value> = piece
+        .view("nft_token")
+        .args_json(json!({ "token_id": "piece-abc" }))
+        .await?
+        .json()?;
+    assert!(token.is_none());
+
+    let winner_status: bool = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(winner_status);
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_burn_nfts() -> Result<(), Box<dyn std::error::Error>> {
     let sandbox = near_workspaces::sandbox().await?;
@@ -715,12 +1107,14 @@ async fn test_burn_nfts() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut outcome_for_account0 = user_account0
         .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
         .max_gas()
         .deposit(NearToken::from_yoctonear(4))
         .transact()
         .await?;
 
     assert!(outcome_for_account0.is_success());
+    assert!(outcome_for_account0.json::<ClaimResult>()? == ClaimResult::NeedsApproval);
 
     let mut account_0_status_call = challenge_contract
         .view("is_account_winner")
@@ -776,6 +1170,7 @@ async fn test_burn_nfts() -> Result<(), Box<dyn std::error::Error>> {
 
     outcome_for_account0 = user_account0
         .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
         .gas(Gas::from_tgas(300))
         .deposit(NearToken::from_yoctonear(4))
         .transact()
@@ -816,3 +1211,3844 @@ async fn test_burn_nfts() -> Result<(), Box<dyn std::error::Error>> {
     assert!(!outcome_owning_challenge_pieces.is_success());
     Ok(())
 }
+
+#[tokio::test]
+async fn test_transfer_pieces_to_treasury() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let treasury_account = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let mut challenge_nft_ids: Vec<String> = vec![];
+    let reward_nft = nfts.pop().unwrap();
+
+    for nft in nfts.iter() {
+        challenge_nft_ids.push(nft.id().to_string());
+    }
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        reward_nft.id().to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "piece_actions": vec![PieceAction::TransferTo(treasury_account.id().clone())],
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    assert!(metadata.piece_actions == vec![PieceAction::TransferTo(treasury_account.id().clone())]);
+
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+
+    assert!(mint_outcome.is_success());
+
+    let outcome_for_nfts_owned_by_user = user_account0
+        .call(nfts[0].id(), "nft_tokens_for_owner")
+        .args_json(json!({
+            "account_id": user_account0.id().clone(),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+
+    let token_id = outcome_for_nfts_owned_by_user
+        .json::<Vec<TokenCompliant>>()
+        .unwrap()[0]
+        .token_id
+        .clone();
+
+    let give_approval_outcome = user_account0
+        .call(nfts[0].id(), "nft_approve")
+        .args_json(json!({
+            "token_id": token_id.clone(),
+            "account_id": challenge_contract.id(),
+        }))
+        .deposit(NearToken::from_millinear(8))
+        .max_gas()
+        .transact()
+        .await?;
+
+    assert!(give_approval_outcome.is_success());
+
+    let outcome_for_account0 = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .gas(Gas::from_tgas(300))
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+
+    assert!(outcome_for_account0.is_success());
+
+    let account_0_status_call = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?;
+    let account_0_status: bool = account_0_status_call.json().unwrap();
+
+    assert!(account_0_status);
+
+    let outcome_for_nfts_owned_by_treasury = user_account0
+        .call(nfts[0].id(), "nft_tokens_for_owner")
+        .args_json(json!({
+            "account_id": treasury_account.id().clone(),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+
+    assert!(outcome_for_nfts_owned_by_treasury.is_success());
+    let treasury_tokens = outcome_for_nfts_owned_by_treasury
+        .json::<Vec<TokenCompliant>>()
+        .unwrap();
+    assert!(treasury_tokens.len() == 1);
+    assert!(treasury_tokens[0].token_id == token_id);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_recent_attempts() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let user_account1 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let mut challenge_nft_ids: Vec<String> = vec![];
+    let reward_nft_id = nfts.pop().unwrap().id().to_string();
+    for nft in nfts.iter() {
+        challenge_nft_ids.push(nft.id().to_string());
+    }
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![false],
+        reward_nft_id,
+        2,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+
+    // Neither account owns the challenge piece yet.
+    for account in [&user_account0, &user_account1] {
+        let outcome = account
+            .call(challenge_contract.id(), "initiate_claim")
+            .args_json(json!({}))
+            .max_gas()
+            .deposit(NearToken::from_near(1))
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+    }
+
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+
+    assert!(mint_outcome.is_success());
+
+    let outcome_for_account0 = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+
+    assert!(outcome_for_account0.is_success());
+
+    let attempts: Vec<ClaimAttempt> = challenge_contract
+        .view("get_recent_attempts")
+        .args_json(json!({
+            "from": 0,
+            "limit": 10,
+        }))
+        .await?
+        .json()
+        .unwrap();
+
+    assert!(attempts.len() == 3);
+    assert!(attempts[0].account == *user_account0.id());
+    assert!(attempts[0].outcome == CLAIM_OUTCOME_MISSING_PIECE);
+    assert!(attempts[1].account == *user_account1.id());
+    assert!(attempts[1].outcome == CLAIM_OUTCOME_MISSING_PIECE);
+    assert!(attempts[2].account == *user_account0.id());
+    assert!(attempts[2].outcome == CLAIM_OUTCOME_WON);
+
+    let attempts: Vec<ClaimAttempt> = challenge_contract
+        .view("get_recent_attempts")
+        .args_json(json!({
+            "from": 2,
+            "limit": 10,
+        }))
+        .await?
+        .json()
+        .unwrap();
+
+    assert!(attempts.len() == 1);
+    assert!(attempts[0].outcome == CLAIM_OUTCOME_WON);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reveal_reward_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let user_account1 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let mut challenge_nft_ids: Vec<String> = vec![];
+    let reward_nft = nfts.pop().unwrap();
+
+    for nft in nfts.iter() {
+        challenge_nft_ids.push(nft.id().to_string());
+    }
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        reward_nft.id().to_string(),
+        2,
+        user_account0.id().clone(),
+        json!({
+            "revealed_reward_metadata": NFTTokenMetadata {
+                title: Some("Revealed reward NFT".to_string()),
+                description: None,
+                media: None,
+                media_hash: None,
+                copies: None,
+                expires_at: None,
+                starts_at: None,
+                extra: None,
+                reference: None,
+                reference_hash: None,
+            },
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    assert!(!metadata.revealed);
+
+    for account in [&user_account0, &user_account1] {
+        let mint_outcome = user_account0
+            .call(nfts[0].id(), "nft_batch_mint")
+            .args_json(json!({
+                "owner_id": account.id().clone(),
+                "metadata":metadata.reward_nft_metadata,
+                "num_to_mint": 1,
+                "royalty_args": None::<RoyaltyArgs>,
+                "split_owners": None::<SplitBetweenUnparsed>,
+            }))
+            .deposit(NearToken::from_near(1))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(mint_outcome.is_success());
+
+        let claim_outcome = account
+            .call(challenge_contract.id(), "initiate_claim")
+            .args_json(json!({}))
+            .max_gas()
+            .deposit(NearToken::from_near(1))
+            .transact()
+            .await?;
+        assert!(claim_outcome.is_success());
+    }
+
+    let change_minters_outcome = user_account0
+        .call(reward_nft.id(), "batch_change_minters")
+        .args_json(json!({
+            "grant": vec![challenge_contract.id()],
+            "revoke":None::<Vec<AccountId>>,
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+
+    assert!(change_minters_outcome.is_success());
+
+    let outcome_for_challenge_mint = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_success());
+
+    // Only the challenge owner can reveal the reward.
+    let reveal_outcome = user_account1
+        .call(challenge_contract.id(), "reveal")
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(reveal_outcome.is_failure());
+
+    let reveal_outcome = user_account0
+        .call(challenge_contract.id(), "reveal")
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(reveal_outcome.is_success());
+
+    let outcome_for_challenge_mint = user_account1
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_success());
+
+    let mut reward_titles: Vec<String> = vec![];
+    for account in [&user_account0, &user_account1] {
+        let outcome_for_nfts_owned_by_user = account
+            .call(reward_nft.id(), "nft_tokens_for_owner")
+            .args_json(json!({
+                "account_id": account.id().clone(),
+            }))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(outcome_for_nfts_owned_by_user.is_success());
+        let tokens = outcome_for_nfts_owned_by_user.json::<serde_json::Value>()?;
+        reward_titles.push(tokens[0]["metadata"]["title"].as_str().unwrap().to_string());
+    }
+
+    assert!(reward_titles[0] == "Reward NFT");
+    assert!(reward_titles[1] == "Revealed reward NFT");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_claim_fee_refunded_on_failed_claim() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let user_account1 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let mut challenge_nft_ids: Vec<String> = vec![];
+    for nft in nfts.iter() {
+        challenge_nft_ids.push(nft.id().to_string());
+    }
+
+    let claim_fee = NearToken::from_near(1);
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "claim_fee": claim_fee,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    // Attaching less than the claim fee is rejected up front.
+    let outcome_without_fee = user_account1
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(outcome_without_fee.is_failure());
+
+    let balance_before_claim = user_account1.view_account().await?.balance;
+    // The account does not own the challenge piece, so the claim fails.
+    let outcome_for_account1 = user_account1
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(claim_fee.saturating_add(NearToken::from_yoctonear(2)))
+        .transact()
+        .await?;
+    assert!(outcome_for_account1.is_success());
+
+    let balance_after_claim = user_account1.view_account().await?.balance;
+    // Only gas should have been spent, since the claim fee was refunded.
+    assert!(
+        balance_before_claim.saturating_sub(balance_after_claim) < NearToken::from_millinear(100)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delegated_claim() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let owner_account = sandbox.dev_create_account().await?;
+    let delegate_account = sandbox.dev_create_account().await?;
+    let holder_account = sandbox.dev_create_account().await?;
+    let winner_account = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(owner_account.id().clone(), nft_ids, &sandbox).await?;
+    let mut challenge_nft_ids: Vec<String> = vec![];
+    for nft in nfts.iter() {
+        challenge_nft_ids.push(nft.id().to_string());
+    }
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        owner_account.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+
+    let mint_outcome = owner_account
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": holder_account.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let claim_args = json!({
+        "holder_id": holder_account.id(),
+        "winner_id": winner_account.id(),
+    });
+
+    // The delegate is not registered yet.
+    let outcome_for_delegate = delegate_account
+        .call(challenge_contract.id(), "initiate_claim_for")
+        .args_json(claim_args.clone())
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome_for_delegate.is_failure());
+
+    let add_delegate_outcome = owner_account
+        .call(challenge_contract.id(), "add_delegate")
+        .args_json(json!({
+            "account_id": delegate_account.id(),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(add_delegate_outcome.is_success());
+
+    let outcome_for_delegate = delegate_account
+        .call(challenge_contract.id(), "initiate_claim_for")
+        .args_json(claim_args)
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome_for_delegate.is_success());
+
+    for (account, is_winner) in [(&winner_account, true), (&holder_account, false)] {
+        let winner_status: bool = challenge_contract
+            .view("is_account_winner")
+            .args_json(json!({
+                "account_id": account.id()
+            }))
+            .await?
+            .json()
+            .unwrap();
+        assert!(winner_status == is_winner);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_staged_claim() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec![
+        "challenge-nft-1",
+        "challenge-nft-2",
+        "challenge-nft-3",
+        "challenge-nft-4",
+        "challenge-nft-5",
+        "challenge-nft-6",
+        "challenge-nft-7",
+        "challenge-nft-8",
+        "challenge-nft-9",
+        "challenge-nft-10",
+    ];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let mut challenge_nft_ids: Vec<String> = vec![];
+    for nft in nfts.iter() {
+        challenge_nft_ids.push(nft.id().to_string());
+    }
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false; 10],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "max_pieces_per_claim_tx": 5,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let stage_count: u32 = challenge_contract
+        .view("get_claim_stage_count")
+        .await?
+        .json()
+        .unwrap();
+    assert!(stage_count == 2);
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+
+    for nft in nfts.iter() {
+        let mint_outcome = user_account0
+            .call(nft.id(), "nft_batch_mint")
+            .args_json(json!({
+                "owner_id": user_account0.id().clone(),
+                "metadata":metadata.reward_nft_metadata,
+                "num_to_mint": 1,
+                "royalty_args": None::<RoyaltyArgs>,
+                "split_owners": None::<SplitBetweenUnparsed>,
+            }))
+            .deposit(NearToken::from_millinear(100))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(mint_outcome.is_success());
+    }
+
+    let stage_outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim_stage")
+        .args_json(json!({
+            "stage": 0,
+        }))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(10))
+        .transact()
+        .await?;
+    assert!(stage_outcome.is_success());
+
+    let progress: Vec<bool> = challenge_contract
+        .view("get_claim_stage_progress")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(progress == [vec![true; 5], vec![false; 5]].concat());
+
+    let winner_status: bool = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(!winner_status);
+
+    let stage_outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim_stage")
+        .args_json(json!({
+            "stage": 1,
+        }))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(10))
+        .transact()
+        .await?;
+    assert!(stage_outcome.is_success());
+
+    let winner_status: bool = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(winner_status);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_staged_claim_requeries_earlier_stages() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let user_account1 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "challenge-nft-2"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false; 2],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "max_pieces_per_claim_tx": 1,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    for nft in nfts.iter() {
+        let mint_outcome = user_account0
+            .call(nft.id(), "nft_batch_mint")
+            .args_json(json!({
+                "owner_id": user_account0.id().clone(),
+                "metadata":metadata.reward_nft_metadata,
+                "num_to_mint": 1,
+                "royalty_args": None::<RoyaltyArgs>,
+                "split_owners": None::<SplitBetweenUnparsed>,
+            }))
+            .deposit(NearToken::from_millinear(100))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(mint_outcome.is_success());
+    }
+
+    let stage_outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim_stage")
+        .args_json(json!({
+            "stage": 0,
+        }))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(stage_outcome.is_success());
+
+    // The piece verified in the first stage changes hands before the last
+    // stage, so the final ownership check must catch it.
+    let transfer_outcome = user_account0
+        .call(nfts[0].id(), "nft_transfer")
+        .args_json(json!({
+            "receiver_id": user_account1.id(),
+            "token_id": "0",
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(transfer_outcome.is_success());
+
+    let stage_outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim_stage")
+        .args_json(json!({
+            "stage": 1,
+        }))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(stage_outcome.is_success());
+    assert_eq!(
+        stage_outcome.json::<ClaimResult>()?,
+        ClaimResult::MissingPiece { index: 0 }
+    );
+
+    let winner_status: bool = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(!winner_status);
+
+    let capacity: Capacity = challenge_contract
+        .view("get_capacity")
+        .await?
+        .json()
+        .unwrap();
+    assert!(capacity.potential_left == 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_piece_minted_before_min_mint_time() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    // Only accept pieces minted a day from now, so the piece minted below is too old.
+    let duration_since_epoch = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    let min_mint_time_ns = duration_since_epoch.as_nanos() as u64 + SECONDS_IN_DAY * NS_IN_SECONDS;
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "min_mint_time_ns": [min_mint_time_ns],
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    assert!(metadata.min_mint_time_ns == vec![Some(min_mint_time_ns)]);
+
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let winner_status: bool = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(!winner_status);
+
+    let attempts: Vec<ClaimAttempt> = challenge_contract
+        .view("get_recent_attempts")
+        .args_json(json!({
+            "from": 0,
+            "limit": 10,
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(attempts.len() == 1);
+    assert!(attempts[0].outcome == CLAIM_OUTCOME_MISSING_PIECE);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_piece_minted_after_min_mint_time() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+
+    // The mock piece contract reports when each token was issued, in epoch
+    // milliseconds like NEP-177 asks.
+    let piece_wasm = near_workspaces::compile_project("./tests/mock-nft").await?;
+    let piece = sandbox.dev_deploy(&piece_wasm).await?;
+    let outcome = piece
+        .call("nft_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id(),
+            "token_id": "0",
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    // A token whose only time is an ISO 8601 date doesn't qualify.
+    let user_account1 = sandbox.dev_create_account().await?;
+    let outcome = piece
+        .call("nft_mint")
+        .args_json(json!({
+            "owner_id": user_account1.id(),
+            "token_id": "1",
+            "issued_at": "2030-01-01T00:00:00Z",
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // Accept pieces minted up to a day ago, so the piece minted above counts.
+    let duration_since_epoch = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    let min_mint_time_ns = duration_since_epoch.as_nanos() as u64 - SECONDS_IN_DAY * NS_IN_SECONDS;
+    let challenge_contract = create_challenge_with_args(
+        vec![piece.id().to_string()],
+        vec![false],
+        "reward-nft".to_string(),
+        2,
+        user_account0.id().clone(),
+        json!({
+            "min_mint_time_ns": [min_mint_time_ns],
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    for account in [&user_account0, &user_account1] {
+        let outcome = account
+            .call(challenge_contract.id(), "initiate_claim")
+            .args_json(json!({}))
+            .max_gas()
+            .deposit(NearToken::from_near(1))
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+    }
+
+    let winner_status: bool = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(winner_status);
+    let winner_status: bool = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account1.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(!winner_status);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rewards_minted_count() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let mut challenge_nft_ids: Vec<String> = vec![];
+    let reward_nft = nfts.pop().unwrap();
+
+    for nft in nfts.iter() {
+        challenge_nft_ids.push(nft.id().to_string());
+    }
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        reward_nft.id().to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "rewards_per_winner": 2,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome_for_account0 = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome_for_account0.is_success());
+
+    let change_minters_outcome = user_account0
+        .call(reward_nft.id(), "batch_change_minters")
+        .args_json(json!({
+            "grant": vec![challenge_contract.id()],
+            "revoke":None::<Vec<AccountId>>,
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(change_minters_outcome.is_success());
+
+    let rewards_minted_count: u64 = challenge_contract
+        .view("get_rewards_minted_count")
+        .await?
+        .json()
+        .unwrap();
+    assert!(rewards_minted_count == 0);
+
+    let outcome_for_challenge_mint = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_success());
+
+    let rewards_minted_count: u64 = challenge_contract
+        .view("get_rewards_minted_count")
+        .await?
+        .json()
+        .unwrap();
+    assert!(rewards_minted_count == metadata.rewards_per_winner);
+    assert!(rewards_minted_count == 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_panic_after_reserving_slot_reverts() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+
+    let challenge_contract = create_challenge(
+        vec!["challenge-nft-1".to_string()],
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    // The ownership check and its callback need more gas than is attached,
+    // so the claim fails when dispatching them, after the slot has been taken.
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .gas(Gas::from_tgas(10))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.failures()).contains("Exceeded the prepaid gas"));
+
+    let potential_winners_left: u64 = challenge_contract
+        .view("potential_winners_left")
+        .await?
+        .json()
+        .unwrap();
+    assert!(potential_winners_left == 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_auto_end_on_full_mint() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let mut challenge_nft_ids: Vec<String> = vec![];
+    let reward_nft = nfts.pop().unwrap();
+
+    for nft in nfts.iter() {
+        challenge_nft_ids.push(nft.id().to_string());
+    }
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        reward_nft.id().to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "auto_end_on_full_mint": true,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome_for_account0 = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome_for_account0.is_success());
+
+    let change_minters_outcome = user_account0
+        .call(reward_nft.id(), "batch_change_minters")
+        .args_json(json!({
+            "grant": vec![challenge_contract.id()],
+            "revoke":None::<Vec<AccountId>>,
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(change_minters_outcome.is_success());
+
+    // The only slot has been won, but the reward has not been minted yet.
+    let challenge_complete: bool = challenge_contract
+        .view("is_challenge_complete")
+        .await?
+        .json()
+        .unwrap();
+    assert!(!challenge_complete);
+
+    let outcome_for_challenge_mint = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_success());
+
+    assert!(outcome_for_challenge_mint
+        .logs()
+        .iter()
+        .any(|log| log.starts_with("EVENT_JSON:") && log.contains("\"challenge_ended\"")));
+
+    let challenge_complete: bool = challenge_contract
+        .view("is_challenge_complete")
+        .await?
+        .json()
+        .unwrap();
+    assert!(challenge_complete);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_piece_ownership_stats() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let user_account1 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "challenge-nft-2"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![false, false],
+        "reward-nft".to_string(),
+        2,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+
+    // Account 0 only owns the first piece, account 1 owns both.
+    for (nft, owners) in nfts
+        .iter()
+        .zip([vec![&user_account0, &user_account1], vec![&user_account1]])
+    {
+        for owner in owners {
+            let mint_outcome = user_account0
+                .call(nft.id(), "nft_batch_mint")
+                .args_json(json!({
+                    "owner_id": owner.id().clone(),
+                    "metadata":metadata.reward_nft_metadata,
+                    "num_to_mint": 1,
+                    "royalty_args": None::<RoyaltyArgs>,
+                    "split_owners": None::<SplitBetweenUnparsed>,
+                }))
+                .deposit(NearToken::from_millinear(100))
+                .max_gas()
+                .transact()
+                .await?;
+            assert!(mint_outcome.is_success());
+        }
+    }
+
+    for account in [&user_account0, &user_account1] {
+        let outcome = account
+            .call(challenge_contract.id(), "initiate_claim")
+            .args_json(json!({}))
+            .max_gas()
+            .deposit(NearToken::from_near(1))
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+    }
+
+    let stats: Vec<u64> = challenge_contract
+        .view("get_piece_ownership_stats")
+        .await?
+        .json()
+        .unwrap();
+    assert!(stats == vec![2, 1]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_claims_per_block() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let owner_account = sandbox.dev_create_account().await?;
+    let delegate_account = sandbox.dev_create_account().await?;
+    let mut winner_accounts = vec![];
+    for _ in 0..3 {
+        winner_accounts.push(sandbox.dev_create_account().await?);
+    }
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(owner_account.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        5,
+        owner_account.id().clone(),
+        json!({
+            "max_claims_per_block": 2,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let add_delegate_outcome = owner_account
+        .call(challenge_contract.id(), "add_delegate")
+        .args_json(json!({
+            "account_id": delegate_account.id(),
+        }))
+        .transact()
+        .await?;
+    assert!(add_delegate_outcome.is_success());
+
+    // Actions in a single transaction run in the same block.
+    let claim_batch = |winner_accounts: &[near_workspaces::Account]| {
+        let mut batch = delegate_account.batch(challenge_contract.id());
+        for winner_account in winner_accounts {
+            batch = batch.call(
+                Function::new("initiate_claim_for")
+                    .args_json(json!({
+                        "holder_id": winner_account.id(),
+                        "winner_id": winner_account.id(),
+                    }))
+                    .deposit(NearToken::from_yoctonear(2))
+                    .gas(Gas::from_tgas(90)),
+            );
+        }
+        batch.transact()
+    };
+
+    let outcome = claim_batch(&winner_accounts).await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.failures()).contains("rate limited"));
+
+    let potential_winners_left: u64 = challenge_contract
+        .view("potential_winners_left")
+        .await?
+        .json()
+        .unwrap();
+    assert!(potential_winners_left == 5);
+
+    let outcome = claim_batch(&winner_accounts[..2]).await?;
+    assert!(outcome.is_success());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bonus_piece_upgrades_reward() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let user_account1 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "bonus-nft-1", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let reward_nft = nfts.pop().unwrap();
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false, false],
+        reward_nft.id().to_string(),
+        2,
+        user_account0.id().clone(),
+        json!({
+            "piece_roles": [PieceRole::Required, PieceRole::Bonus],
+            "bonus_reward_metadata": [NFTTokenMetadata {
+                title: Some("Bonus reward NFT".to_string()),
+                description: None,
+                media: None,
+                media_hash: None,
+                copies: None,
+                expires_at: None,
+                starts_at: None,
+                extra: None,
+                reference: None,
+                reference_hash: None,
+            }],
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    assert!(metadata.piece_roles == vec![PieceRole::Required, PieceRole::Bonus]);
+
+    // Both accounts own the required piece, only account 1 owns the bonus piece.
+    for (nft, owners) in nfts
+        .iter()
+        .zip([vec![&user_account0, &user_account1], vec![&user_account1]])
+    {
+        for owner in owners {
+            let mint_outcome = user_account0
+                .call(nft.id(), "nft_batch_mint")
+                .args_json(json!({
+                    "owner_id": owner.id().clone(),
+                    "metadata":metadata.reward_nft_metadata,
+                    "num_to_mint": 1,
+                    "royalty_args": None::<RoyaltyArgs>,
+                    "split_owners": None::<SplitBetweenUnparsed>,
+                }))
+                .deposit(NearToken::from_millinear(100))
+                .max_gas()
+                .transact()
+                .await?;
+            assert!(mint_outcome.is_success());
+        }
+    }
+
+    let change_minters_outcome = user_account0
+        .call(reward_nft.id(), "batch_change_minters")
+        .args_json(json!({
+            "grant": vec![challenge_contract.id()],
+            "revoke":None::<Vec<AccountId>>,
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(change_minters_outcome.is_success());
+
+    let mut reward_titles: Vec<String> = vec![];
+    for account in [&user_account0, &user_account1] {
+        let claim_outcome = account
+            .call(challenge_contract.id(), "initiate_claim")
+            .args_json(json!({}))
+            .max_gas()
+            .deposit(NearToken::from_near(1))
+            .transact()
+            .await?;
+        assert!(claim_outcome.is_success());
+
+        let winner_status: bool = challenge_contract
+            .view("is_account_winner")
+            .args_json(json!({
+                "account_id": account.id()
+            }))
+            .await?
+            .json()
+            .unwrap();
+        assert!(winner_status);
+
+        let outcome_for_challenge_mint = account
+            .call(challenge_contract.id(), "mint_nft")
+            .args_json(json!({}))
+            .deposit(NearToken::from_near(1))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(outcome_for_challenge_mint.is_success());
+
+        let tokens = account
+            .call(reward_nft.id(), "nft_tokens_for_owner")
+            .args_json(json!({
+                "account_id": account.id().clone(),
+            }))
+            .max_gas()
+            .transact()
+            .await?
+            .json::<serde_json::Value>()?;
+        reward_titles.push(tokens[0]["metadata"]["title"].as_str().unwrap().to_string());
+    }
+
+    assert!(reward_titles[0] == "Reward NFT");
+    assert!(reward_titles[1] == "Bonus reward NFT");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_royalty_split_from_piece_owners() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let owner_account0 = sandbox.dev_create_account().await?;
+    let owner_account1 = sandbox.dev_create_account().await?;
+    let user_account = sandbox.dev_create_account().await?;
+    let mut nfts = create_nfts(
+        owner_account0.id().clone(),
+        vec!["challenge-nft-1", "reward-nft"],
+        &sandbox,
+    )
+    .await?;
+    let reward_nft = nfts.pop().unwrap();
+    nfts.extend(
+        create_nfts(
+            owner_account1.id().clone(),
+            vec!["challenge-nft-2"],
+            &sandbox,
+        )
+        .await?,
+    );
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false, false],
+        reward_nft.id().to_string(),
+        1,
+        owner_account0.id().clone(),
+        json!({
+            "royalty_from_piece_owners": 1000,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    assert!(metadata.royalty_from_piece_owners == Some(1000));
+
+    for (nft, owner) in nfts.iter().zip([&owner_account0, &owner_account1]) {
+        let mint_outcome = owner
+            .call(nft.id(), "nft_batch_mint")
+            .args_json(json!({
+                "owner_id": user_account.id().clone(),
+                "metadata":metadata.reward_nft_metadata,
+                "num_to_mint": 1,
+                "royalty_args": None::<RoyaltyArgs>,
+                "split_owners": None::<SplitBetweenUnparsed>,
+            }))
+            .deposit(NearToken::from_millinear(100))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(mint_outcome.is_success());
+    }
+
+    let claim_outcome = user_account
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(claim_outcome.is_success());
+
+    let change_minters_outcome = owner_account0
+        .call(reward_nft.id(), "batch_change_minters")
+        .args_json(json!({
+            "grant": vec![challenge_contract.id()],
+            "revoke":None::<Vec<AccountId>>,
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(change_minters_outcome.is_success());
+
+    let outcome_for_challenge_mint = user_account
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_success());
+
+    let split: Option<SplitBetweenUnparsed> = challenge_contract
+        .view("get_reward_royalty_split")
+        .await?
+        .json()
+        .unwrap();
+    let split = split.unwrap();
+    assert!(split.len() == 2);
+    assert!(split[owner_account0.id()] == 5000);
+    assert!(split[owner_account1.id()] == 5000);
+
+    let rewards_minted_count: u64 = challenge_contract
+        .view("get_rewards_minted_count")
+        .await?
+        .json()
+        .unwrap();
+    assert!(rewards_minted_count == 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_last_receipt() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let receipt: Option<ClaimReceipt> = challenge_contract
+        .view("get_last_receipt")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(receipt.is_none());
+
+    // The account does not own the challenge piece yet.
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let receipt: Option<ClaimReceipt> = challenge_contract
+        .view("get_last_receipt")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    let receipt = receipt.unwrap();
+    assert!(!receipt.won);
+    assert!(receipt.outcome == CLAIM_OUTCOME_MISSING_PIECE);
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let receipt: Option<ClaimReceipt> = challenge_contract
+        .view("get_last_receipt")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    let receipt = receipt.unwrap();
+    assert!(receipt.won);
+    assert!(receipt.outcome == CLAIM_OUTCOME_WON);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_preview_reward_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let user_account1 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "bonus-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false, false],
+        "reward-nft".to_string(),
+        2,
+        user_account0.id().clone(),
+        json!({
+            "piece_roles": [PieceRole::Required, PieceRole::Bonus],
+            "bonus_reward_metadata": [NFTTokenMetadata {
+                title: Some("Bonus reward NFT".to_string()),
+                description: None,
+                media: None,
+                media_hash: None,
+                copies: None,
+                expires_at: None,
+                starts_at: None,
+                extra: None,
+                reference: None,
+                reference_hash: None,
+            }],
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+
+    // Only account 1 owns the bonus piece.
+    let mint_outcome = user_account0
+        .call(nfts[1].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account1.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let mut previewed_titles: Vec<String> = vec![];
+    for account in [&user_account0, &user_account1] {
+        let preview = account
+            .call(challenge_contract.id(), "preview_reward_metadata")
+            .args_json(json!({
+                "account_id": account.id(),
+            }))
+            .max_gas()
+            .transact()
+            .await?
+            .json::<NFTTokenMetadata>()?;
+        previewed_titles.push(preview.title.unwrap());
+    }
+
+    assert!(previewed_titles[0] == "Reward NFT");
+    assert!(previewed_titles[1] == "Bonus reward NFT");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_winner_at() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let user_account1 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        2,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+
+    // Account 1 wins first, then account 0.
+    for account in [&user_account1, &user_account0] {
+        let mint_outcome = user_account0
+            .call(nfts[0].id(), "nft_batch_mint")
+            .args_json(json!({
+                "owner_id": account.id().clone(),
+                "metadata":metadata.reward_nft_metadata,
+                "num_to_mint": 1,
+                "royalty_args": None::<RoyaltyArgs>,
+                "split_owners": None::<SplitBetweenUnparsed>,
+            }))
+            .deposit(NearToken::from_millinear(100))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(mint_outcome.is_success());
+
+        let claim_outcome = account
+            .call(challenge_contract.id(), "initiate_claim")
+            .args_json(json!({}))
+            .max_gas()
+            .deposit(NearToken::from_near(1))
+            .transact()
+            .await?;
+        assert!(claim_outcome.is_success());
+    }
+
+    for (position, expected_winner) in [
+        (0, Some(user_account1.id().clone())),
+        (1, Some(user_account0.id().clone())),
+        (2, None),
+    ] {
+        let winner: Option<AccountId> = challenge_contract
+            .view("get_winner_at")
+            .args_json(json!({
+                "position": position,
+            }))
+            .await?
+            .json()
+            .unwrap();
+        assert!(winner == expected_winner);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_release_expired_reservation() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let owner_account = sandbox.dev_create_account().await?;
+    let user_account = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(owner_account.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    // Reservations expire right away, so the slot can be released in the
+    // next block.
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        owner_account.id().clone(),
+        json!({
+            "reservation_ttl_ns": 1,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let reserve_outcome = user_account
+        .call(challenge_contract.id(), "reserve_slot")
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(reserve_outcome.is_success());
+
+    let potential_winners_left: u64 = challenge_contract
+        .view("potential_winners_left")
+        .await?
+        .json()
+        .unwrap();
+    assert!(potential_winners_left == 0);
+
+    let balance_before_release = user_account.view_account().await?.balance;
+    let release_outcome = owner_account
+        .call(challenge_contract.id(), "release_expired_reservations")
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(release_outcome.is_success());
+    assert!(release_outcome.json::<u64>()? == 1);
+
+    let potential_winners_left: u64 = challenge_contract
+        .view("potential_winners_left")
+        .await?
+        .json()
+        .unwrap();
+    assert!(potential_winners_left == 1);
+
+    let balance_after_release = user_account.view_account().await?.balance;
+    assert!(
+        balance_after_release.as_yoctonear()
+            == balance_before_release.as_yoctonear() + NearToken::from_near(1).as_yoctonear()
+    );
+
+    // The reservation is gone, so it can no longer be completed.
+    let complete_outcome = user_account
+        .call(challenge_contract.id(), "complete_reservation")
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(complete_outcome.is_failure());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_winner_badge_minted_on_win() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "badge-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let badge_nft = nfts.pop().unwrap();
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "winner_badge_nft_id": badge_nft.id(),
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let change_minters_outcome = user_account0
+        .call(badge_nft.id(), "batch_change_minters")
+        .args_json(json!({
+            "grant": vec![challenge_contract.id()],
+            "revoke":None::<Vec<AccountId>>,
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(change_minters_outcome.is_success());
+
+    // Badges are paid for from a budget the owner funds up front.
+    let fund_outcome = user_account0
+        .call(challenge_contract.id(), "fund_badge_budget")
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(fund_outcome.is_success());
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    assert!(metadata.winner_badge_nft_id.as_ref() == Some(badge_nft.id()));
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("Winner badge minted")));
+
+    let badge_budget: NearToken = challenge_contract
+        .view("get_badge_budget")
+        .await?
+        .json()
+        .unwrap();
+    assert!(badge_budget < NearToken::from_near(1));
+
+    let badges: Vec<TokenCompliant> = badge_nft
+        .view("nft_tokens_for_owner")
+        .args_json(json!({
+            "account_id": user_account0.id().clone(),
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(badges.len() == 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_forfeit_unminted_win() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "mint_deadline_ns_after_win": NS_IN_SECONDS,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let potential_winners_left: u64 = challenge_contract
+        .view("potential_winners_left")
+        .await?
+        .json()
+        .unwrap();
+    assert!(potential_winners_left == 0);
+
+    // Move well past the one second mint deadline.
+    sandbox.fast_forward(100).await?;
+
+    let forfeit_outcome = user_account0
+        .call(challenge_contract.id(), "forfeit_unminted")
+        .args_json(json!({
+            "account_id": user_account0.id(),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(forfeit_outcome.is_success());
+
+    let potential_winners_left: u64 = challenge_contract
+        .view("potential_winners_left")
+        .await?
+        .json()
+        .unwrap();
+    assert!(potential_winners_left == 1);
+    let is_winner: bool = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id(),
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(!is_winner);
+
+    let mint_outcome = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_failure());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_slots_exhausted_and_available_events() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    // The account does not own the challenge piece, so the only slot is
+    // taken by the claim and restored once it fails.
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let logs = outcome.logs();
+    let position_of = |event: &str| {
+        logs.iter().position(|log| {
+            log.starts_with("EVENT_JSON:") && log.contains(&format!("\"{}\"", event))
+        })
+    };
+    let exhausted = position_of("slots_exhausted").unwrap();
+    let available = position_of("slots_available").unwrap();
+    assert!(exhausted < available);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_referral_counted_on_win() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let referrer_account = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    // Referring yourself is rejected before the claim counts as an attempt.
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({
+            "referral": user_account0.id(),
+        }))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.failures()).contains("You cannot refer yourself"));
+    let attempt_count: u32 = challenge_contract
+        .view("get_attempt_count")
+        .args_json(json!({
+            "account_id": user_account0.id(),
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(attempt_count == 0);
+
+    // The account does not own the challenge piece yet, so the referral is
+    // not credited.
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({
+            "referral": referrer_account.id(),
+        }))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let referral_count: u64 = challenge_contract
+        .view("get_referral_count")
+        .args_json(json!({
+            "account_id": referrer_account.id(),
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(referral_count == 0);
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({
+            "referral": referrer_account.id(),
+        }))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let referral_count: u64 = challenge_contract
+        .view("get_referral_count")
+        .args_json(json!({
+            "account_id": referrer_account.id(),
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(referral_count == 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mint_deposit_bounds_reward_store_storage_cost(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let reward_nft = nfts.pop().unwrap();
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![false],
+        reward_nft.id().to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // The challenge is not a minter on the reward contract yet, so the mint
+    // fails and the whole deposit comes back.
+    let balance_before_mint = user_account0.view_account().await?.balance;
+    let outcome_for_challenge_mint = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_failure());
+    let balance_after_mint = user_account0.view_account().await?.balance;
+    assert!(
+        balance_before_mint.saturating_sub(balance_after_mint) < NearToken::from_millinear(100)
+    );
+
+    let change_minters_outcome = user_account0
+        .call(reward_nft.id(), "batch_change_minters")
+        .args_json(json!({
+            "grant": vec![challenge_contract.id()],
+            "revoke":None::<Vec<AccountId>>,
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(change_minters_outcome.is_success());
+
+    // Challenges that don't configure a mint deposit require 54 millinear.
+    let mint_deposit: NearToken = challenge_contract
+        .view("get_mint_deposit")
+        .await?
+        .json()
+        .unwrap();
+    assert!(mint_deposit == NearToken::from_millinear(54));
+
+    let outcome_for_challenge_mint = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(mint_deposit.saturating_sub(NearToken::from_yoctonear(1)))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_failure());
+
+    // What the reward store charges for a mint call, the metadata and each
+    // token. The mint deposit is only an upper bound on it.
+    let storage_costs: StorageCosts = reward_nft.view("get_storage_costs").await?.json()?;
+    let metadata_bytes = near_sdk::borsh::to_vec(&metadata.reward_nft_metadata)
+        .unwrap()
+        .len() as u128;
+    let expected_cost = storage_costs.common.0
+        + storage_costs.storage_price_per_byte.0 * metadata_bytes
+        + storage_costs.token.0;
+    assert!(expected_cost <= mint_deposit.as_yoctonear());
+
+    // Only the exact storage cost is kept, everything above it is refunded.
+    let balance_before_mint = user_account0.view_account().await?.balance;
+    let storage_usage_before = reward_nft.view_account().await?.storage_usage;
+    let outcome_for_challenge_mint = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_success());
+    assert!(outcome_for_challenge_mint.json::<bool>()?);
+    assert!(outcome_for_challenge_mint
+        .logs()
+        .contains(&format!("Attaching {} yoctoNEAR to the reward mint", expected_cost).as_str()));
+    let balance_after_mint = user_account0.view_account().await?.balance;
+    assert!(
+        balance_before_mint.saturating_sub(balance_after_mint)
+            < NearToken::from_yoctonear(expected_cost)
+                .saturating_add(NearToken::from_millinear(25))
+    );
+
+    // The attached deposit covers the storage the mint actually used.
+    let storage_usage_after = reward_nft.view_account().await?.storage_usage;
+    let storage_used = (storage_usage_after - storage_usage_before) as u128;
+    assert!(storage_used * storage_costs.storage_price_per_byte.0 <= expected_cost);
+
+    let has_minted: bool = challenge_contract
+        .view("has_minted")
+        .args_json(json!({
+            "account_id": user_account0.id(),
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(has_minted);
+
+    // The reward can only be minted once per win.
+    let outcome_for_challenge_mint = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(mint_deposit)
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_failure());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mint_nft_rejects_disallowed_recipient() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let allowed_account = sandbox.dev_create_account().await?;
+    let bot_account = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let reward_nft = nfts.pop().unwrap();
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        reward_nft.id().to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "restrict_mint_recipient": true,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    assert!(metadata.restrict_mint_recipient);
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let change_minters_outcome = user_account0
+        .call(reward_nft.id(), "batch_change_minters")
+        .args_json(json!({
+            "grant": vec![challenge_contract.id()],
+            "revoke":None::<Vec<AccountId>>,
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(change_minters_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "add_mint_recipient")
+        .args_json(json!({
+            "account_id": allowed_account.id(),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome_for_challenge_mint = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({
+            "recipient": bot_account.id(),
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_failure());
+    let has_minted: bool = challenge_contract
+        .view("has_minted")
+        .args_json(json!({
+            "account_id": user_account0.id(),
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(!has_minted);
+
+    let outcome_for_challenge_mint = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({
+            "recipient": allowed_account.id(),
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_success());
+    let tokens: Vec<TokenCompliant> = reward_nft
+        .view("nft_tokens_for_owner")
+        .args_json(json!({
+            "account_id": allowed_account.id(),
+        }))
+        .await?
+        .json()?;
+    assert_eq!(tokens.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_repeat_winner_mints_once_per_win() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let reward_nft = nfts.pop().unwrap();
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        reward_nft.id().to_string(),
+        2,
+        user_account0.id().clone(),
+        json!({
+            "allow_repeat_wins": true,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let change_minters_outcome = user_account0
+        .call(reward_nft.id(), "batch_change_minters")
+        .args_json(json!({
+            "grant": vec![challenge_contract.id()],
+            "revoke":None::<Vec<AccountId>>,
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(change_minters_outcome.is_success());
+
+    for win in 1..=2 {
+        let outcome = user_account0
+            .call(challenge_contract.id(), "initiate_claim")
+            .args_json(json!({}))
+            .max_gas()
+            .deposit(NearToken::from_near(1))
+            .transact()
+            .await?;
+        assert!(outcome.is_success());
+
+        // Winning again is rejected until the reward of this win is minted.
+        let outcome = user_account0
+            .call(challenge_contract.id(), "initiate_claim")
+            .args_json(json!({}))
+            .max_gas()
+            .deposit(NearToken::from_near(1))
+            .transact()
+            .await?;
+        assert!(outcome.is_failure());
+
+        let outcome_for_challenge_mint = user_account0
+            .call(challenge_contract.id(), "mint_nft")
+            .args_json(json!({}))
+            .deposit(NearToken::from_near(1))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(outcome_for_challenge_mint.is_success());
+
+        let winners: Vec<(AccountId, u64)> = challenge_contract
+            .view("get_winners")
+            .args_json(json!({}))
+            .await?
+            .json()
+            .unwrap();
+        assert!(winners == vec![(user_account0.id().clone(), win)]);
+    }
+
+    let rewards_minted_count: u64 = challenge_contract
+        .view("get_rewards_minted_count")
+        .await?
+        .json()
+        .unwrap();
+    assert!(rewards_minted_count == 2);
+    let tokens: Vec<TokenCompliant> = reward_nft
+        .view("nft_tokens_for_owner")
+        .args_json(json!({
+            "account_id": user_account0.id(),
+        }))
+        .await?
+        .json()?;
+    assert!(tokens.len() == 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resolve_piece_names() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "challenge-nft-2"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let mut challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+    // A valid account id with no contract deployed to it.
+    challenge_nft_ids.push("missing-collection.test.near".to_string());
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids.clone(),
+        vec![false, false, false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "resolve_piece_names")
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let names: Vec<(String, Option<String>)> = outcome.json().unwrap();
+    assert!(
+        names
+            == vec![
+                (
+                    challenge_nft_ids[0].clone(),
+                    Some("challenge-nft-1".to_string())
+                ),
+                (
+                    challenge_nft_ids[1].clone(),
+                    Some("challenge-nft-2".to_string())
+                ),
+                (challenge_nft_ids[2].clone(), None),
+            ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_abandoned_reservation_reclaimed_by_next_claim(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let owner_account = sandbox.dev_create_account().await?;
+    let user_account = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(owner_account.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    // Reservations would be held for a day, but the claim TTL abandons them
+    // after a second.
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        owner_account.id().clone(),
+        json!({
+            "reservation_ttl_ns": SECONDS_IN_DAY * NS_IN_SECONDS,
+            "claim_ttl_ns": NS_IN_SECONDS,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let reserve_outcome = user_account
+        .call(challenge_contract.id(), "reserve_slot")
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(reserve_outcome.is_success());
+
+    // The only slot is reserved, so claims are rejected for now.
+    let outcome = owner_account
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+
+    sandbox.fast_forward(100).await?;
+
+    // The next claim reclaims the abandoned slot without a sweep. The owner
+    // does not hold the challenge piece, so the claim itself fails.
+    let outcome = owner_account
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log.contains("Releasing expired reservation")));
+
+    let potential_winners_left: u64 = challenge_contract
+        .view("potential_winners_left")
+        .await?
+        .json()
+        .unwrap();
+    assert!(potential_winners_left == 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_burn_only_flagged_pieces() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec![
+        "challenge-nft-1",
+        "challenge-nft-2",
+        "challenge-nft-3",
+        "reward-nft",
+    ];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let mut challenge_nft_ids: Vec<String> = vec![];
+    let reward_nft = nfts.pop().unwrap();
+
+    for nft in nfts.iter() {
+        challenge_nft_ids.push(nft.id().to_string());
+    }
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![true, false, true],
+        reward_nft.id().to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+
+    for i in 0..nfts.len() {
+        let mint_outcome = user_account0
+            .call(nfts[i].id(), "nft_batch_mint")
+            .args_json(json!({
+                "owner_id": user_account0.id().clone(),
+                "metadata":metadata.reward_nft_metadata,
+                "num_to_mint": 1,
+                "royalty_args": None::<RoyaltyArgs>,
+                "split_owners": None::<SplitBetweenUnparsed>,
+            }))
+            .deposit(NearToken::from_near(1))
+            .max_gas()
+            .transact()
+            .await?;
+
+        assert!(mint_outcome.is_success());
+    }
+
+    // Only the pieces that get burned need an approval; the kept piece in
+    // the middle is never approval-checked.
+    for i in [0, 2] {
+        let outcome_for_nfts_owned_by_user = user_account0
+            .call(nfts[i].id(), "nft_tokens_for_owner")
+            .args_json(json!({
+                "account_id": user_account0.id().clone(),
+            }))
+            .max_gas()
+            .transact()
+            .await?;
+
+        assert!(outcome_for_nfts_owned_by_user.is_success());
+
+        let token_id = outcome_for_nfts_owned_by_user
+            .json::<Vec<TokenCompliant>>()
+            .unwrap()[0]
+            .token_id
+            .clone();
+
+        let give_approval_outcome = user_account0
+            .call(nfts[i].id(), "nft_approve")
+            .args_json(json!({
+                "token_id":     token_id.clone(),
+                "account_id":challenge_contract.id(),
+            }))
+            .deposit(NearToken::from_millinear(8))
+            .max_gas()
+            .transact()
+            .await?;
+
+        assert!(give_approval_outcome.is_success());
+    }
+
+    let outcome_for_account0 = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .gas(Gas::from_tgas(300))
+        .deposit(NearToken::from_yoctonear(4))
+        .transact()
+        .await?;
+
+    assert!(outcome_for_account0.is_success());
+
+    let account_0_status_call = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?;
+    let account_0_status: bool = account_0_status_call.json().unwrap();
+    assert!(account_0_status);
+
+    for i in [0, 2] {
+        let outcome_owning_challenge_pieces = user_account0
+            .call(nfts[i].id(), "nft_tokens_for_owner")
+            .args_json(json!({
+                "account_id": user_account0.id().clone(),
+            }))
+            .max_gas()
+            .transact()
+            .await?;
+
+        assert!(!outcome_owning_challenge_pieces.is_success());
+    }
+
+    let outcome_owning_kept_piece = user_account0
+        .call(nfts[1].id(), "nft_tokens_for_owner")
+        .args_json(json!({
+            "account_id": user_account0.id().clone(),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+
+    assert!(outcome_owning_kept_piece.is_success());
+    assert_eq!(
+        outcome_owning_kept_piece
+            .json::<Vec<TokenCompliant>>()
+            .unwrap()
+            .len(),
+        1
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pre_approval_rejects_claim_without_taking_slot(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let reward_nft = nfts.pop().unwrap();
+
+    let challenge_contract = create_challenge_with_args(
+        vec![nfts[0].id().to_string()],
+        vec![true],
+        reward_nft.id().to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({ "require_pre_approval": true }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    assert!(metadata.require_pre_approval);
+
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    // The piece is burned on claim but was never approved to the challenge.
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert!(outcome.logs().iter().any(|log| {
+        log
+        == &"You must grant transfer approval for the challenge NFTs at indices [0] before claiming"
+    }));
+
+    let account_0_status: bool = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(!account_0_status);
+
+    let capacity: Capacity = challenge_contract
+        .view("get_capacity")
+        .await?
+        .json()
+        .unwrap();
+    assert!(capacity.potential_left == 1);
+    assert!(capacity.inflight == 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mint_nft_with_synchronous_reward_store() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    // The mock reward store resolves `nft_batch_mint` with a value rather
+    // than a promise.
+    let reward_wasm = near_workspaces::compile_project("./tests/mock-nft").await?;
+    let reward_nft = sandbox.dev_deploy(&reward_wasm).await?;
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![false],
+        reward_nft.id().to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome_for_challenge_mint = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_success());
+    assert!(outcome_for_challenge_mint
+        .logs()
+        .contains(&"Reward NFT minted"));
+
+    let has_minted: bool = challenge_contract
+        .view("has_minted")
+        .args_json(json!({
+            "account_id": user_account0.id(),
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(has_minted);
+    let rewards_minted_count: u64 = challenge_contract
+        .view("get_rewards_minted_count")
+        .await?
+        .json()
+        .unwrap();
+    assert!(rewards_minted_count == 1);
+    let tokens: Vec<serde_json::Value> = reward_nft
+        .view("nft_tokens_for_owner")
+        .args_json(json!({
+            "account_id": user_account0.id(),
+        }))
+        .await?
+        .json()?;
+    assert!(tokens.len() == 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_partial_mint_refunds_unminted_share() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    // The mock reward store hits its copies cap after a single token.
+    let reward_wasm = near_workspaces::compile_project("./tests/mock-nft").await?;
+    let reward_nft = sandbox.dev_deploy(&reward_wasm).await?;
+    let outcome = reward_nft
+        .call("set_mint_cap")
+        .args_json(json!({ "mint_cap": 1 }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        reward_nft.id().to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "rewards_per_winner": 3,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let outcome_for_challenge_mint = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_success());
+
+    // Two of the three rewards weren't minted, so two thirds of the deposit
+    // attached to the mint are refunded.
+    let logs = outcome_for_challenge_mint.logs();
+    let attached: u128 = logs
+        .iter()
+        .find_map(|log| {
+            log.strip_prefix("Attaching ")?
+                .strip_suffix(" yoctoNEAR to the reward mint")
+        })
+        .unwrap()
+        .parse()?;
+    let refund = attached * 2 / 3;
+    assert!(logs.contains(
+        &format!(
+            "Only 1 of 3 reward NFTs were minted, refunding {} yoctoNEAR",
+            refund
+        )
+        .as_str()
+    ));
+
+    let rewards_minted_count: u64 = challenge_contract
+        .view("get_rewards_minted_count")
+        .await?
+        .json()
+        .unwrap();
+    assert!(rewards_minted_count == 1);
+    let tokens: Vec<serde_json::Value> = reward_nft
+        .view("nft_tokens_for_owner")
+        .args_json(json!({
+            "account_id": user_account0.id(),
+        }))
+        .await?
+        .json()?;
+    assert!(tokens.len() == 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mint_nft_requires_minter() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let reward_nft = nfts.pop().unwrap();
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![false],
+        reward_nft.id().to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    // The reward store never granted the challenge minting rights.
+    let outcome_for_challenge_mint = user_account0
+        .call(challenge_contract.id(), "mint_nft")
+        .args_json(json!({}))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome_for_challenge_mint.is_failure());
+    assert!(format!("{:?}", outcome_for_challenge_mint.failures())
+        .contains("Challenge contract is not an approved minter on the reward store"));
+
+    let has_minted: bool = challenge_contract
+        .view("has_minted")
+        .args_json(json!({
+            "account_id": user_account0.id(),
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(!has_minted);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_build_approval_calls() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "challenge-nft-2", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let reward_nft = nfts.pop().unwrap();
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![true, false],
+        reward_nft.id().to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    for nft in nfts.iter() {
+        let mint_outcome = user_account0
+            .call(nft.id(), "nft_batch_mint")
+            .args_json(json!({
+                "owner_id": user_account0.id().clone(),
+                "metadata":metadata.reward_nft_metadata,
+                "num_to_mint": 1,
+                "royalty_args": None::<RoyaltyArgs>,
+                "split_owners": None::<SplitBetweenUnparsed>,
+            }))
+            .deposit(NearToken::from_near(1))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(mint_outcome.is_success());
+    }
+
+    // Only the burned piece needs an approval.
+    let outcome = user_account0
+        .call(challenge_contract.id(), "build_approval_calls")
+        .args_json(json!({
+            "account_id": user_account0.id(),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let calls: Vec<ApprovalCall> = outcome.json().unwrap();
+    assert!(calls.len() == 1);
+    assert!(&calls[0].contract == nfts[0].id());
+    assert!(&calls[0].account_id == challenge_contract.id());
+
+    let approve_outcome = user_account0
+        .call(&calls[0].contract, "nft_approve")
+        .args_json(json!({
+            "token_id": calls[0].token_id,
+            "account_id": calls[0].account_id,
+        }))
+        .deposit(NearToken::from_millinear(8))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(approve_outcome.is_success());
+
+    // Once approved, no calls are left to make.
+    let outcome = user_account0
+        .call(challenge_contract.id(), "build_approval_calls")
+        .args_json(json!({
+            "account_id": user_account0.id(),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let calls: Vec<ApprovalCall> = outcome.json().unwrap();
+    assert!(calls.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ft_gate_rejects_account_below_threshold() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let mainnet = near_workspaces::mainnet().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "reward-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let reward_nft = nfts.pop().unwrap();
+
+    // wNEAR stands in for the gating token, since it's a plain NEP-141
+    // contract that reports a zero balance for unregistered accounts.
+    let wrap_near_id: AccountId = "wrap.near".parse()?;
+    let ft = sandbox
+        .import_contract(&wrap_near_id, &mainnet)
+        .transact()
+        .await?;
+    let init_outcome = ft.call("new").max_gas().transact().await?;
+    assert!(init_outcome.is_success());
+
+    let challenge_contract = create_challenge_with_args(
+        vec![nfts[0].id().to_string()],
+        vec![false],
+        reward_nft.id().to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "ft_gate": [ft.id(), "1"],
+            "reservation_ttl_ns": SECONDS_IN_DAY * NS_IN_SECONDS,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(outcome.json::<ClaimResult>()?, ClaimResult::BelowFtGate);
+    assert!(outcome
+        .logs()
+        .iter()
+        .any(|log| log == &format!("Claiming requires holding at least 1 of {}", ft.id())));
+
+    let account_0_status: bool = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(!account_0_status);
+
+    let capacity: Capacity = challenge_contract
+        .view("get_capacity")
+        .await?
+        .json()
+        .unwrap();
+    assert!(capacity.potential_left == 1);
+
+    // Completing a reservation goes through the same gate, and releases the
+    // reserved slot when it is rejected.
+    let reserve_outcome = user_account0
+        .call(challenge_contract.id(), "reserve_slot")
+        .max_gas()
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(reserve_outcome.is_success());
+    let outcome = user_account0
+        .call(challenge_contract.id(), "complete_reservation")
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(outcome.json::<ClaimResult>()?, ClaimResult::BelowFtGate);
+
+    let capacity: Capacity = challenge_contract
+        .view("get_capacity")
+        .await?
+        .json()
+        .unwrap();
+    assert!(capacity.potential_left == 1);
+    assert!(capacity.inflight == 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_achievement_granted_on_win() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let achievements_wasm = near_workspaces::compile_project("./tests/mock-achievements").await?;
+    let achievements = sandbox.dev_deploy(&achievements_wasm).await?;
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "achievements_contract": achievements.id(),
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    assert!(metadata.achievements_contract.as_ref() == Some(achievements.id()));
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 1,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(outcome.json::<ClaimResult>()?, ClaimResult::Won);
+
+    let grants: Vec<(AccountId, Base64VecU8)> =
+        achievements.view("get_grants").await?.json().unwrap();
+    assert!(grants.len() == 1);
+    assert!(&grants[0].0 == user_account0.id());
+    assert!(grants[0].1 .0.len() == 32);
+
+    let last_grant: Option<bool> = challenge_contract
+        .view("get_last_achievement_grant")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(last_grant == Some(true));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_claim_logs_gas_usage() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let gas_log = outcome
+        .logs()
+        .into_iter()
+        .find(|log| log.starts_with("Claim resolved using "))
+        .unwrap();
+    // "Claim resolved using <used> of <prepaid> prepaid gas"
+    let figures: Vec<u64> = gas_log
+        .split(' ')
+        .filter_map(|word| word.parse().ok())
+        .collect();
+    assert!(figures.len() == 2);
+    assert!(figures[0] > 0);
+    assert!(figures[0] <= figures[1]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_attempts_per_account() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "max_attempts_per_account": 2,
+            "max_pieces_per_claim_tx": 1,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    // The account owns no pieces, so every claim fails, and staged claims
+    // use up attempts like regular ones.
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert!(outcome.json::<ClaimResult>()? == ClaimResult::MissingPiece { index: 0 });
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim_stage")
+        .args_json(json!({
+            "stage": 0,
+        }))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert!(outcome.json::<ClaimResult>()? == ClaimResult::MissingPiece { index: 0 });
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.failures()).contains("Attempt limit reached"));
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim_stage")
+        .args_json(json!({
+            "stage": 0,
+        }))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.failures()).contains("Attempt limit reached"));
+
+    let attempts: u32 = challenge_contract
+        .view("get_attempt_count")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(attempts == 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_claim_consolation() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let user_account1 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "consolation-nft"];
+    let mut nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let consolation_nft = nfts.pop().unwrap();
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let consolation_metadata = NFTTokenMetadata {
+        title: Some("Consolation NFT".to_string()),
+        description: Some("Thanks for taking part".to_string()),
+        media: None,
+        media_hash: None,
+        copies: None,
+        expires_at: None,
+        starts_at: None,
+        extra: None,
+        reference: None,
+        reference_hash: None,
+    };
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "consolation_reward": [consolation_nft.id(), consolation_metadata],
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let change_minters_outcome = user_account0
+        .call(consolation_nft.id(), "batch_change_minters")
+        .args_json(json!({
+            "grant": vec![challenge_contract.id()],
+            "revoke":None::<Vec<AccountId>>,
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(change_minters_outcome.is_success());
+
+    // Account 0 owns no pieces, so its attempt fails.
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(2))
+        .transact()
+        .await?;
+    assert!(outcome.json::<ClaimResult>()? == ClaimResult::MissingPiece { index: 0 });
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "claim_consolation")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert!(outcome.json::<bool>()?);
+
+    let consolations: Vec<TokenCompliant> = consolation_nft
+        .view("nft_tokens_for_owner")
+        .args_json(json!({
+            "account_id": user_account0.id().clone(),
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(consolations.len() == 1);
+
+    // Each account can only claim it once.
+    let outcome = user_account0
+        .call(challenge_contract.id(), "claim_consolation")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.failures()).contains("Consolation reward already claimed"));
+
+    // Account 1 never attempted the challenge.
+    let outcome = user_account1
+        .call(challenge_contract.id(), "claim_consolation")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_millinear(100))
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.failures())
+        .contains("You must attempt the challenge to claim the consolation reward"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cleanup_and_refund() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+
+    let challenge_contract = create_challenge(
+        vec![
+            "challenge-nft-1".to_string(),
+            "challenge-nft-2".to_string(),
+            "challenge-nft-3".to_string(),
+        ],
+        vec![false, false, false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "cleanup_and_refund")
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    assert!(format!("{:?}", outcome.failures()).contains("Challenge is not complete yet"));
+
+    let end_challenge_outcome = user_account0
+        .call(challenge_contract.id(), "end_challenge")
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(end_challenge_outcome.is_success());
+
+    let balance_before = user_account0.view_account().await?.balance;
+    let outcome = user_account0
+        .call(challenge_contract.id(), "cleanup_and_refund")
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    let refund: NearToken = outcome.json()?;
+    assert!(!refund.is_zero());
+    // Add back what the call cost in gas to see the refund itself.
+    let tokens_burnt = outcome
+        .outcomes()
+        .iter()
+        .fold(NearToken::from_yoctonear(0), |total, receipt| {
+            total.saturating_add(receipt.tokens_burnt)
+        });
+    let balance_after = user_account0.view_account().await?.balance;
+    assert!(balance_after.saturating_add(tokens_burnt) > balance_before);
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "cleanup_and_refund")
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_failure());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_required_pieces_threshold() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1", "challenge-nft-2", "challenge-nft-3"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![false, false, false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "required_pieces": 2,
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    assert!(metadata.required_pieces == 2);
+
+    // Only the first two of the three pieces are minted to the account.
+    for nft in nfts.iter().take(2) {
+        let mint_outcome = user_account0
+            .call(nft.id(), "nft_batch_mint")
+            .args_json(json!({
+                "owner_id": user_account0.id().clone(),
+                "metadata":metadata.reward_nft_metadata,
+                "num_to_mint": 1,
+                "royalty_args": None::<RoyaltyArgs>,
+                "split_owners": None::<SplitBetweenUnparsed>,
+            }))
+            .deposit(NearToken::from_millinear(100))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(mint_outcome.is_success());
+    }
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(6))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(outcome.json::<ClaimResult>()?, ClaimResult::Won);
+
+    let account_0_status: bool = challenge_contract
+        .view("is_account_winner")
+        .args_json(json!({
+            "account_id": user_account0.id()
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(account_0_status);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_burn_quantity() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+    let challenge_nft_ids: Vec<String> = nfts.iter().map(|nft| nft.id().to_string()).collect();
+
+    let challenge_contract = create_challenge_with_args(
+        challenge_nft_ids,
+        vec![true],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        json!({
+            "burn_quantities": [2],
+        }),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    assert!(metadata.pieces[0].spec.burn_quantity == 2);
+
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 2,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let tokens: Vec<TokenCompliant> = nfts[0]
+        .view("nft_tokens_for_owner")
+        .args_json(json!({
+            "account_id": user_account0.id().clone(),
+        }))
+        .await?
+        .json()
+        .unwrap();
+    assert!(tokens.len() == 2);
+    for token in tokens {
+        let give_approval_outcome = user_account0
+            .call(nfts[0].id(), "nft_approve")
+            .args_json(json!({
+                "token_id": token.token_id,
+                "account_id": challenge_contract.id(),
+            }))
+            .deposit(NearToken::from_millinear(8))
+            .max_gas()
+            .transact()
+            .await?;
+        assert!(give_approval_outcome.is_success());
+    }
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_yoctonear(4))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+    assert_eq!(outcome.json::<ClaimResult>()?, ClaimResult::Won);
+
+    // Both tokens were consumed, so the account owns none of the piece.
+    let outcome_owning_piece = user_account0
+        .call(nfts[0].id(), "nft_tokens_for_owner")
+        .args_json(json!({
+            "account_id": user_account0.id().clone(),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(!outcome_owning_piece.is_success());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_new_from_template() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+
+    let template_wasm = near_workspaces::compile_project("./tests/mock-template").await?;
+    let template = sandbox.dev_deploy(&template_wasm).await?;
+    let outcome = user_account0
+        .call(template.id(), "set_config")
+        .args_json(json!({
+            "config": {
+                "name": "Template challenge",
+                "description": "A challenge created from a template",
+                "media_link": "A link to an image!",
+                "reward_nft_id": "reward-nft",
+                "challenge_nft_ids": ["challenge-nft-1", "challenge-nft-2"],
+                "burn_challenge_piece_on_claim": [true, false],
+                "expiration_date_in_ns": 1000,
+                "winner_limit": 1,
+                "creator_can_update": true,
+                "reward_nft_metadata": NFTTokenMetadata {
+                    title: Some("Reward NFT".to_string()),
+                    description: None,
+                    media: None,
+                    media_hash: None,
+                    copies: None,
+                    expires_at: None,
+                    starts_at: None,
+                    extra: None,
+                    reference: None,
+                    reference_hash: None,
+                },
+            },
+        }))
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let contract_wasm = near_workspaces::compile_project("./").await?;
+    let challenge_contract = sandbox.dev_deploy(&contract_wasm).await?;
+    let duration_since_epoch = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    let timestamp_nanos = duration_since_epoch.as_nanos() as u64 + SECONDS_IN_DAY * NS_IN_SECONDS;
+    let outcome = user_account0
+        .call(challenge_contract.id(), "new_from_template")
+        .args_json(json!({
+            "template_id": template.id(),
+            "overrides": {
+                "name": "Overridden challenge",
+                "expiration_date_in_ns": timestamp_nanos,
+                "winner_limit": 3,
+            },
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(outcome.is_success());
+
+    let metadata: ChallengeMetaData = challenge_contract
+        .view("get_challenge_metadata")
+        .await?
+        .json()
+        .unwrap();
+    assert!(metadata.owner_id == user_account0.id().to_string());
+    assert!(metadata.name == "Overridden challenge");
+    assert!(metadata.description == "A challenge created from a template");
+    assert!(metadata.reward_nft_id == "reward-nft");
+    assert!(metadata.challenge_nft_ids == vec!["challenge-nft-1", "challenge-nft-2"]);
+    assert!(metadata.burn_challenge_piece_on_claim == vec![true, false]);
+    assert!(metadata.expiration_date_in_ns == timestamp_nanos);
+    assert!(metadata.winner_limit == 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_claim_pieces_on_one_contract() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = near_workspaces::sandbox().await?;
+    let user_account0 = sandbox.dev_create_account().await?;
+    let nft_ids = vec!["challenge-nft-1"];
+    let nfts = create_nfts(user_account0.id().clone(), nft_ids, &sandbox).await?;
+
+    // Both pieces live on the same contract.
+    let challenge_contract = create_challenge(
+        vec![nfts[0].id().to_string(), nfts[0].id().to_string()],
+        vec![false, false],
+        "reward-nft".to_string(),
+        1,
+        user_account0.id().clone(),
+        &sandbox,
+    )
+    .await?;
+
+    let metadata_call = challenge_contract.view("get_challenge_metadata").await?;
+    let metadata: ChallengeMetaData = metadata_call.json().unwrap();
+    let mint_outcome = user_account0
+        .call(nfts[0].id(), "nft_batch_mint")
+        .args_json(json!({
+            "owner_id": user_account0.id().clone(),
+            "metadata":metadata.reward_nft_metadata,
+            "num_to_mint": 2,
+            "royalty_args": None::<RoyaltyArgs>,
+            "split_owners": None::<SplitBetweenUnparsed>,
+        }))
+        .deposit(NearToken::from_near(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(mint_outcome.is_success());
+
+    let outcome = user_account0
+        .call(challenge_contract.id(), "initiate_claim")
+        .args_json(json!({}))
+        .max_gas()
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?;
+    assert!(outcome.json::<ClaimResult>()? == ClaimResult::Won);
+
+    // The ownership of both pieces is checked with a single call.
+    let ownership_calls = outcome
+        .outcomes()
+        .iter()
+        .filter(|receipt| &receipt.executor_id == nfts[0].id())
+        .count();
+    assert!(ownership_calls == 1);
+    Ok(())
+}